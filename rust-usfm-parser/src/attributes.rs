@@ -0,0 +1,110 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::char_markers::analyze_char_markers;
+use crate::diagnostics::Diagnostic;
+use crate::milestones::find_milestones;
+use crate::stylesheet::MarkerCatalog;
+
+static ATTRIBUTE_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"([A-Za-z][\w-]*)\s*=\s*"([^"]*)""#).unwrap());
+
+/// Reads an attribute list (the text after `|`). A lone value belongs to the
+/// marker's default attribute and is dropped if the marker has none.
+pub fn parse_attributes(
+    list: &str,
+    marker: &str,
+    catalog: &MarkerCatalog,
+) -> Vec<(String, String)> {
+    if list.contains('=') {
+        ATTRIBUTE_PATTERN
+            .captures_iter(list)
+            .map(|capture| (capture[1].to_string(), capture[2].to_string()))
+            .collect()
+    } else {
+        catalog
+            .default_attribute(marker)
+            .map(|name| vec![(name.to_string(), list.trim().to_string())])
+            .unwrap_or_default()
+    }
+}
+
+/// Checks the attributes of character markers and milestones against the
+/// `\Attributes` their stylesheet declares.
+pub fn check_attributes(usfm: &str, catalog: &MarkerCatalog) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for pair in analyze_char_markers(usfm, catalog).pairs {
+        let content = &usfm[pair.open.end_byte..pair.close.start_byte];
+        // The list belongs to this marker only if no nested marker follows it
+        let list = content
+            .rsplit_once('|')
+            .map(|(_, list)| list)
+            .filter(|list| !list.contains('\\'));
+        let attributes = match list {
+            // USFM 2 figures separate plain values by `|`
+            Some(list) if !list.contains('=') && content.matches('|').count() > 1 => continue,
+            Some(list)
+                if !list.contains('=')
+                    && catalog.default_attribute(&pair.open.marker).is_none() =>
+            {
+                diagnostics.push(Diagnostic::at_marker(
+                    "unknown-attribute",
+                    format!(
+                        "\\{} has no default attribute to take \"{}\"",
+                        pair.open.marker,
+                        list.trim()
+                    ),
+                    &pair.open,
+                ));
+                continue;
+            }
+            Some(list) => parse_attributes(list, &pair.open.marker, catalog),
+            None => Vec::new(),
+        };
+        for (rule, message) in check_marker(&pair.open.marker, &attributes, catalog) {
+            diagnostics.push(Diagnostic::at_marker(rule, message, &pair.open));
+        }
+    }
+
+    for milestone in find_milestones(usfm, catalog) {
+        for (rule, message) in check_marker(&milestone.marker, &milestone.attributes, catalog) {
+            diagnostics.push(milestone.diagnostic(rule, message));
+        }
+    }
+    diagnostics
+}
+
+// Rule ids and messages for attributes the marker does not define or lacks
+fn check_marker(
+    marker: &str,
+    attributes: &[(String, String)],
+    catalog: &MarkerCatalog,
+) -> Vec<(&'static str, String)> {
+    let mut problems = Vec::new();
+    let Some(info) = catalog.get(marker) else {
+        return problems;
+    };
+    for (name, _) in attributes {
+        // Custom (x-) and linking attributes may be used on any marker
+        let universal = name.starts_with("x-") || name.starts_with("link-");
+        if !universal && !info.attributes.iter().any(|spec| spec.name == *name) {
+            problems.push((
+                "unknown-attribute",
+                format!("\\{} does not define the attribute {}", marker, name),
+            ));
+        }
+    }
+    for spec in info.attributes.iter().filter(|spec| spec.required) {
+        if !attributes.iter().any(|(name, _)| *name == spec.name) {
+            problems.push((
+                "missing-attribute",
+                format!(
+                    "\\{} is missing the required attribute {}",
+                    marker, spec.name
+                ),
+            ));
+        }
+    }
+    problems
+}
@@ -0,0 +1,294 @@
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info_span};
+
+use crate::characters::normalize_usfm;
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::lint::LintConfig;
+use crate::stylesheet::MarkerCatalog;
+use crate::usj_generator::{write_usj_with, UsjOptions};
+use crate::validator::Validator;
+
+/// What a batch does with each file, and on how many threads.
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Worker threads; `None` uses one per core
+    pub threads: Option<usize>,
+    pub convert: bool,
+    pub lint: bool,
+    /// Normalize the USJ text to NFC, and keep each file's USFM in NFC for
+    /// [`BatchReport::write_usfm`]
+    pub nfc: bool,
+    pub lint_config: LintConfig,
+    pub catalog: MarkerCatalog,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            threads: None,
+            convert: true,
+            lint: false,
+            nfc: false,
+            lint_config: LintConfig::default(),
+            catalog: MarkerCatalog::usfm(),
+        }
+    }
+}
+
+/// What a batch gave for one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReport {
+    pub path: PathBuf,
+    /// The USJ, when converting succeeded
+    pub usj: Option<String>,
+    /// The USFM normalized to NFC, when the batch normalizes
+    pub usfm: Option<String>,
+    /// Lint findings, when linting
+    pub diagnostics: Vec<Diagnostic>,
+    /// Why the file could not be read, parsed or converted
+    pub error: Option<String>,
+}
+
+impl FileReport {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            usj: None,
+            usfm: None,
+            diagnostics: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+}
+
+/// Totals over the files of a batch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub files: usize,
+    pub converted: usize,
+    /// Files with an error
+    pub failed: usize,
+    /// Lint findings of error severity
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+/// The reports of a batch, in the order its files were given.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    pub files: Vec<FileReport>,
+}
+
+impl BatchReport {
+    pub fn summary(&self) -> BatchSummary {
+        let mut summary = BatchSummary {
+            files: self.files.len(),
+            ..BatchSummary::default()
+        };
+        for file in &self.files {
+            summary.converted += usize::from(file.usj.is_some());
+            summary.failed += usize::from(file.error.is_some());
+            summary.errors += file.count(Severity::Error);
+            summary.warnings += file.count(Severity::Warning);
+        }
+        summary
+    }
+
+    /// True when no file failed and lint found no errors.
+    pub fn is_success(&self) -> bool {
+        let summary = self.summary();
+        summary.failed == 0 && summary.errors == 0
+    }
+
+    /// Writes each converted file to `dir` as `<file stem>.json`, or as
+    /// `<folder>-<file stem>.json` when several files share a stem. Names
+    /// are compared ignoring case, and ones that still clash get `-2`, `-3`,
+    /// ... added.
+    pub fn write_usj<P: AsRef<Path>>(&self, dir: P) -> Result<(), String> {
+        self.write_files(dir.as_ref(), "json", |file| file.usj.as_deref())
+    }
+
+    /// Writes the NFC USFM of each file to `dir`, named as [`Self::write_usj`]
+    /// names USJ but with a `.usfm` extension.
+    pub fn write_usfm<P: AsRef<Path>>(&self, dir: P) -> Result<(), String> {
+        self.write_files(dir.as_ref(), "usfm", |file| file.usfm.as_deref())
+    }
+
+    fn write_files(
+        &self,
+        dir: &Path,
+        extension: &str,
+        content: impl Fn(&FileReport) -> Option<&str>,
+    ) -> Result<(), String> {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+        let written: Vec<_> = self
+            .files
+            .iter()
+            .filter_map(|file| Some((file, content(file)?)))
+            .collect();
+        let mut taken = HashSet::new();
+        for (file, text) in &written {
+            let stem = file.path.file_stem().unwrap_or_default();
+            let shared = written
+                .iter()
+                .filter(|(other, _)| {
+                    other
+                        .path
+                        .file_stem()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case(stem)
+                })
+                .count()
+                > 1;
+            let folder = file.path.parent().and_then(Path::file_name);
+            let base = match folder.filter(|_| shared) {
+                Some(folder) => format!("{}-{}", folder.to_string_lossy(), stem.to_string_lossy()),
+                None => stem.to_string_lossy().into_owned(),
+            };
+            // `a/x/GEN.usfm` and `b/x/GEN.usfm`, or `GEN.usfm` and `GEN.SFM`,
+            // would otherwise overwrite each other
+            let mut name = base.clone();
+            let mut index = 1;
+            while !taken.insert(name.to_lowercase()) {
+                index += 1;
+                name = format!("{}-{}", base, index);
+            }
+            let path = dir.join(format!("{}.{}", name, extension));
+            fs::write(&path, text)
+                .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for file in &self.files {
+            if let Some(error) = &file.error {
+                writeln!(f, "{}: {}", file.path.display(), error)?;
+            }
+            for diagnostic in &file.diagnostics {
+                writeln!(
+                    f,
+                    "{}: {}: {}",
+                    file.path.display(),
+                    diagnostic.severity,
+                    diagnostic
+                )?;
+            }
+        }
+        let summary = self.summary();
+        write!(
+            f,
+            "{} files, {} converted, {} failed, {} errors, {} warnings",
+            summary.files, summary.converted, summary.failed, summary.errors, summary.warnings
+        )
+    }
+}
+
+/// Converts and lints files concurrently. Each worker thread has its own
+/// parser and validator; reports come back in the order of `paths`.
+pub fn run_batch<P>(paths: &[P], options: &BatchOptions) -> Result<BatchReport, String>
+where
+    P: AsRef<Path> + Sync,
+{
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.threads.unwrap_or(0))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let files = pool.install(|| {
+        paths
+            .par_iter()
+            .map_init(
+                || worker_validator(options),
+                |validator, path| process_file(validator, path.as_ref(), options),
+            )
+            .collect()
+    });
+    Ok(BatchReport { files })
+}
+
+fn worker_validator(options: &BatchOptions) -> Result<Validator, String> {
+    let mut validator = Validator::new().map_err(|e| e.to_string())?;
+    validator.catalog = options.catalog.clone();
+    validator.lint_config = options.lint_config.clone();
+    Ok(validator)
+}
+
+fn process_file(
+    validator: &mut Result<Validator, String>,
+    path: &Path,
+    options: &BatchOptions,
+) -> FileReport {
+    let _span = info_span!("file", path = %path.display()).entered();
+    let mut report = FileReport::new(path);
+    let validator = match validator {
+        Ok(validator) => validator,
+        Err(e) => {
+            report.error = Some(e.clone());
+            return report;
+        }
+    };
+    let usfm = match fs::read_to_string(path) {
+        Ok(usfm) => usfm,
+        Err(e) => {
+            report.error = Some(format!("Cannot read {}: {}", path.display(), e));
+            return report;
+        }
+    };
+    if options.convert {
+        // Parsed without USFMParser::parse_usfm, which would share the global
+        // tree with every other worker. A book with syntax errors still
+        // converts; linting reports the errors.
+        match convert(validator, &usfm, options) {
+            Ok(usj) => report.usj = Some(usj),
+            Err(e) => report.error = Some(e),
+        }
+    }
+    if options.nfc {
+        report.usfm = Some(normalize_usfm(&usfm).into_owned());
+    }
+    if options.lint {
+        match validator.lint(&usfm) {
+            Ok(diagnostics) => report.diagnostics = diagnostics,
+            Err(e) => report.error = report.error.take().or(Some(e)),
+        }
+    }
+    debug!(
+        diagnostics = report.diagnostics.len(),
+        failed = report.error.is_some(),
+        "processed"
+    );
+    report
+}
+
+fn convert(
+    validator: &mut Validator,
+    usfm: &str,
+    options: &BatchOptions,
+) -> Result<String, String> {
+    let tree = validator
+        .parser
+        .parser
+        .parse(usfm, None)
+        .ok_or("Failed to parse the USFM string.")?;
+    let options = UsjOptions {
+        catalog: &options.catalog,
+        nfc: options.nfc,
+        ..UsjOptions::default()
+    };
+    let mut usj = Vec::new();
+    write_usj_with(&tree, usfm, &mut usj, options).map_err(|e| e.to_string())?;
+    String::from_utf8(usj).map_err(|e| e.to_string())
+}
@@ -0,0 +1,151 @@
+use crate::scanner::scan_markers;
+use crate::stylesheet::MarkerCatalog;
+use crate::verse_text::after_delimiter;
+
+/// A `\mt#` title line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Title {
+    /// `1` for `\mt` and `\mt1`, `2` for `\mt2` and so on
+    pub level: u8,
+    pub text: String,
+}
+
+/// The identification block and titles at the top of a book. Text has
+/// character markers, notes and attributes removed and whitespace collapsed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BookInfo {
+    /// The code after `\id`, as written
+    pub code: String,
+    /// The rest of the `\id` line
+    pub description: Option<String>,
+    /// `\usfm`
+    pub usfm_version: Option<String>,
+    /// `\ide`
+    pub encoding: Option<String>,
+    /// `\h`
+    pub header: Option<String>,
+    /// `\toc1`
+    pub long_name: Option<String>,
+    /// `\toc2`
+    pub short_name: Option<String>,
+    /// `\toc3`
+    pub abbreviation: Option<String>,
+    /// `\toca1`
+    pub alternate_long_name: Option<String>,
+    /// `\toca2`
+    pub alternate_short_name: Option<String>,
+    /// `\toca3`
+    pub alternate_abbreviation: Option<String>,
+    /// `\mt#` lines in order
+    pub titles: Vec<Title>,
+}
+
+impl BookInfo {
+    // Stores the text of one header paragraph; the first of a kind wins
+    fn set(&mut self, marker: &str, text: String) {
+        if marker == "id" {
+            if !self.code.is_empty() {
+                return;
+            }
+            let (code, description) = text.split_once(' ').unwrap_or((&text, ""));
+            self.code = code.to_string();
+            if !description.is_empty() {
+                self.description = Some(description.to_string());
+            }
+            return;
+        }
+        if let Some(level) = marker.strip_prefix("mt") {
+            let level = if level.is_empty() {
+                Some(1)
+            } else {
+                level.parse().ok()
+            };
+            if let (Some(level), false) = (level, text.is_empty()) {
+                self.titles.push(Title { level, text });
+            }
+            return;
+        }
+        let field = match marker {
+            "usfm" => &mut self.usfm_version,
+            "ide" => &mut self.encoding,
+            "h" => &mut self.header,
+            "toc1" => &mut self.long_name,
+            "toc2" => &mut self.short_name,
+            "toc3" => &mut self.abbreviation,
+            "toca1" => &mut self.alternate_long_name,
+            "toca2" => &mut self.alternate_short_name,
+            "toca3" => &mut self.alternate_abbreviation,
+            _ => return,
+        };
+        if field.is_none() && !text.is_empty() {
+            *field = Some(text);
+        }
+    }
+}
+
+/// Reads the header of a book, up to its first chapter or verse, without
+/// parsing the rest. `None` when there is no `\id`.
+pub fn book_info(usfm: &str, catalog: &MarkerCatalog) -> Option<BookInfo> {
+    let mut info: Option<BookInfo> = None;
+    // The header paragraph being read, with its text so far
+    let mut current: Option<(String, String)> = None;
+    // The note being skipped
+    let mut note: Option<String> = None;
+    let mut cursor = 0;
+    let mut end = usfm.len();
+
+    for token in scan_markers(usfm) {
+        if let (Some((_, text)), None) = (current.as_mut(), &note) {
+            push_text(text, &usfm[cursor.min(token.start_byte)..token.start_byte]);
+        }
+        cursor = after_delimiter(usfm, token.end_byte);
+        if token.closing {
+            if note.as_deref() == Some(token.marker.as_str()) {
+                note = None;
+            }
+            cursor = token.end_byte;
+            continue;
+        }
+        if note.is_some() {
+            continue;
+        }
+        match token.marker.as_str() {
+            "c" | "v" => {
+                end = token.start_byte;
+                break;
+            }
+            marker if catalog.is_note(marker) => note = Some(marker.to_string()),
+            marker if catalog.is_paragraph(marker) => {
+                finish(&mut info, current.take());
+                current = Some((marker.to_string(), String::new()));
+            }
+            _ => {}
+        }
+    }
+    if let (Some((_, text)), None) = (current.as_mut(), &note) {
+        push_text(text, &usfm[cursor.min(end)..end]);
+    }
+    finish(&mut info, current);
+    info
+}
+
+// Stores a finished header paragraph; `\id` starts the book
+fn finish(info: &mut Option<BookInfo>, paragraph: Option<(String, String)>) {
+    let Some((marker, text)) = paragraph else {
+        return;
+    };
+    if marker == "id" && info.is_none() {
+        *info = Some(BookInfo::default());
+    }
+    if let Some(info) = info {
+        info.set(
+            &marker,
+            text.split_whitespace().collect::<Vec<_>>().join(" "),
+        );
+    }
+}
+
+// Adds text between markers, leaving out attributes after `|`
+fn push_text(text: &mut String, run: &str) {
+    text.push_str(run.split('|').next().unwrap_or_default());
+}
@@ -0,0 +1,253 @@
+use Testament::{Deuterocanon, New, Old, Peripheral};
+
+/// The part of the canon a book belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Testament {
+    Old,
+    New,
+    /// Deuterocanonical and other books some churches include
+    Deuterocanon,
+    /// Front and back matter, glossaries, indexes and extra books
+    Peripheral,
+}
+
+/// Versification schemes, named as in Paratext, that number chapters
+/// differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Versification {
+    /// Hebrew and Greek originals
+    #[default]
+    Original,
+    Septuagint,
+    Vulgate,
+    English,
+    RussianProtestant,
+    RussianOrthodox,
+}
+
+impl Versification {
+    pub const ALL: [Versification; 6] = [
+        Versification::Original,
+        Versification::Septuagint,
+        Versification::Vulgate,
+        Versification::English,
+        Versification::RussianProtestant,
+        Versification::RussianOrthodox,
+    ];
+
+    /// Lower case name, as written in configuration files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Versification::Original => "original",
+            Versification::Septuagint => "septuagint",
+            Versification::Vulgate => "vulgate",
+            Versification::English => "english",
+            Versification::RussianProtestant => "russian-protestant",
+            Versification::RussianOrthodox => "russian-orthodox",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|versification| versification.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// A book of the USFM book list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Book {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub testament: Testament,
+    // Chapters in the Original versification
+    chapters: Option<u32>,
+}
+
+impl Book {
+    /// Number of chapters in a versification, if the book has a fixed number.
+    pub fn chapter_count(&self, versification: Versification) -> Option<u32> {
+        CHAPTER_DIFFERENCES
+            .iter()
+            .find(|(scheme, code, _)| *scheme == versification && *code == self.code)
+            .map(|(_, _, chapters)| *chapters)
+            .or(self.chapters)
+    }
+}
+
+const fn entry(
+    code: &'static str,
+    name: &'static str,
+    testament: Testament,
+    chapters: Option<u32>,
+) -> Book {
+    Book {
+        code,
+        name,
+        testament,
+        chapters,
+    }
+}
+
+/// Every USFM 3 book code, in the order of a printed Bible: front matter,
+/// the Old and New Testaments, the deuterocanon and then back matter and
+/// extra books.
+pub const BOOKS: &[Book] = &[
+    entry("FRT", "Front Matter", Peripheral, None),
+    entry("INT", "Introductions", Peripheral, None),
+    entry("GEN", "Genesis", Old, Some(50)),
+    entry("EXO", "Exodus", Old, Some(40)),
+    entry("LEV", "Leviticus", Old, Some(27)),
+    entry("NUM", "Numbers", Old, Some(36)),
+    entry("DEU", "Deuteronomy", Old, Some(34)),
+    entry("JOS", "Joshua", Old, Some(24)),
+    entry("JDG", "Judges", Old, Some(21)),
+    entry("RUT", "Ruth", Old, Some(4)),
+    entry("1SA", "1 Samuel", Old, Some(31)),
+    entry("2SA", "2 Samuel", Old, Some(24)),
+    entry("1KI", "1 Kings", Old, Some(22)),
+    entry("2KI", "2 Kings", Old, Some(25)),
+    entry("1CH", "1 Chronicles", Old, Some(29)),
+    entry("2CH", "2 Chronicles", Old, Some(36)),
+    entry("EZR", "Ezra", Old, Some(10)),
+    entry("NEH", "Nehemiah", Old, Some(13)),
+    entry("EST", "Esther", Old, Some(10)),
+    entry("JOB", "Job", Old, Some(42)),
+    entry("PSA", "Psalms", Old, Some(150)),
+    entry("PRO", "Proverbs", Old, Some(31)),
+    entry("ECC", "Ecclesiastes", Old, Some(12)),
+    entry("SNG", "Song of Songs", Old, Some(8)),
+    entry("ISA", "Isaiah", Old, Some(66)),
+    entry("JER", "Jeremiah", Old, Some(52)),
+    entry("LAM", "Lamentations", Old, Some(5)),
+    entry("EZK", "Ezekiel", Old, Some(48)),
+    entry("DAN", "Daniel", Old, Some(12)),
+    entry("HOS", "Hosea", Old, Some(14)),
+    entry("JOL", "Joel", Old, Some(4)),
+    entry("AMO", "Amos", Old, Some(9)),
+    entry("OBA", "Obadiah", Old, Some(1)),
+    entry("JON", "Jonah", Old, Some(4)),
+    entry("MIC", "Micah", Old, Some(7)),
+    entry("NAM", "Nahum", Old, Some(3)),
+    entry("HAB", "Habakkuk", Old, Some(3)),
+    entry("ZEP", "Zephaniah", Old, Some(3)),
+    entry("HAG", "Haggai", Old, Some(2)),
+    entry("ZEC", "Zechariah", Old, Some(14)),
+    entry("MAL", "Malachi", Old, Some(3)),
+    entry("MAT", "Matthew", New, Some(28)),
+    entry("MRK", "Mark", New, Some(16)),
+    entry("LUK", "Luke", New, Some(24)),
+    entry("JHN", "John", New, Some(21)),
+    entry("ACT", "Acts", New, Some(28)),
+    entry("ROM", "Romans", New, Some(16)),
+    entry("1CO", "1 Corinthians", New, Some(16)),
+    entry("2CO", "2 Corinthians", New, Some(13)),
+    entry("GAL", "Galatians", New, Some(6)),
+    entry("EPH", "Ephesians", New, Some(6)),
+    entry("PHP", "Philippians", New, Some(4)),
+    entry("COL", "Colossians", New, Some(4)),
+    entry("1TH", "1 Thessalonians", New, Some(5)),
+    entry("2TH", "2 Thessalonians", New, Some(3)),
+    entry("1TI", "1 Timothy", New, Some(6)),
+    entry("2TI", "2 Timothy", New, Some(4)),
+    entry("TIT", "Titus", New, Some(3)),
+    entry("PHM", "Philemon", New, Some(1)),
+    entry("HEB", "Hebrews", New, Some(13)),
+    entry("JAS", "James", New, Some(5)),
+    entry("1PE", "1 Peter", New, Some(5)),
+    entry("2PE", "2 Peter", New, Some(3)),
+    entry("1JN", "1 John", New, Some(5)),
+    entry("2JN", "2 John", New, Some(1)),
+    entry("3JN", "3 John", New, Some(1)),
+    entry("JUD", "Jude", New, Some(1)),
+    entry("REV", "Revelation", New, Some(22)),
+    entry("TOB", "Tobit", Deuterocanon, Some(14)),
+    entry("JDT", "Judith", Deuterocanon, Some(16)),
+    entry("ESG", "Esther (Greek)", Deuterocanon, Some(10)),
+    entry("WIS", "Wisdom of Solomon", Deuterocanon, Some(19)),
+    entry("SIR", "Sirach", Deuterocanon, Some(51)),
+    entry("BAR", "Baruch", Deuterocanon, Some(5)),
+    entry("LJE", "Letter of Jeremiah", Deuterocanon, Some(1)),
+    entry("S3Y", "Song of the Three Young Men", Deuterocanon, Some(1)),
+    entry("SUS", "Susanna", Deuterocanon, Some(1)),
+    entry("BEL", "Bel and the Dragon", Deuterocanon, Some(1)),
+    entry("1MA", "1 Maccabees", Deuterocanon, Some(16)),
+    entry("2MA", "2 Maccabees", Deuterocanon, Some(15)),
+    entry("3MA", "3 Maccabees", Deuterocanon, Some(7)),
+    entry("4MA", "4 Maccabees", Deuterocanon, Some(18)),
+    entry("1ES", "1 Esdras", Deuterocanon, Some(9)),
+    entry("2ES", "2 Esdras", Deuterocanon, Some(16)),
+    entry("MAN", "Prayer of Manasseh", Deuterocanon, Some(1)),
+    entry("PS2", "Psalm 151", Deuterocanon, Some(1)),
+    entry("ODA", "Odes", Deuterocanon, Some(14)),
+    entry("PSS", "Psalms of Solomon", Deuterocanon, Some(18)),
+    entry("EZA", "Ezra Apocalypse", Deuterocanon, Some(12)),
+    entry("5EZ", "5 Ezra", Deuterocanon, Some(2)),
+    entry("6EZ", "6 Ezra", Deuterocanon, Some(2)),
+    entry("DAG", "Daniel (Greek)", Deuterocanon, Some(14)),
+    entry("PS3", "Psalms 152-155", Deuterocanon, None),
+    entry("2BA", "2 Baruch", Deuterocanon, None),
+    entry("LBA", "Letter of Baruch", Deuterocanon, None),
+    entry("JUB", "Jubilees", Deuterocanon, Some(50)),
+    entry("ENO", "Enoch", Deuterocanon, None),
+    entry("1MQ", "1 Meqabyan", Deuterocanon, None),
+    entry("2MQ", "2 Meqabyan", Deuterocanon, None),
+    entry("3MQ", "3 Meqabyan", Deuterocanon, None),
+    entry("REP", "Reproof", Deuterocanon, None),
+    entry("4BA", "4 Baruch", Deuterocanon, None),
+    entry("LAO", "Letter to the Laodiceans", Deuterocanon, Some(1)),
+    entry("BAK", "Back Matter", Peripheral, None),
+    entry("OTH", "Other Matter", Peripheral, None),
+    entry("CNC", "Concordance", Peripheral, None),
+    entry("GLO", "Glossary", Peripheral, None),
+    entry("TDX", "Topical Index", Peripheral, None),
+    entry("NDX", "Names Index", Peripheral, None),
+    entry("XXA", "Extra Book A", Peripheral, None),
+    entry("XXB", "Extra Book B", Peripheral, None),
+    entry("XXC", "Extra Book C", Peripheral, None),
+    entry("XXD", "Extra Book D", Peripheral, None),
+    entry("XXE", "Extra Book E", Peripheral, None),
+    entry("XXF", "Extra Book F", Peripheral, None),
+    entry("XXG", "Extra Book G", Peripheral, None),
+];
+
+// Chapter counts that differ from the Original versification
+const CHAPTER_DIFFERENCES: &[(Versification, &str, u32)] = &[
+    (Versification::Septuagint, "PSA", 151),
+    (Versification::Vulgate, "EST", 16),
+    (Versification::Vulgate, "DAN", 14),
+    (Versification::Vulgate, "JOL", 3),
+    (Versification::Vulgate, "MAL", 4),
+    (Versification::English, "JOL", 3),
+    (Versification::English, "MAL", 4),
+    (Versification::RussianProtestant, "JOL", 3),
+    (Versification::RussianProtestant, "MAL", 4),
+    (Versification::RussianOrthodox, "PSA", 151),
+    (Versification::RussianOrthodox, "JOL", 3),
+    (Versification::RussianOrthodox, "MAL", 4),
+];
+
+/// The book with a code, which must be in upper case.
+pub fn book(code: &str) -> Option<&'static Book> {
+    BOOKS.iter().find(|book| book.code == code)
+}
+
+pub fn is_book_code(code: &str) -> bool {
+    book(code).is_some()
+}
+
+/// The English name of a book code.
+pub fn book_name(code: &str) -> Option<&'static str> {
+    book(code).map(|book| book.name)
+}
+
+/// Position of a book code in [`BOOKS`].
+pub fn canonical_order(code: &str) -> Option<usize> {
+    BOOKS.iter().position(|book| book.code == code)
+}
+
+/// Sorts items by the canonical order of their book codes, keeping the
+/// order of items from the same book. Unknown codes go last.
+pub fn sort_by_canon<T>(items: &mut [T], code: impl Fn(&T) -> &str) {
+    items.sort_by_key(|item| canonical_order(code(item)).unwrap_or(BOOKS.len()));
+}
@@ -0,0 +1,399 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::books::{book, Versification};
+use crate::characters::normalize_usfm;
+use crate::project::{Project, ProjectBook};
+use crate::reference::VerseNumber;
+use crate::scanner::{scan_markers, word_after};
+
+/// Name of the metadata file at the root of a burrito.
+pub const METADATA_FILE: &str = "metadata.json";
+pub const USFM_MIME_TYPE: &str = "text/x-usfm";
+pub const USX_MIME_TYPE: &str = "text/x-usx";
+
+// Version of the Scripture Burrito schema generated metadata follows
+const BURRITO_VERSION: &str = "1.0.0";
+/// Id authority that generated metadata identifies projects under.
+pub const ID_AUTHORITY: &str = "usfmgrammar";
+const ID_AUTHORITY_URL: &str = "https://usfmgrammar.vachanengine.org/";
+
+/// Books mapped to the chapters and verses they cover. An empty list
+/// stands for the whole book.
+pub type Scope = BTreeMap<String, Vec<String>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checksum {
+    pub md5: String,
+}
+
+/// One entry of a burrito's `ingredients`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ingredient {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<Checksum>,
+    pub mime_type: String,
+    /// Size in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Scope>,
+}
+
+impl Ingredient {
+    /// Describes the contents of a USFM file.
+    pub fn usfm(code: &str, usfm: &str) -> Self {
+        Self {
+            checksum: Some(Checksum {
+                md5: format!("{:x}", md5::compute(usfm.as_bytes())),
+            }),
+            mime_type: USFM_MIME_TYPE.to_string(),
+            size: Some(usfm.len() as u64),
+            scope: Some(Scope::from([(code.to_string(), book_scope(code, usfm))])),
+        }
+    }
+}
+
+/// A burrito folder and its `metadata.json`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Burrito {
+    pub dir: PathBuf,
+    /// The whole metadata document, as read
+    pub metadata: Value,
+    /// Ingredients by path relative to `dir`
+    pub ingredients: BTreeMap<String, Ingredient>,
+}
+
+impl Burrito {
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, String> {
+        let dir = dir.as_ref().to_path_buf();
+        let path = dir.join(METADATA_FILE);
+        let json = fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Self::from_metadata(dir, &json)
+    }
+
+    pub fn from_metadata(dir: PathBuf, json: &str) -> Result<Self, String> {
+        let metadata: Value =
+            serde_json::from_str(json).map_err(|e| format!("Invalid {}: {}", METADATA_FILE, e))?;
+        if metadata["format"] != "scripture burrito" {
+            return Err(format!(
+                "{} is not Scripture Burrito metadata",
+                METADATA_FILE
+            ));
+        }
+        let ingredients = match metadata.get("ingredients") {
+            Some(ingredients) => serde_json::from_value(ingredients.clone())
+                .map_err(|e| format!("Invalid ingredients in {}: {}", METADATA_FILE, e))?,
+            None => BTreeMap::new(),
+        };
+        Ok(Self {
+            dir,
+            metadata,
+            ingredients,
+        })
+    }
+
+    /// The name in the default locale, or the first one given.
+    pub fn name(&self) -> Option<&str> {
+        let names = self.metadata["identification"]["name"].as_object()?;
+        self.metadata["meta"]["defaultLocale"]
+            .as_str()
+            .and_then(|locale| names.get(locale))
+            .or_else(|| names.values().next())
+            .and_then(Value::as_str)
+    }
+
+    /// Paths of the USFM ingredients, by MIME type or else by extension.
+    pub fn usfm_ingredients(&self) -> Vec<PathBuf> {
+        self.ingredient_paths(USFM_MIME_TYPE, &["usfm", "sfm"])
+    }
+
+    pub fn usx_ingredients(&self) -> Vec<PathBuf> {
+        self.ingredient_paths(USX_MIME_TYPE, &["usx"])
+    }
+
+    fn ingredient_paths(&self, mime_type: &str, extensions: &[&str]) -> Vec<PathBuf> {
+        self.ingredients
+            .iter()
+            .filter(|(path, ingredient)| {
+                ingredient.mime_type == mime_type
+                    || Path::new(path)
+                        .extension()
+                        .and_then(|ext| ext.to_str())
+                        .is_some_and(|ext| {
+                            extensions
+                                .iter()
+                                .any(|extension| ext.eq_ignore_ascii_case(extension))
+                        })
+            })
+            .map(|(path, _)| self.dir.join(path))
+            .collect()
+    }
+
+    /// Loads the USFM ingredients as a project.
+    pub fn project(&self) -> Result<Project, String> {
+        Project::from_files(self.usfm_ingredients())
+    }
+}
+
+/// What metadata generated for a project says about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BurritoOptions {
+    pub name: String,
+    pub abbreviation: Option<String>,
+    /// BCP 47 tag, e.g. `en`
+    pub language_tag: String,
+    pub language_name: String,
+    pub copyright: Option<String>,
+    /// RFC 3339 date and time, e.g. `2024-05-01T09:30:00Z`; now when not given
+    pub date_created: Option<String>,
+    /// The project's id under [`ID_AUTHORITY`]; the MD5 of the name when
+    /// not given
+    pub id: Option<String>,
+}
+
+/// The path a book is exported to, relative to the burrito folder.
+pub fn ingredient_path(book: &ProjectBook) -> String {
+    format!("ingredients/{}.usfm", book.code)
+}
+
+/// Metadata for a text translation burrito holding the project's books,
+/// described as [`export_burrito`] writes them.
+pub fn burrito_metadata(project: &Project, options: &BurritoOptions) -> Value {
+    let locale = options.language_tag.as_str();
+    let mut ingredients = Map::new();
+    let mut current_scope = Map::new();
+    let mut localized_names = Map::new();
+    for project_book in &project.books {
+        let usfm = normalize_usfm(&project_book.usfm);
+        let ingredient = Ingredient::usfm(&project_book.code, &usfm);
+        for (code, scope) in ingredient.scope.iter().flatten() {
+            current_scope.insert(code.clone(), json!(scope));
+        }
+        ingredients.insert(ingredient_path(project_book), json!(ingredient));
+
+        // Burritos need all three names, so a missing one takes another's
+        let info = &project_book.info;
+        let given = [&info.short_name, &info.abbreviation, &info.long_name];
+        if let Some(fallback) = given.into_iter().flatten().next() {
+            let mut names = Map::new();
+            for (key, name) in ["short", "abbr", "long"].into_iter().zip(given) {
+                let name = name.as_ref().unwrap_or(fallback);
+                names.insert(key.to_string(), json!({ locale: name }));
+            }
+            let key = format!("book-{}", project_book.code.to_lowercase());
+            localized_names.insert(key, Value::Object(names));
+        }
+    }
+
+    let date_created = options.date_created.clone().unwrap_or_else(now);
+    let id = options
+        .id
+        .clone()
+        .unwrap_or_else(|| format!("{:x}", md5::compute(options.name.as_bytes())));
+    let mut identification = json!({
+        "name": { locale: options.name },
+        "primary": {
+            ID_AUTHORITY: { id: { "revision": "1", "timestamp": date_created } },
+        },
+    });
+    if let Some(abbreviation) = &options.abbreviation {
+        identification["abbreviation"] = json!({ locale: abbreviation });
+    }
+    let mut copyright = json!({});
+    if let Some(statement) = &options.copyright {
+        copyright["shortStatements"] =
+            json!([{ "statement": statement, "mimetype": "text/plain", "lang": locale }]);
+    }
+
+    json!({
+        "format": "scripture burrito",
+        "meta": {
+            "version": BURRITO_VERSION,
+            "category": "source",
+            "generator": {
+                "softwareName": env!("CARGO_PKG_NAME"),
+                "softwareVersion": env!("CARGO_PKG_VERSION"),
+            },
+            "defaultLocale": locale,
+            "dateCreated": date_created,
+            "normalization": "NFC",
+        },
+        "idAuthorities": {
+            ID_AUTHORITY: {
+                "id": ID_AUTHORITY_URL,
+                "name": { "en": "USFM Grammar" },
+            },
+        },
+        "identification": identification,
+        "languages": [{
+            "tag": locale,
+            "name": { locale: options.language_name },
+        }],
+        "type": {
+            "flavorType": {
+                "name": "scripture",
+                "flavor": {
+                    "name": "textTranslation",
+                    "usfmVersion": "3.0",
+                    "translationType": "firstTranslation",
+                    "audience": "common",
+                    "projectType": "standard",
+                },
+                "currentScope": current_scope,
+            },
+        },
+        "confidential": false,
+        "copyright": copyright,
+        "ingredients": ingredients,
+        "localizedNames": localized_names,
+    })
+}
+
+/// Writes the project's books, normalized to NFC, and their `metadata.json`
+/// to a folder, returning the metadata.
+pub fn export_burrito<P: AsRef<Path>>(
+    project: &Project,
+    dir: P,
+    options: &BurritoOptions,
+) -> Result<Value, String> {
+    let dir = dir.as_ref();
+    let metadata = burrito_metadata(project, options);
+    for project_book in &project.books {
+        let path = dir.join(ingredient_path(project_book));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+        }
+        fs::write(&path, normalize_usfm(&project_book.usfm).as_bytes())
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    }
+    let path = dir.join(METADATA_FILE);
+    let json = serde_json::to_string_pretty(&metadata).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(metadata)
+}
+
+/// The chapters and verses a book covers, as burrito scope entries: `[]`
+/// for a whole book, otherwise chapters (`1-3`) and verses (`4:1-10`).
+/// A chapter counts as whole when its verses run from 1 without gaps.
+pub fn book_scope(code: &str, usfm: &str) -> Vec<String> {
+    let mut chapters: BTreeMap<u32, BTreeSet<u32>> = BTreeMap::new();
+    let mut chapter = None;
+    for token in scan_markers(usfm) {
+        if token.closing {
+            continue;
+        }
+        match token.marker.as_str() {
+            "c" => {
+                chapter = word_after(usfm, &token).parse().ok();
+                if let Some(number) = chapter {
+                    chapters.entry(number).or_default();
+                }
+            }
+            "v" => {
+                if let (Some(number), Ok(verse)) =
+                    (chapter, VerseNumber::parse(word_after(usfm, &token)))
+                {
+                    chapters.entry(number).or_default().extend(verse.verses());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let whole = |verses: &BTreeSet<u32>| {
+        verses.is_empty() || verses.last().copied() == Some(verses.len() as u32)
+    };
+    let last = chapters.keys().last().copied();
+    let complete_book = chapters.values().all(whole)
+        && last == Some(chapters.len() as u32)
+        && book(code).is_some_and(|book| {
+            Versification::ALL
+                .iter()
+                .any(|versification| book.chapter_count(*versification) == last)
+        });
+    if chapters.is_empty() || complete_book {
+        return Vec::new();
+    }
+
+    let mut scope = Vec::new();
+    // The run of whole chapters being collected
+    let mut run: Option<(u32, u32)> = None;
+    for (number, verses) in &chapters {
+        if whole(verses) {
+            run = match run {
+                Some((first, previous)) if previous + 1 == *number => Some((first, *number)),
+                _ => {
+                    scope.extend(run.map(|(first, end)| span(first, end)));
+                    Some((*number, *number))
+                }
+            };
+            continue;
+        }
+        scope.extend(run.take().map(|(first, end)| span(first, end)));
+        for (first, end) in runs(verses) {
+            scope.push(format!("{}:{}", number, span(first, end)));
+        }
+    }
+    scope.extend(run.map(|(first, end)| span(first, end)));
+    scope
+}
+
+// Consecutive numbers grouped into first and last
+fn runs(numbers: &BTreeSet<u32>) -> Vec<(u32, u32)> {
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for number in numbers {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == *number => *end = *number,
+            _ => runs.push((*number, *number)),
+        }
+    }
+    runs
+}
+
+fn span(first: u32, end: u32) -> String {
+    if first == end {
+        first.to_string()
+    } else {
+        format!("{}-{}", first, end)
+    }
+}
+
+// The current UTC time as YYYY-MM-DDThh:mm:ssZ
+fn now() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let time = seconds % 86_400;
+    // Days to a civil date, after Howard Hinnant's algorithm
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time / 60 % 60,
+        time % 60
+    )
+}
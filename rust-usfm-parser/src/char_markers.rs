@@ -0,0 +1,233 @@
+use crate::diagnostics::Diagnostic;
+use crate::scanner::{scan_markers, word_after, MarkerToken};
+use crate::stylesheet::{MarkerCatalog, StyleType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairingIssueKind {
+    /// Opened but not closed before the end of its paragraph, cell or note
+    Unclosed,
+    /// Closed without having been opened
+    StrayClosing,
+    /// Closed while a marker opened inside it is still open (`\add \+nd ..\add*`)
+    Crossed,
+    /// Closed, but only after the following verse number
+    SpansVerse,
+}
+
+/// An opening marker together with the closing marker that ends it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharMarkerPair {
+    pub open: MarkerToken,
+    pub close: MarkerToken,
+}
+
+impl PairingIssueKind {
+    /// The lint rule reporting this kind of issue.
+    pub fn rule(&self) -> &'static str {
+        match self {
+            PairingIssueKind::Unclosed => "unclosed-marker",
+            PairingIssueKind::StrayClosing => "stray-closing-marker",
+            PairingIssueKind::Crossed => "crossed-markers",
+            PairingIssueKind::SpansVerse => "marker-spans-verse",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairingIssue {
+    pub kind: PairingIssueKind,
+    /// The opener for unclosed and verse spanning markers, otherwise the closer
+    pub token: MarkerToken,
+    pub message: String,
+    /// Byte offset at which the missing closer belongs
+    pub suggested_closer: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CharMarkerAnalysis {
+    pub pairs: Vec<CharMarkerPair>,
+    pub issues: Vec<PairingIssue>,
+}
+
+impl CharMarkerAnalysis {
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.issues
+            .iter()
+            .map(|issue| {
+                Diagnostic::at_marker(issue.kind.rule(), issue.message.clone(), &issue.token)
+            })
+            .collect()
+    }
+}
+
+struct OpenMarker {
+    token: MarkerToken,
+    is_note: bool,
+    // Set when a verse number follows while the marker is open: where the
+    // closer belongs and how to describe that place
+    verse_end: Option<(usize, String)>,
+}
+
+/// Pairs the opening and closing character and note markers of a document,
+/// `+` nested forms included. Character markers are closed implicitly at the end
+/// of a paragraph or table cell and must not run on into the next verse, so
+/// those boundaries end the search for a closer.
+pub fn analyze_char_markers(usfm: &str, catalog: &MarkerCatalog) -> CharMarkerAnalysis {
+    let mut analysis = CharMarkerAnalysis::default();
+    let mut open: Vec<OpenMarker> = Vec::new();
+    let mut verse = String::new();
+
+    for token in scan_markers(usfm) {
+        let Some(info) = catalog.get(&token.marker) else {
+            continue;
+        };
+        let in_note = open.iter().any(|marker| marker.is_note);
+        // \ft, \fq, \xt ... inside a note end at the next one and need no closer
+        if in_note && catalog.is_note_content(&token.marker) {
+            continue;
+        }
+        if token.closing {
+            close_marker(&mut analysis, &mut open, token);
+            continue;
+        }
+        match info.style_type {
+            Some(StyleType::Paragraph) => {
+                let end = trimmed_end(usfm, token.start_byte);
+                close_all(&mut analysis, &mut open, end, "at the end of the paragraph");
+            }
+            Some(StyleType::Note) => open.push(OpenMarker {
+                token,
+                is_note: true,
+                verse_end: None,
+            }),
+            Some(StyleType::Character) if info.marker == "v" => {
+                let next = word_after(usfm, &token).to_string();
+                let place = if verse.is_empty() {
+                    format!("before \\v {}", next)
+                } else {
+                    format!("at the end of verse {}", verse)
+                };
+                let end = trimmed_end(usfm, token.start_byte);
+                for marker in open.iter_mut().filter(|marker| marker.verse_end.is_none()) {
+                    marker.verse_end = Some((end, place.clone()));
+                }
+                verse = next;
+            }
+            Some(StyleType::Character) if info.occurs_under.iter().any(|p| p == "tr") => {
+                let end = trimmed_end(usfm, token.start_byte);
+                close_all(&mut analysis, &mut open, end, "at the end of the cell");
+            }
+            Some(StyleType::Character) if info.endmarker.is_some() => open.push(OpenMarker {
+                token,
+                is_note: false,
+                verse_end: None,
+            }),
+            _ => {}
+        }
+    }
+    let end = trimmed_end(usfm, usfm.len());
+    close_all(&mut analysis, &mut open, end, "at the end of the paragraph");
+    analysis
+}
+
+fn close_marker(analysis: &mut CharMarkerAnalysis, open: &mut Vec<OpenMarker>, token: MarkerToken) {
+    let Some(index) = open
+        .iter()
+        .rposition(|marker| marker.token.marker == token.marker)
+    else {
+        analysis.issues.push(PairingIssue {
+            kind: PairingIssueKind::StrayClosing,
+            message: format!(
+                "{} has no matching \\{}",
+                written_closer(&token),
+                token.marker
+            ),
+            token,
+            suggested_closer: None,
+        });
+        return;
+    };
+
+    if open[index].is_note {
+        // Nothing opened inside a note survives its end
+        let inner: Vec<OpenMarker> = open.drain(index + 1..).collect();
+        let place = format!("before {}", written_closer(&token));
+        for marker in inner {
+            report_unclosed(analysis, marker, token.start_byte, &place);
+        }
+    } else {
+        for marker in &open[index + 1..] {
+            analysis.issues.push(PairingIssue {
+                kind: PairingIssueKind::Crossed,
+                token: token.clone(),
+                message: format!(
+                    "{} closes \\{} while {} opened inside it is still open; add {} before it",
+                    written_closer(&token),
+                    token.marker,
+                    written_opener(&marker.token),
+                    written_closer(&marker.token)
+                ),
+                suggested_closer: Some(token.start_byte),
+            });
+        }
+    }
+
+    let opened = open.remove(index);
+    if let Some((end, place)) = opened.verse_end {
+        analysis.issues.push(PairingIssue {
+            kind: PairingIssueKind::SpansVerse,
+            token: opened.token.clone(),
+            message: format!(
+                "{} runs on into the next verse; add {} {}",
+                written_opener(&opened.token),
+                written_closer(&opened.token),
+                place
+            ),
+            suggested_closer: Some(end),
+        });
+    }
+    analysis.pairs.push(CharMarkerPair {
+        open: opened.token,
+        close: token,
+    });
+}
+
+fn close_all(
+    analysis: &mut CharMarkerAnalysis,
+    open: &mut Vec<OpenMarker>,
+    end: usize,
+    place: &str,
+) {
+    for marker in open.drain(..) {
+        report_unclosed(analysis, marker, end, place);
+    }
+}
+
+fn report_unclosed(analysis: &mut CharMarkerAnalysis, marker: OpenMarker, end: usize, place: &str) {
+    let (end, place) = marker.verse_end.unwrap_or_else(|| (end, place.to_string()));
+    analysis.issues.push(PairingIssue {
+        kind: PairingIssueKind::Unclosed,
+        message: format!(
+            "{} is not closed; add {} {}",
+            written_opener(&marker.token),
+            written_closer(&marker.token),
+            place
+        ),
+        token: marker.token,
+        suggested_closer: Some(end),
+    });
+}
+
+fn written_opener(token: &MarkerToken) -> String {
+    format!("\\{}{}", if token.nested { "+" } else { "" }, token.marker)
+}
+
+// For an opener, the closer that matches it, keeping its `+` form
+fn written_closer(token: &MarkerToken) -> String {
+    format!("{}*", written_opener(token))
+}
+
+// Closers go right after the text, not after the line break before the next marker
+fn trimmed_end(usfm: &str, byte: usize) -> usize {
+    usfm[..byte].trim_end().len()
+}
@@ -0,0 +1,386 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use unicode_general_category::{get_general_category, GeneralCategory};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::books::sort_by_canon;
+use crate::diagnostics::Diagnostic;
+use crate::scanner::{point_at, scan_markers, word_after};
+use crate::stylesheet::MarkerCatalog;
+use crate::verse_text::{after_delimiter, after_milestone, end_of_word};
+
+// References kept per character in an inventory
+const SAMPLE_REFERENCES: usize = 3;
+
+/// One character of an inventory, with where it was first seen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharacterEntry {
+    pub character: char,
+    /// Unicode name, e.g. `LATIN SMALL LETTER A`
+    pub name: String,
+    pub count: usize,
+    /// The first few distinct references it occurs in, in canonical order,
+    /// e.g. `GEN 1:1`
+    pub references: Vec<String>,
+}
+
+impl CharacterEntry {
+    /// `U+0041` style code point.
+    pub fn code_point(&self) -> String {
+        code_point(self.character)
+    }
+}
+
+/// The characters used in the text of one or more books: everything except
+/// markup (markers, attributes, numbers, note callers) and ordinary whitespace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CharacterInventory {
+    entries: BTreeMap<char, CharacterEntry>,
+}
+
+impl CharacterInventory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the characters of a book; call it once per book of a project.
+    pub fn add_book(&mut self, usfm: &str, catalog: &MarkerCatalog) {
+        for run in text_runs(usfm, catalog) {
+            for c in usfm[run.start..run.end].chars() {
+                if matches!(c, ' ' | '\t' | '\n' | '\r') {
+                    continue;
+                }
+                let entry = self.entries.entry(c).or_insert_with(|| CharacterEntry {
+                    character: c,
+                    name: character_name(c),
+                    count: 0,
+                    references: Vec::new(),
+                });
+                entry.count += 1;
+                if !entry.references.contains(&run.reference) {
+                    // Books may be added in any order; keep the first
+                    // references in canonical order
+                    entry.references.push(run.reference.clone());
+                    sort_by_canon(&mut entry.references, |reference| {
+                        reference.split(' ').next().unwrap_or_default()
+                    });
+                    entry.references.truncate(SAMPLE_REFERENCES);
+                }
+            }
+        }
+    }
+
+    /// Entries in code point order.
+    pub fn entries(&self) -> impl Iterator<Item = &CharacterEntry> {
+        self.entries.values()
+    }
+
+    pub fn get(&self, c: char) -> Option<&CharacterEntry> {
+        self.entries.get(&c)
+    }
+
+    /// Entries for characters the set does not allow.
+    pub fn outside<'a>(
+        &'a self,
+        allowed: &'a CharacterSet,
+    ) -> impl Iterator<Item = &'a CharacterEntry> {
+        self.entries()
+            .filter(|entry| !allowed.contains(entry.character))
+    }
+}
+
+/// Characters a project allows, written as a list such as `a-zA-Z.,;:’`
+/// where `x-y` is a range. A `-` of its own goes first or last.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CharacterSet {
+    ranges: Vec<(char, char)>,
+}
+
+impl CharacterSet {
+    pub fn parse(list: &str) -> Self {
+        let chars: Vec<char> = list.chars().collect();
+        let mut ranges = Vec::new();
+        let mut index = 0;
+        while index < chars.len() {
+            match chars.get(index + 1..index + 3) {
+                Some(&['-', last]) => {
+                    let first = chars[index];
+                    ranges.push((first.min(last), first.max(last)));
+                    index += 3;
+                }
+                _ => {
+                    ranges.push((chars[index], chars[index]));
+                    index += 1;
+                }
+            }
+        }
+        Self { ranges }
+    }
+
+    /// Whether the set has the character; whitespace is always allowed.
+    pub fn contains(&self, c: char) -> bool {
+        c.is_whitespace()
+            || self
+                .ranges
+                .iter()
+                .any(|&(first, last)| (first..=last).contains(&c))
+    }
+}
+
+/// The text in Unicode Normalization Form C, borrowed when it already is.
+pub fn to_nfc(text: &str) -> Cow<'_, str> {
+    if is_nfc(text) {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(text.nfc().collect())
+    }
+}
+
+/// USFM with the text between markers normalized to NFC. Markers are left
+/// as written, so a combining mark after one cannot change its name.
+pub fn normalize_usfm(usfm: &str) -> Cow<'_, str> {
+    if is_nfc(usfm) {
+        return Cow::Borrowed(usfm);
+    }
+    let mut normalized = String::with_capacity(usfm.len());
+    let mut copied = 0;
+    for token in scan_markers(usfm) {
+        normalized.extend(usfm[copied..token.start_byte].nfc());
+        normalized.push_str(&usfm[token.start_byte..token.end_byte]);
+        copied = token.end_byte;
+    }
+    normalized.extend(usfm[copied..].nfc());
+    Cow::Owned(normalized)
+}
+
+/// Flags invisible and control characters, characters outside the allowed
+/// set (if there is one), words not in NFC and books that mix composed and
+/// decomposed forms.
+pub fn check_characters(
+    usfm: &str,
+    catalog: &MarkerCatalog,
+    allowed: Option<&CharacterSet>,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    // First occurrence and count of each character outside the allowed set
+    let mut disallowed: BTreeMap<char, (usize, usize)> = BTreeMap::new();
+    // First composed and first decomposed word, with their references
+    let mut composed: Option<(usize, usize, String)> = None;
+    let mut decomposed: Option<(usize, usize, String)> = None;
+
+    for run in text_runs(usfm, catalog) {
+        let text = &usfm[run.start..run.end];
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        for (index, &(offset, c)) in chars.iter().enumerate() {
+            let byte = run.start + offset;
+            let after_letter = index > 0 && is_letter_or_mark(chars[index - 1].1);
+            // A byte order mark may start the file
+            if c == '\u{feff}' && byte == 0 {
+                continue;
+            }
+            if is_invisible(c) && !(is_joiner(c) && after_letter) {
+                diagnostics.push(diagnostic(
+                    usfm,
+                    "invisible-character",
+                    format!(
+                        "Invisible character {} {} in {}",
+                        code_point(c),
+                        character_name(c),
+                        run.reference
+                    ),
+                    byte,
+                    byte + c.len_utf8(),
+                ));
+            } else if allowed.is_some_and(|allowed| !allowed.contains(c)) {
+                disallowed.entry(c).or_insert((byte, 0)).1 += 1;
+            }
+        }
+
+        for (offset, word) in words(text) {
+            let start = run.start + offset;
+            let end = start + word.len();
+            if !is_nfc(word) {
+                diagnostics.push(diagnostic(
+                    usfm,
+                    "non-nfc-text",
+                    format!("\"{}\" in {} is not in NFC", word, run.reference),
+                    start,
+                    end,
+                ));
+                decomposed.get_or_insert((start, end, run.reference.clone()));
+            } else if word.nfd().ne(word.chars()) {
+                composed.get_or_insert((start, end, run.reference.clone()));
+            }
+        }
+    }
+
+    for (c, (byte, count)) in disallowed {
+        diagnostics.push(diagnostic(
+            usfm,
+            "disallowed-character",
+            format!(
+                "Character {} {} is not in the allowed set ({} {})",
+                code_point(c),
+                character_name(c),
+                count,
+                if count == 1 {
+                    "occurrence"
+                } else {
+                    "occurrences"
+                }
+            ),
+            byte,
+            byte + c.len_utf8(),
+        ));
+    }
+    if let (Some(composed), Some(decomposed)) = (composed, decomposed) {
+        // Reported where the second form first appears
+        let (start, end, _) = if composed.0 < decomposed.0 {
+            &decomposed
+        } else {
+            &composed
+        };
+        diagnostics.push(diagnostic(
+            usfm,
+            "mixed-normalization",
+            format!(
+                "Text mixes composed (\"{}\" in {}) and decomposed (\"{}\" in {}) characters",
+                &usfm[composed.0..composed.1],
+                composed.2,
+                &usfm[decomposed.0..decomposed.1],
+                decomposed.2
+            ),
+            *start,
+            *end,
+        ));
+    }
+    diagnostics
+}
+
+fn diagnostic(
+    usfm: &str,
+    rule: &'static str,
+    message: String,
+    start: usize,
+    end: usize,
+) -> Diagnostic {
+    let (row, column) = point_at(usfm, start);
+    Diagnostic::new(rule, message, start, end, row, column)
+}
+
+fn code_point(c: char) -> String {
+    format!("U+{:04X}", c as u32)
+}
+
+fn character_name(c: char) -> String {
+    match unicode_names2::name(c) {
+        Some(name) => name.to_string(),
+        None if c.is_control() => "<control>".to_string(),
+        None => "<unnamed>".to_string(),
+    }
+}
+
+// Control and format characters other than tabs and line breaks, which
+// display as nothing or look like an ordinary space
+fn is_invisible(c: char) -> bool {
+    !matches!(c, '\t' | '\n' | '\r')
+        && (matches!(
+            get_general_category(c),
+            GeneralCategory::Control | GeneralCategory::Format
+        ) || matches!(c, '\u{2000}'..='\u{200a}' | '\u{205f}' | '\u{3164}'))
+}
+
+// Zero width joiners and non-joiners shape letters in many scripts
+fn is_joiner(c: char) -> bool {
+    c == '\u{200c}' || c == '\u{200d}'
+}
+
+fn is_letter_or_mark(c: char) -> bool {
+    c.is_alphabetic()
+        || matches!(
+            get_general_category(c),
+            GeneralCategory::NonspacingMark | GeneralCategory::SpacingMark
+        )
+}
+
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_whitespace()
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+// A stretch of text between markers and the reference it belongs to
+struct TextRun {
+    start: usize,
+    end: usize,
+    reference: String,
+}
+
+// The text of a book, leaving out markers, attributes, the book code, note
+// callers and chapter and verse numbers
+fn text_runs(usfm: &str, catalog: &MarkerCatalog) -> Vec<TextRun> {
+    let mut runs = Vec::new();
+    let (mut book, mut chapter, mut verse) = (String::new(), String::new(), String::new());
+    let mut cursor = 0;
+    let mut add_run = |start: usize, end: usize, reference: String| {
+        // Attributes after `|` are not text
+        let end = usfm[start..end].find('|').map_or(end, |bar| start + bar);
+        if start < end {
+            runs.push(TextRun {
+                start,
+                end,
+                reference,
+            });
+        }
+    };
+
+    for token in scan_markers(usfm) {
+        add_run(
+            cursor.min(token.start_byte),
+            token.start_byte,
+            reference(&book, &chapter, &verse),
+        );
+        cursor = after_delimiter(usfm, token.end_byte);
+        match token.marker.as_str() {
+            _ if token.closing => cursor = token.end_byte,
+            "id" | "c" | "v" => {
+                let word = word_after(usfm, &token).to_string();
+                match token.marker.as_str() {
+                    "id" => (book, chapter, verse) = (word, String::new(), String::new()),
+                    "c" => (chapter, verse) = (word, String::new()),
+                    _ => verse = word,
+                }
+                cursor = after_delimiter(usfm, end_of_word(usfm, &token));
+            }
+            marker if catalog.is_milestone(marker) => {
+                cursor = after_milestone(usfm, token.end_byte)
+            }
+            // The caller of a note
+            marker if catalog.is_note(marker) => {
+                cursor = after_delimiter(usfm, end_of_word(usfm, &token))
+            }
+            _ => {}
+        }
+    }
+    add_run(
+        cursor.min(usfm.len()),
+        usfm.len(),
+        reference(&book, &chapter, &verse),
+    );
+    runs
+}
+
+// `GEN 1:2`, `GEN 1` before the first verse or `GEN` before the first chapter
+fn reference(book: &str, chapter: &str, verse: &str) -> String {
+    let mut reference = book.to_string();
+    if !chapter.is_empty() {
+        if !reference.is_empty() {
+            reference.push(' ');
+        }
+        reference.push_str(chapter);
+        if !verse.is_empty() {
+            reference.push(':');
+            reference.push_str(verse);
+        }
+    }
+    reference
+}
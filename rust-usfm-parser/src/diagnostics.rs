@@ -0,0 +1,112 @@
+use jsonschema::error::ValidationErrorKind;
+use jsonschema::ValidationError;
+use std::fmt;
+
+use crate::lint::find_rule;
+use crate::scanner::MarkerToken;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Info => write!(f, "info"),
+        }
+    }
+}
+
+/// A problem found in a USFM document, located by byte range and by the
+/// zero-based row/column of its start (as tree-sitter reports positions).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Id of the lint rule that reported it (see [`crate::lint::RULES`])
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub row: usize,
+    pub column: usize,
+}
+
+impl Diagnostic {
+    /// A diagnostic with the default severity of its rule.
+    pub fn new(
+        rule: &'static str,
+        message: String,
+        start_byte: usize,
+        end_byte: usize,
+        row: usize,
+        column: usize,
+    ) -> Self {
+        Self {
+            rule,
+            severity: find_rule(rule).map_or(Severity::Error, |rule| rule.severity),
+            message,
+            start_byte,
+            end_byte,
+            row,
+            column,
+        }
+    }
+
+    /// A diagnostic spanning a single marker.
+    pub fn at_marker(rule: &'static str, message: String, token: &MarkerToken) -> Self {
+        Self::new(
+            rule,
+            message,
+            token.start_byte,
+            token.end_byte,
+            token.row,
+            token.column,
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "At Point(row={}, column={}):{} [{}]",
+            self.row, self.column, self.message, self.rule
+        )
+    }
+}
+
+/// A USJ schema violation at the value `path` points to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsjError {
+    /// JSON Pointer to the offending value, such as `/content/2/number`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for UsjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "At {}: {}", self.path, self.message)
+    }
+}
+
+impl From<ValidationError<'_>> for UsjError {
+    fn from(error: ValidationError<'_>) -> Self {
+        let message = match &error.kind {
+            // The marker lists are too long to repeat in every message
+            ValidationErrorKind::Enum { .. } => {
+                format!("{} is not one of the allowed values", error.instance)
+            }
+            _ => error.to_string(),
+        };
+        let path = error.instance_path.to_string();
+        Self {
+            path: if path.is_empty() { "/".to_string() } else { path },
+            message,
+        }
+    }
+}
@@ -0,0 +1,409 @@
+use std::ops::Range;
+use tracing::debug;
+use tree_sitter::{InputEdit, Node, Point, Tree};
+
+use crate::diagnostics::Diagnostic;
+use crate::lint::mask_markers;
+use crate::scanner;
+use crate::structure::check_structure;
+use crate::usj_generator::write_usj_nodes;
+use crate::validator::Validator;
+
+// Rules whose findings depend on more than one chapter. They are checked
+// over the whole text after every reparse rather than chapter by chapter.
+const DOCUMENT_RULES: [&str; 6] = [
+    "chapter-sequence",
+    "milestone-orphan-start",
+    "milestone-orphan-end",
+    "milestone-id-mismatch",
+    "milestone-nesting",
+    "unmatched-punctuation",
+];
+
+/// The part of a document before its first chapter, or one chapter up to
+/// the next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Byte range in the document
+    pub range: Range<usize>,
+    /// Number written after `\c`; `None` for the part before the first chapter
+    pub chapter: Option<String>,
+    /// Lint findings in the segment, located in the document
+    pub diagnostics: Vec<Diagnostic>,
+    // USJ content items of the segment as compact JSON, separated by commas
+    usj: String,
+}
+
+/// What a [`Document::reparse`] changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    /// Ranges of the new text that were edited or parse differently
+    pub ranges: Vec<tree_sitter::Range>,
+    /// Indices in [`Document::segments`] of the segments converted and
+    /// linted again
+    pub segments: Vec<usize>,
+}
+
+/// A parsed book kept up to date as an editor changes it. Edits reparse
+/// incrementally, and only the chapters they touch are converted and
+/// linted again.
+pub struct Document {
+    validator: Validator,
+    usfm: String,
+    tree: Tree,
+    segments: Vec<Segment>,
+    /// Findings of [`DOCUMENT_RULES`]
+    document_diagnostics: Vec<Diagnostic>,
+    /// Code from `\id`, which chapter sids need
+    book_code: Option<String>,
+    /// Edits since the last parse, in the order they were made
+    edits: Vec<InputEdit>,
+}
+
+impl Document {
+    pub fn new(usfm: impl Into<String>) -> Result<Self, String> {
+        Self::with_validator(Validator::new().map_err(|e| e.to_string())?, usfm)
+    }
+
+    /// A document linted with the validator's catalog and lint configuration.
+    pub fn with_validator(
+        mut validator: Validator,
+        usfm: impl Into<String>,
+    ) -> Result<Self, String> {
+        let usfm = usfm.into();
+        let tree = parse(&mut validator, &usfm, None)?;
+        let mut document = Self {
+            validator,
+            usfm,
+            tree,
+            segments: Vec::new(),
+            document_diagnostics: Vec::new(),
+            book_code: None,
+            edits: Vec::new(),
+        };
+        document.update_segments(Vec::new(), &[])?;
+        Ok(document)
+    }
+
+    pub fn usfm(&self) -> &str {
+        &self.usfm
+    }
+
+    pub fn tree(&self) -> &Tree {
+        &self.tree
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Lint findings of the whole document, in document order.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<_> = self
+            .segments
+            .iter()
+            .flat_map(|segment| segment.diagnostics.iter().cloned())
+            .chain(self.document_diagnostics.iter().cloned())
+            .collect();
+        diagnostics.sort_by_key(|diagnostic| diagnostic.start_byte);
+        diagnostics
+    }
+
+    /// The document as compact USJ.
+    pub fn usj(&self) -> String {
+        let content: Vec<_> = self
+            .segments
+            .iter()
+            .map(|segment| segment.usj.as_str())
+            .filter(|usj| !usj.is_empty())
+            .collect();
+        format!(
+            r#"{{"type":"USJ","version":"3.1","content":[{}]}}"#,
+            content.join(",")
+        )
+    }
+
+    /// Records an edit made to the text. Call [`Document::reparse`] with the
+    /// edited text once the edits are made.
+    pub fn edit(&mut self, edit: &InputEdit) {
+        self.tree.edit(edit);
+        self.edits.push(*edit);
+    }
+
+    /// Parses the edited text, reusing the old tree, and converts and lints
+    /// the segments the edits touched. A changed header redoes every segment,
+    /// as chapters take their sids from the book code.
+    pub fn reparse(&mut self, usfm: impl Into<String>) -> Result<Changes, String> {
+        let usfm = usfm.into();
+        let tree = parse(&mut self.validator, &usfm, Some(&self.tree))?;
+        let edits = std::mem::take(&mut self.edits);
+
+        // Changes within a text node leave the syntax as it was, so the
+        // edited ranges are added to the ones tree-sitter reports
+        let mut ranges: Vec<_> = self.tree.changed_ranges(&tree).collect();
+        for (i, edit) in edits.iter().enumerate() {
+            let mut range = tree_sitter::Range {
+                start_byte: edit.start_byte,
+                end_byte: edit.new_end_byte,
+                start_point: edit.start_position,
+                end_point: edit.new_end_position,
+            };
+            for later in &edits[i + 1..] {
+                (range.start_byte, range.start_point) =
+                    shift(range.start_byte, range.start_point, later);
+                (range.end_byte, range.end_point) = shift(range.end_byte, range.end_point, later);
+            }
+            ranges.push(range);
+        }
+        ranges.sort_by_key(|range| (range.start_byte, range.end_byte));
+
+        let old_segments = std::mem::take(&mut self.segments)
+            .into_iter()
+            .map(|mut segment| {
+                for edit in &edits {
+                    segment.range.start = shift_byte(segment.range.start, edit);
+                    segment.range.end = shift_byte(segment.range.end, edit);
+                    for diagnostic in &mut segment.diagnostics {
+                        shift_diagnostic(diagnostic, edit);
+                    }
+                }
+                segment
+            })
+            .collect();
+        self.usfm = usfm;
+        self.tree = tree;
+        let segments = self.update_segments(old_segments, &ranges)?;
+        debug!(edits = edits.len(), segments = ?segments, "reparsed");
+        Ok(Changes { ranges, segments })
+    }
+
+    /// Replaces a byte range of the text and reparses, for callers that do
+    /// not track tree-sitter edits themselves.
+    pub fn replace(&mut self, range: Range<usize>, text: &str) -> Result<Changes, String> {
+        if range.start > range.end
+            || range.end > self.usfm.len()
+            || !self.usfm.is_char_boundary(range.start)
+            || !self.usfm.is_char_boundary(range.end)
+        {
+            return Err(format!("Invalid range {:?}", range));
+        }
+        let mut usfm = self.usfm.clone();
+        usfm.replace_range(range.clone(), text);
+        let new_end_byte = range.start + text.len();
+        self.edit(&InputEdit {
+            start_byte: range.start,
+            old_end_byte: range.end,
+            new_end_byte,
+            start_position: point_at(&self.usfm, range.start),
+            old_end_position: point_at(&self.usfm, range.end),
+            new_end_position: point_at(&usfm, new_end_byte),
+        });
+        self.reparse(usfm)
+    }
+
+    // Splits the tree into segments, reusing an old segment that covers the
+    // same range and no changed range touches. Returns the indices of the
+    // segments redone.
+    fn update_segments(
+        &mut self,
+        mut old_segments: Vec<Segment>,
+        ranges: &[tree_sitter::Range],
+    ) -> Result<Vec<usize>, String> {
+        // A tree clone is cheap and lets segments be converted while its nodes are held
+        let tree = self.tree.clone();
+        let root = tree.root_node();
+        let nodes: Vec<Node> = root.children(&mut root.walk()).collect();
+        let mut starts: Vec<usize> = nodes
+            .iter()
+            .filter(|node| node.kind() == "chapter")
+            .map(Node::start_byte)
+            .collect();
+        starts.insert(0, 0);
+        starts.push(self.usfm.len());
+        let header_end = starts[1];
+
+        let touched = |range: &Range<usize>| {
+            ranges
+                .iter()
+                .any(|changed| changed.start_byte <= range.end && range.start <= changed.end_byte)
+        };
+        let mut redone = Vec::new();
+        let mut segments = Vec::new();
+        for (index, bounds) in starts.windows(2).enumerate() {
+            let range = bounds[0]..bounds[1];
+            let reusable = redone.first() != Some(&0) && !touched(&range);
+            let old = reusable
+                .then(|| {
+                    let position = old_segments
+                        .iter()
+                        .position(|segment| segment.range == range)?;
+                    Some(old_segments.swap_remove(position))
+                })
+                .flatten();
+            let segment = match old {
+                Some(segment) => segment,
+                None => {
+                    redone.push(index);
+                    let segment_nodes: Vec<Node> = nodes
+                        .iter()
+                        .filter(|node| range.contains(&node.start_byte()))
+                        .copied()
+                        .collect();
+                    self.convert_segment(range, &segment_nodes, header_end)?
+                }
+            };
+            segments.push(segment);
+        }
+        self.segments = segments;
+        self.document_diagnostics = self.check_document();
+        Ok(redone)
+    }
+
+    fn convert_segment(
+        &mut self,
+        range: Range<usize>,
+        nodes: &[Node],
+        header_end: usize,
+    ) -> Result<Segment, String> {
+        let is_header = range.start == 0;
+        let mut usj = Vec::new();
+        let book_code = write_usj_nodes(
+            nodes,
+            &self.usfm,
+            (!is_header).then(|| self.book_code.clone()).flatten(),
+            &mut usj,
+            &self.validator.catalog,
+        )
+        .map_err(|e| e.to_string())?;
+        if is_header {
+            self.book_code = book_code;
+        }
+        // Drop the brackets of the array
+        let usj = String::from_utf8_lossy(&usj[1..usj.len() - 1]).into_owned();
+
+        let chapter = nodes
+            .iter()
+            .find(|node| node.kind() == "chapter")
+            .and_then(|node| node.child(0))
+            .and_then(|c| {
+                c.named_children(&mut c.walk())
+                    .find(|child| child.kind() == "chapterNumber")
+            })
+            .and_then(|number| number.utf8_text(self.usfm.as_bytes()).ok())
+            .map(|number| number.trim().to_string());
+
+        Ok(Segment {
+            diagnostics: self.lint_segment(&range, header_end)?,
+            range,
+            chapter,
+            usj,
+        })
+    }
+
+    // A chapter is linted after the header, so rules that look at the book
+    // code and headers see them, and its findings are moved to where the
+    // chapter is in the document
+    fn lint_segment(
+        &mut self,
+        range: &Range<usize>,
+        header_end: usize,
+    ) -> Result<Vec<Diagnostic>, String> {
+        let header = &self.usfm[..header_end];
+        if range.start == 0 {
+            let diagnostics = self.validator.lint(header)?;
+            return Ok(without_document_rules(diagnostics));
+        }
+        let text = format!("{}{}", header, &self.usfm[range.clone()]);
+        let header_point = point_at(header, header_end);
+        let edit = InputEdit {
+            start_byte: 0,
+            old_end_byte: header_end,
+            new_end_byte: range.start,
+            start_position: Point::new(0, 0),
+            old_end_position: header_point,
+            new_end_position: point_at(&self.usfm, range.start),
+        };
+        let mut diagnostics = without_document_rules(self.validator.lint(&text)?);
+        diagnostics.retain(|diagnostic| diagnostic.start_byte >= header_end);
+        for diagnostic in &mut diagnostics {
+            shift_diagnostic(diagnostic, &edit);
+        }
+        Ok(diagnostics)
+    }
+
+    fn check_document(&self) -> Vec<Diagnostic> {
+        let lint_config = &self.validator.lint_config;
+        let usfm = mask_markers(&self.usfm, &lint_config.ignore_markers);
+        let mut diagnostics: Vec<_> = check_structure(&usfm)
+            .into_iter()
+            .filter(|diagnostic| DOCUMENT_RULES.contains(&diagnostic.rule))
+            .collect();
+        diagnostics.extend(self.validator.check_milestones(&usfm));
+        diagnostics.extend(self.validator.check_punctuation_pairs(&usfm));
+        lint_config.apply(diagnostics)
+    }
+}
+
+fn parse(validator: &mut Validator, usfm: &str, old_tree: Option<&Tree>) -> Result<Tree, String> {
+    validator
+        .parser
+        .parser
+        .parse(usfm, old_tree)
+        .ok_or_else(|| "Failed to parse the USFM string.".to_string())
+}
+
+fn without_document_rules(diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    diagnostics
+        .into_iter()
+        .filter(|diagnostic| !DOCUMENT_RULES.contains(&diagnostic.rule))
+        .collect()
+}
+
+// The scanner's row and byte column of an offset, as a tree-sitter point
+fn point_at(text: &str, byte: usize) -> Point {
+    let (row, column) = scanner::point_at(text, byte);
+    Point::new(row, column)
+}
+
+// Where a position after an edit moves to; positions inside the replaced
+// text move to the end of the new text
+fn shift(byte: usize, point: Point, edit: &InputEdit) -> (usize, Point) {
+    if byte <= edit.start_byte {
+        return (byte, point);
+    }
+    if byte < edit.old_end_byte {
+        return (edit.new_end_byte, edit.new_end_position);
+    }
+    let point = if point.row == edit.old_end_position.row {
+        Point::new(
+            edit.new_end_position.row,
+            (point.column + edit.new_end_position.column)
+                .saturating_sub(edit.old_end_position.column),
+        )
+    } else {
+        Point::new(
+            (point.row + edit.new_end_position.row).saturating_sub(edit.old_end_position.row),
+            point.column,
+        )
+    };
+    (shift_byte(byte, edit), point)
+}
+
+fn shift_byte(byte: usize, edit: &InputEdit) -> usize {
+    if byte <= edit.start_byte {
+        byte
+    } else if byte < edit.old_end_byte {
+        edit.new_end_byte
+    } else {
+        (byte + edit.new_end_byte).saturating_sub(edit.old_end_byte)
+    }
+}
+
+fn shift_diagnostic(diagnostic: &mut Diagnostic, edit: &InputEdit) {
+    let point = Point::new(diagnostic.row, diagnostic.column);
+    let (start_byte, point) = shift(diagnostic.start_byte, point, edit);
+    diagnostic.end_byte = shift_byte(diagnostic.end_byte, edit);
+    diagnostic.start_byte = start_byte;
+    diagnostic.row = point.row;
+    diagnostic.column = point.column;
+}
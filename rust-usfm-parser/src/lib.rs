@@ -1,5 +1,31 @@
 // src/lib.rs
 //test comment
+pub mod attributes;
+pub mod batch;
+pub mod book_info;
+pub mod books;
+pub mod burrito;
+pub mod characters;
+pub mod char_markers;
+pub mod diagnostics;
+pub mod document;
 pub mod globals;
+pub mod lint;
+pub mod milestones;
+pub mod paratext;
 pub mod parser;
+pub mod placement;
+pub mod project;
+pub mod reference;
+pub mod relaxng;
+pub mod resource_container;
+pub mod scanner;
+pub mod schema;
+pub mod source_map;
+pub mod structure;
+pub mod stylesheet;
+pub mod text_checks;
 pub mod usj_generator;
+pub mod usj_semantics;
+pub mod validator;
+pub mod verse_text;
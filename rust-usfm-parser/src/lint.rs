@@ -0,0 +1,388 @@
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::books::Versification;
+use crate::characters::CharacterSet;
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::scanner::scan_markers;
+
+/// A check that can be configured by its id in `usfm-lint.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    pub id: &'static str,
+    pub severity: Severity,
+    /// Whether the rule runs when the configuration does not mention it
+    pub enabled: bool,
+    pub description: &'static str,
+}
+
+const fn rule(
+    id: &'static str,
+    severity: Severity,
+    enabled: bool,
+    description: &'static str,
+) -> Rule {
+    Rule {
+        id,
+        severity,
+        enabled,
+        description,
+    }
+}
+
+pub const RULES: &[Rule] = &[
+    rule(
+        "syntax-error",
+        Severity::Error,
+        true,
+        "Text the USFM grammar cannot parse",
+    ),
+    rule(
+        "unknown-marker",
+        Severity::Error,
+        true,
+        "Marker not defined by usfm.sty or custom.sty",
+    ),
+    rule(
+        "marker-placement",
+        Severity::Error,
+        true,
+        "Marker used outside the contexts its \\OccursUnder allows",
+    ),
+    rule(
+        "unclosed-marker",
+        Severity::Error,
+        true,
+        "Character or note marker without its closing marker",
+    ),
+    rule(
+        "stray-closing-marker",
+        Severity::Error,
+        true,
+        "Closing marker without an opening marker",
+    ),
+    rule(
+        "crossed-markers",
+        Severity::Error,
+        true,
+        "Marker closed while one opened inside it is still open",
+    ),
+    rule(
+        "marker-spans-verse",
+        Severity::Error,
+        true,
+        "Character marker closed only after the next verse number",
+    ),
+    rule(
+        "milestone-orphan-start",
+        Severity::Warning,
+        true,
+        "Start milestone that is never ended",
+    ),
+    rule(
+        "milestone-orphan-end",
+        Severity::Warning,
+        true,
+        "End milestone without a start",
+    ),
+    rule(
+        "milestone-id-mismatch",
+        Severity::Warning,
+        true,
+        "End milestone eid not matching the start sid",
+    ),
+    rule(
+        "milestone-nesting",
+        Severity::Warning,
+        true,
+        "Milestones of the same kind ended out of order",
+    ),
+    rule(
+        "chapter-sequence",
+        Severity::Warning,
+        true,
+        "Chapter numbers repeated, out of order or skipped",
+    ),
+    rule(
+        "verse-sequence",
+        Severity::Warning,
+        true,
+        "Verse numbers repeated, out of order or skipped",
+    ),
+    rule(
+        "unknown-book-code",
+        Severity::Error,
+        true,
+        "\\id code that is not a USFM book code",
+    ),
+    rule(
+        "chapter-out-of-range",
+        Severity::Warning,
+        true,
+        "Chapter beyond the last chapter of the book in the configured versification",
+    ),
+    rule(
+        "missing-header-marker",
+        Severity::Warning,
+        true,
+        "Book without \\h, \\toc1, \\toc2 or \\mt",
+    ),
+    rule(
+        "missing-attribute",
+        Severity::Warning,
+        true,
+        "Required attribute not given",
+    ),
+    rule(
+        "unknown-attribute",
+        Severity::Warning,
+        true,
+        "Attribute the marker does not define",
+    ),
+    rule(
+        "repeated-word",
+        Severity::Warning,
+        true,
+        "The same word twice in a row, as in \"the the\"",
+    ),
+    // Off by default as spacing conventions differ between languages, e.g.
+    // French puts a space before ; : ! and ?
+    rule(
+        "punctuation-spacing",
+        Severity::Warning,
+        false,
+        "Space before closing punctuation or none after it",
+    ),
+    rule(
+        "unmatched-punctuation",
+        Severity::Warning,
+        true,
+        "Bracket or quotation mark without its pair",
+    ),
+    // Off by default as loanwords and transliterations may mix scripts
+    rule(
+        "mixed-script",
+        Severity::Warning,
+        false,
+        "Word mixing letters of several scripts",
+    ),
+    rule(
+        "verse-whitespace",
+        Severity::Warning,
+        true,
+        "Verse text starting or ending with extra whitespace",
+    ),
+    rule(
+        "invisible-character",
+        Severity::Warning,
+        true,
+        "Control or format character such as a zero width space",
+    ),
+    rule(
+        "disallowed-character",
+        Severity::Warning,
+        true,
+        "Character outside the allowed set",
+    ),
+    rule(
+        "non-nfc-text",
+        Severity::Warning,
+        true,
+        "Text not in Unicode Normalization Form C",
+    ),
+    rule(
+        "mixed-normalization",
+        Severity::Warning,
+        true,
+        "Book using both composed and decomposed characters",
+    ),
+    rule(
+        "invalid-reference",
+        Severity::Warning,
+        true,
+        "\\ref or \\xt target that is not a scripture reference",
+    ),
+];
+
+pub fn find_rule(id: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|rule| rule.id == id)
+}
+
+/// How a configuration file sets a rule: `"off"`, `"on"` (default severity)
+/// or one of `"error"`, `"warning"`, `"info"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleLevel {
+    Off,
+    On,
+    Severity(Severity),
+}
+
+impl RuleLevel {
+    fn parse(level: &str) -> Option<Self> {
+        match level.to_ascii_lowercase().as_str() {
+            "off" => Some(RuleLevel::Off),
+            "on" => Some(RuleLevel::On),
+            "error" => Some(RuleLevel::Severity(Severity::Error)),
+            "warning" => Some(RuleLevel::Severity(Severity::Warning)),
+            "info" => Some(RuleLevel::Severity(Severity::Info)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LintFile {
+    #[serde(default)]
+    ignore_markers: Vec<String>,
+    #[serde(default)]
+    rules: HashMap<String, String>,
+    quotes: Option<Vec<String>>,
+    allowed_characters: Option<String>,
+    versification: Option<String>,
+}
+
+// Curly double and single quotation marks
+const DEFAULT_QUOTES: [(char, char); 2] = [('\u{201c}', '\u{201d}'), ('\u{2018}', '\u{2019}')];
+
+/// Lint settings, usually read from a `usfm-lint.toml` such as
+///
+/// ```toml
+/// ignore_markers = ["s5"]
+///
+/// [rules]
+/// missing-header-marker = "error"
+/// verse-sequence = "off"
+/// ```
+///
+/// Rules off by default, such as punctuation-spacing, run when set to
+/// `"on"` or a severity.
+/// `quotes = ["«»", "‹›"]` sets the quotation marks paired by the
+/// unmatched-punctuation rule, each as its opening and closing character.
+/// `allowed_characters = "a-zA-Z.,;:’"` turns on the disallowed-character
+/// rule. `versification = "english"` turns on the chapter-out-of-range rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintConfig {
+    /// Markers treated as if they were not in the text at all
+    pub ignore_markers: Vec<String>,
+    pub rules: HashMap<String, RuleLevel>,
+    /// Opening and closing quotation marks, outermost level first
+    pub quotes: Vec<(char, char)>,
+    /// Characters the text may use, if the project restricts them
+    pub allowed_characters: Option<CharacterSet>,
+    /// Versification whose chapter counts books are checked against
+    pub versification: Option<Versification>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            ignore_markers: Vec::new(),
+            rules: HashMap::new(),
+            quotes: DEFAULT_QUOTES.to_vec(),
+            allowed_characters: None,
+            versification: None,
+        }
+    }
+}
+
+impl LintConfig {
+    pub fn from_toml(toml: &str) -> Result<Self, String> {
+        let file: LintFile = toml::from_str(toml).map_err(|e| e.to_string())?;
+        let mut rules = HashMap::new();
+        for (id, level) in file.rules {
+            if find_rule(&id).is_none() {
+                return Err(format!("Unknown lint rule '{}'", id));
+            }
+            let level = RuleLevel::parse(&level).ok_or_else(|| {
+                format!(
+                    "Invalid level '{}' for rule '{}'; expected off, on, error, warning or info",
+                    level, id
+                )
+            })?;
+            rules.insert(id, level);
+        }
+        let quotes = match file.quotes {
+            Some(quotes) => quotes
+                .iter()
+                .map(|pair| match pair.chars().collect::<Vec<_>>()[..] {
+                    [open, close] => Ok((open, close)),
+                    _ => Err(format!(
+                        "Quote pair '{}' must be an opening and a closing character",
+                        pair
+                    )),
+                })
+                .collect::<Result<_, _>>()?,
+            None => DEFAULT_QUOTES.to_vec(),
+        };
+        let versification = match file.versification {
+            Some(name) => Some(Versification::from_name(&name).ok_or_else(|| {
+                format!(
+                    "Unknown versification '{}'; expected one of {}",
+                    name,
+                    Versification::ALL.map(|versification| versification.name()).join(", ")
+                )
+            })?),
+            None => None,
+        };
+        Ok(Self {
+            ignore_markers: file
+                .ignore_markers
+                .iter()
+                .map(|marker| marker.trim_start_matches('\\').to_string())
+                .collect(),
+            rules,
+            quotes,
+            allowed_characters: file.allowed_characters.as_deref().map(CharacterSet::parse),
+            versification,
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let toml = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_toml(&toml).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The severity a rule reports with, or `None` when it is turned off.
+    pub fn severity_of(&self, id: &str) -> Option<Severity> {
+        let rule = find_rule(id)?;
+        match self.rules.get(id) {
+            Some(RuleLevel::Off) => None,
+            Some(RuleLevel::On) => Some(rule.severity),
+            Some(RuleLevel::Severity(severity)) => Some(*severity),
+            None => rule.enabled.then_some(rule.severity),
+        }
+    }
+
+    /// Drops diagnostics of disabled rules and applies severity overrides.
+    pub fn apply(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        diagnostics
+            .into_iter()
+            .filter_map(|mut diagnostic| {
+                diagnostic.severity = self.severity_of(diagnostic.rule)?;
+                Some(diagnostic)
+            })
+            .collect()
+    }
+}
+
+/// Blanks out the ignored markers so no check sees them, keeping every other
+/// byte offset, row and column unchanged.
+pub fn mask_markers<'a>(usfm: &'a str, markers: &[String]) -> Cow<'a, str> {
+    if markers.is_empty() {
+        return Cow::Borrowed(usfm);
+    }
+    let mut masked = usfm.to_string();
+    for token in scan_markers(usfm) {
+        if markers.contains(&token.marker) {
+            let blank = " ".repeat(token.end_byte - token.start_byte);
+            masked.replace_range(token.start_byte..token.end_byte, &blank);
+        }
+    }
+    Cow::Owned(masked)
+}
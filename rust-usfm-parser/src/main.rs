@@ -1,22 +1,67 @@
-mod globals;
-mod parser;
-mod schema;
-mod usj_generator;
-mod validator;
-
-extern crate lazy_static;
-use crate::validator::Validator;
-use lazy_static::lazy_static;
-use parser::USFMParser;
+use rust_usfm::batch::{run_batch, BatchOptions};
+use rust_usfm::lint::LintConfig;
+use rust_usfm::parser::USFMParser;
+use rust_usfm::usj_generator;
+use rust_usfm::validator::Validator;
 use std::fs::File;
-use std::io::{self, Read};
-use std::sync::Mutex;
-use tree_sitter::Tree;
+use std::io::{self, IsTerminal, Read};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use tracing_subscriber::EnvFilter;
+
+#[derive(StructOpt)]
+#[structopt(name = "rust-usfm")]
+struct Opt {
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(StructOpt)]
+enum Command {
+    /// Converts and lints many USFM files in parallel
+    Batch(BatchCommand),
+}
+
+#[derive(StructOpt)]
+struct BatchCommand {
+    /// Worker threads; one per core when not given
+    #[structopt(short, long)]
+    threads: Option<usize>,
+    /// Lint the files
+    #[structopt(long)]
+    lint: bool,
+    /// Skip converting the files to USJ
+    #[structopt(long)]
+    no_convert: bool,
+    /// usfm-lint.toml to lint with
+    #[structopt(long, parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Folder to write the USJ of each file to, as <file stem>.json
+    #[structopt(short, long, parse(from_os_str))]
+    out: Option<PathBuf>,
+    /// Normalize text to Unicode NFC in the USJ written
+    #[structopt(long)]
+    nfc: bool,
+    /// Folder to write the USFM of each file to, normalized to NFC
+    #[structopt(long, parse(from_os_str), requires = "nfc")]
+    usfm_out: Option<PathBuf>,
+    #[structopt(parse(from_os_str), required = true)]
+    files: Vec<PathBuf>,
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    lazy_static! {
-        static ref GLOBAL_TREE: Mutex<Option<Tree>> = Mutex::new(None);
+    // Logs go to stderr, filtered by RUST_LOG; `RUST_LOG=rust_usfm=trace`
+    // also lists every node converted
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .init();
+
+    if let Some(Command::Batch(batch)) = Opt::from_args().command {
+        return run_batch_command(batch);
     }
+
     let _parser = USFMParser::new()?;
 
     //let usfm_input = read_file("input.usfm")?;
@@ -77,11 +122,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     match validator.is_valid_usj(usj_sample) {
-        Ok(valid) => {
-            if valid {
+        Ok(errors) => {
+            if errors.is_empty() {
                 println!("USJ is valid");
             } else {
-                eprintln!("Invalid USJ:\n{}", validator.get_message());
+                eprintln!("Invalid USJ:");
+                for error in errors {
+                    eprintln!("{}", error);
+                }
             }
         }
         Err(e) => eprintln!("USJ Validation Error: {}", e),
@@ -90,6 +138,31 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+fn run_batch_command(command: BatchCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let mut options = BatchOptions {
+        threads: command.threads,
+        convert: !command.no_convert,
+        lint: command.lint,
+        nfc: command.nfc,
+        ..BatchOptions::default()
+    };
+    if let Some(config) = &command.config {
+        options.lint_config = LintConfig::load(config)?;
+    }
+    let report = run_batch(&command.files, &options)?;
+    if let Some(out) = &command.out {
+        report.write_usj(out)?;
+    }
+    if let Some(usfm_out) = &command.usfm_out {
+        report.write_usfm(usfm_out)?;
+    }
+    println!("{}", report);
+    if !report.is_success() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
 // Function to read the USFM file content
 fn read_file(file_path: &str) -> Result<String, io::Error> {
     let mut file = File::open(file_path)?; // Open the file
@@ -0,0 +1,254 @@
+use crate::attributes::parse_attributes;
+use crate::diagnostics::Diagnostic;
+use crate::scanner::{scan_markers, MarkerToken};
+use crate::stylesheet::{base_marker, MarkerCatalog, StyleType};
+
+/// A start or end milestone such as `\qt-s |sid="qt1" who="Pilate"\*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Milestone {
+    /// Marker name without the backslash (`qt-s`, `zaln-e`)
+    pub marker: String,
+    pub attributes: Vec<(String, String)>,
+    /// Byte range of the whole milestone, attributes and `\*` included
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub row: usize,
+    pub column: usize,
+}
+
+impl Milestone {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The marker shared by the start and end milestone (`qt` for `qt-s`).
+    pub fn stem(&self) -> &str {
+        milestone_stem(&self.marker).0
+    }
+
+    pub fn is_start(&self) -> bool {
+        milestone_stem(&self.marker).1
+    }
+
+    fn describe(&self) -> String {
+        let id = if self.is_start() { "sid" } else { "eid" };
+        match self.attribute(id) {
+            Some(value) => format!("\\{} ({}=\"{}\")", self.marker, id, value),
+            None => format!("\\{}", self.marker),
+        }
+    }
+
+    pub(crate) fn diagnostic(&self, rule: &'static str, message: String) -> Diagnostic {
+        Diagnostic::new(
+            rule,
+            message,
+            self.start_byte,
+            self.end_byte,
+            self.row,
+            self.column,
+        )
+    }
+}
+
+/// A start milestone and the end milestone that closes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MilestonePair {
+    pub start: Milestone,
+    pub end: Milestone,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MilestoneIssueKind {
+    /// A start milestone that is never ended
+    OrphanStart,
+    /// An end milestone without a start
+    OrphanEnd,
+    /// The end's `eid` does not match the `sid` of the start it closes
+    IdMismatch,
+    /// Closes a start while a later start of the same kind is still open
+    ImproperNesting,
+}
+
+impl MilestoneIssueKind {
+    /// The lint rule reporting this kind of issue.
+    pub fn rule(&self) -> &'static str {
+        match self {
+            MilestoneIssueKind::OrphanStart => "milestone-orphan-start",
+            MilestoneIssueKind::OrphanEnd => "milestone-orphan-end",
+            MilestoneIssueKind::IdMismatch => "milestone-id-mismatch",
+            MilestoneIssueKind::ImproperNesting => "milestone-nesting",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MilestoneIssue {
+    pub kind: MilestoneIssueKind,
+    pub milestone: Milestone,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MilestoneAnalysis {
+    pub pairs: Vec<MilestonePair>,
+    pub issues: Vec<MilestoneIssue>,
+}
+
+impl MilestoneAnalysis {
+    /// Milestone problems do not make a document invalid USFM, so their rules
+    /// default to warnings.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.issues
+            .iter()
+            .map(|issue| {
+                issue
+                    .milestone
+                    .diagnostic(issue.kind.rule(), issue.message.clone())
+            })
+            .collect()
+    }
+
+    /// The pair a start milestone belongs to, looked up by its `sid`.
+    pub fn pair_by_sid(&self, sid: &str) -> Option<&MilestonePair> {
+        self.pairs
+            .iter()
+            .find(|pair| pair.start.attribute("sid") == Some(sid))
+    }
+}
+
+/// Start and end milestones in document order: those the stylesheet declares
+/// with a `-s`/`-e` marker pair, and `\z...-s`/`\z...-e` ones such as `\zaln-s`.
+pub fn find_milestones(usfm: &str, catalog: &MarkerCatalog) -> Vec<Milestone> {
+    scan_markers(usfm)
+        .iter()
+        .filter(|token| !token.closing && is_paired_milestone(&token.marker, catalog))
+        .map(|token| read_milestone(usfm, token, catalog))
+        .collect()
+}
+
+/// Pairs start milestones with their ends, by `sid`/`eid` where given and
+/// otherwise with the innermost open start of the same marker.
+pub fn pair_milestones(usfm: &str, catalog: &MarkerCatalog) -> MilestoneAnalysis {
+    let mut analysis = MilestoneAnalysis::default();
+    let mut open: Vec<Milestone> = Vec::new();
+
+    for milestone in find_milestones(usfm, catalog) {
+        if milestone.is_start() {
+            open.push(milestone);
+            continue;
+        }
+        let stem = milestone.stem().to_string();
+        let eid = milestone.attribute("eid");
+        let by_id = eid.and_then(|eid| {
+            open.iter()
+                .rposition(|start| start.stem() == stem && start.attribute("sid") == Some(eid))
+        });
+        let innermost = open.iter().rposition(|start| start.stem() == stem);
+        let Some(index) = by_id.or(innermost) else {
+            analysis.issues.push(MilestoneIssue {
+                kind: MilestoneIssueKind::OrphanEnd,
+                message: format!("{} has no matching \\{}-s", milestone.describe(), stem),
+                milestone,
+            });
+            continue;
+        };
+
+        let start = open.remove(index);
+        let sid = start.attribute("sid");
+        if by_id.is_none() && (sid.is_some() || eid.is_some()) {
+            let expected = match sid {
+                Some(sid) => format!("expected eid=\"{}\"", sid),
+                None => "the start has no sid".to_string(),
+            };
+            analysis.issues.push(MilestoneIssue {
+                kind: MilestoneIssueKind::IdMismatch,
+                message: format!(
+                    "{} does not match {}; {}",
+                    milestone.describe(),
+                    start.describe(),
+                    expected
+                ),
+                milestone: milestone.clone(),
+            });
+        }
+        // Starts of the same family opened after this one must end before it
+        let family = base_marker(&stem);
+        for inner in open[index..]
+            .iter()
+            .filter(|inner| base_marker(inner.stem()) == family)
+        {
+            analysis.issues.push(MilestoneIssue {
+                kind: MilestoneIssueKind::ImproperNesting,
+                message: format!(
+                    "{} ends {} while {}, opened inside it, is still open",
+                    milestone.describe(),
+                    start.describe(),
+                    inner.describe()
+                ),
+                milestone: milestone.clone(),
+            });
+        }
+        analysis.pairs.push(MilestonePair {
+            start,
+            end: milestone,
+        });
+    }
+
+    for start in open {
+        analysis.issues.push(MilestoneIssue {
+            kind: MilestoneIssueKind::OrphanStart,
+            message: format!(
+                "{} is never ended by \\{}-e",
+                start.describe(),
+                start.stem()
+            ),
+            milestone: start,
+        });
+    }
+    analysis
+}
+
+// Splits `qt2-s` into (`qt2`, true) and `zaln-e` into (`zaln`, false)
+fn milestone_stem(marker: &str) -> (&str, bool) {
+    match marker.rsplit_once('-') {
+        Some((stem, suffix)) => (stem, suffix == "s"),
+        None => (marker, false),
+    }
+}
+
+fn is_paired_milestone(marker: &str, catalog: &MarkerCatalog) -> bool {
+    if !(marker.ends_with("-s") || marker.ends_with("-e")) {
+        return false;
+    }
+    marker.starts_with('z')
+        || catalog
+            .get(marker)
+            .is_some_and(|info| info.style_type == Some(StyleType::Milestone))
+}
+
+fn read_milestone(usfm: &str, token: &MarkerToken, catalog: &MarkerCatalog) -> Milestone {
+    // The milestone runs up to its `\*`, unless another marker comes first
+    let rest = &usfm[token.end_byte..];
+    let length = match (rest.find("\\*"), rest.find('\\')) {
+        (Some(close), Some(next)) if close == next => close + 2,
+        _ => 0,
+    };
+    let inside = &rest[..length.saturating_sub(2)];
+
+    let attributes = inside
+        .split_once('|')
+        .map(|(_, list)| parse_attributes(list, &token.marker, catalog))
+        .unwrap_or_default();
+
+    Milestone {
+        marker: token.marker.clone(),
+        attributes,
+        start_byte: token.start_byte,
+        end_byte: token.end_byte + length,
+        row: token.row,
+        column: token.column,
+    }
+}
@@ -0,0 +1,264 @@
+use encoding_rs::Encoding;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::books::{Versification, BOOKS};
+use crate::diagnostics::Diagnostic;
+use crate::project::{BookOutput, Project};
+use crate::stylesheet::MarkerCatalog;
+use crate::validator::Validator;
+
+// Code page of UTF-8, the encoding of nearly every current project
+const UTF8_CODE_PAGE: u32 = 65001;
+
+/// The parts of a Paratext `Settings.xml` that affect reading a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParatextSettings {
+    /// Short name of the project
+    pub name: String,
+    pub full_name: Option<String>,
+    /// Language name, e.g. `English`
+    pub language: Option<String>,
+    /// Language code, e.g. `en`
+    pub language_code: Option<String>,
+    /// Windows code page of the book files
+    pub encoding: u32,
+    pub versification: Versification,
+    /// Book file names are the prefix, the book name form and the suffix
+    pub file_prefix: String,
+    /// `41MAT`, `MAT` or `41`, standing for the book number and code
+    pub book_name_form: String,
+    pub file_suffix: String,
+}
+
+impl Default for ParatextSettings {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            full_name: None,
+            language: None,
+            language_code: None,
+            encoding: UTF8_CODE_PAGE,
+            versification: Versification::English,
+            file_prefix: String::new(),
+            book_name_form: "41MAT".to_string(),
+            file_suffix: ".SFM".to_string(),
+        }
+    }
+}
+
+impl ParatextSettings {
+    pub fn parse(xml: &str) -> Result<Self, String> {
+        let document =
+            roxmltree::Document::parse(xml).map_err(|e| format!("Invalid Settings.xml: {}", e))?;
+        let field = |name: &str| {
+            document
+                .root_element()
+                .children()
+                .find(|node| node.has_tag_name(name))
+                .and_then(|node| node.text())
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+        };
+
+        let mut settings = Self::default();
+        if let Some(name) = field("Name") {
+            settings.name = name.to_string();
+        }
+        settings.full_name = field("FullName").map(str::to_string);
+        settings.language = field("Language").map(str::to_string);
+        // Written as `en:::` with optional script, region and variant parts
+        settings.language_code = field("LanguageIsoCode")
+            .and_then(|code| code.split(':').next())
+            .filter(|code| !code.is_empty())
+            .map(str::to_string);
+        if let Some(encoding) = field("Encoding") {
+            settings.encoding = encoding
+                .parse()
+                .map_err(|_| format!("Invalid Encoding \"{}\" in Settings.xml", encoding))?;
+        }
+        // Numbered 1 to 6 in the order of `Versification::ALL`
+        if let Some(versification) = field("Versification") {
+            settings.versification = versification
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| Versification::ALL.get(number.checked_sub(1)?).copied())
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid Versification \"{}\" in Settings.xml",
+                        versification
+                    )
+                })?;
+        }
+        if let Some(prefix) = field("FileNamePrePart") {
+            settings.file_prefix = prefix.to_string();
+        }
+        if let Some(form) = field("FileNameBookNameForm") {
+            settings.book_name_form = form.to_string();
+        }
+        if let Some(suffix) = field("FileNamePostPart") {
+            settings.file_suffix = suffix.to_string();
+        }
+        Ok(settings)
+    }
+
+    /// The name of a book's file, e.g. `41MATXYZ.SFM`.
+    pub fn file_name(&self, code: &str) -> Option<String> {
+        let number = paratext_book_number(code)?;
+        let book = self
+            .book_name_form
+            .replace("41", &number)
+            .replace("MAT", code);
+        Some(format!("{}{}{}", self.file_prefix, book, self.file_suffix))
+    }
+
+    /// Reads a book file in the project's encoding.
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, String> {
+        let encoding = code_page_encoding(self.encoding)
+            .ok_or_else(|| format!("Unsupported encoding (code page {})", self.encoding))?;
+        let (text, _, had_errors) = encoding.decode(bytes);
+        if had_errors {
+            return Err(format!("Text is not valid {}", encoding.name()));
+        }
+        Ok(text.into_owned())
+    }
+}
+
+/// A Paratext project folder: its settings, stylesheet and books.
+#[derive(Debug, Clone)]
+pub struct ParatextProject {
+    pub dir: PathBuf,
+    pub settings: ParatextSettings,
+    /// `usfm.sty` with the project's `custom.sty`, if it has one
+    pub catalog: MarkerCatalog,
+    pub project: Project,
+}
+
+impl ParatextProject {
+    /// Reads `Settings.xml`, `custom.sty` and the book files the settings
+    /// name. Other files in the folder are left out.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, String> {
+        let dir = dir.as_ref().to_path_buf();
+        let settings_path = dir.join("Settings.xml");
+        let xml = fs::read_to_string(&settings_path)
+            .map_err(|e| format!("Cannot read {}: {}", settings_path.display(), e))?;
+        let settings = ParatextSettings::parse(&xml)?;
+
+        let mut catalog = MarkerCatalog::usfm();
+        let custom = dir.join("custom.sty");
+        if custom.is_file() {
+            catalog.load_custom(&custom)?;
+        }
+
+        // File names are matched ignoring case, as Paratext does on Windows
+        let entries =
+            fs::read_dir(&dir).map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?;
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?
+                .path();
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+        let mut sources = Vec::new();
+        for book in BOOKS {
+            let Some(name) = settings.file_name(book.code) else {
+                continue;
+            };
+            let found = files.iter().find(|path| {
+                path.file_name()
+                    .and_then(|file| file.to_str())
+                    .is_some_and(|file| file.eq_ignore_ascii_case(&name))
+            });
+            if let Some(path) = found {
+                let bytes =
+                    fs::read(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+                let usfm = settings
+                    .decode(&bytes)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                sources.push((path.clone(), usfm));
+            }
+        }
+
+        Ok(Self {
+            dir,
+            settings,
+            project: Project::from_sources_with(sources, &catalog),
+            catalog,
+        })
+    }
+
+    /// A validator using the project's stylesheet and versification.
+    pub fn validator(&self) -> Result<Validator, String> {
+        let mut validator = Validator::new().map_err(|e| e.to_string())?;
+        validator.catalog = self.catalog.clone();
+        validator.lint_config.versification = Some(self.settings.versification);
+        Ok(validator)
+    }
+
+    /// Lints every book with [`ParatextProject::validator`].
+    pub fn lint(&self) -> Result<Vec<BookOutput<'_, Vec<Diagnostic>>>, String> {
+        self.project.lint(&mut self.validator()?)
+    }
+
+    /// Converts every book, as decoded from the project's encoding, to USJ
+    /// with the project's stylesheet.
+    pub fn to_usj(&self) -> Result<Vec<BookOutput<'_, Result<String, String>>>, String> {
+        self.project.to_usj_with(&self.catalog)
+    }
+}
+
+/// The number Paratext puts in book file names: `01` to `39` for the Old
+/// Testament, `41` to `67` for the New, then `68` and on, and `A0`, `B0`,
+/// `C0` and on for books after `100`.
+pub fn paratext_book_number(code: &str) -> Option<String> {
+    let number = PARATEXT_BOOK_ORDER
+        .iter()
+        .position(|book| !book.is_empty() && *book == code)?
+        + 1;
+    Some(match number {
+        1..=39 => format!("{:02}", number),
+        40..=99 => (number + 1).to_string(),
+        _ => format!(
+            "{}{}",
+            (b'A' + ((number - 100) / 10) as u8) as char,
+            (number - 100) % 10
+        ),
+    })
+}
+
+// Books in Paratext's numbering, which differs from canonical order after
+// the New Testament. Retired codes keep their numbers as empty places.
+const PARATEXT_BOOK_ORDER: [&str; 123] = [
+    "GEN", "EXO", "LEV", "NUM", "DEU", "JOS", "JDG", "RUT", "1SA", "2SA", "1KI", "2KI", "1CH",
+    "2CH", "EZR", "NEH", "EST", "JOB", "PSA", "PRO", "ECC", "SNG", "ISA", "JER", "LAM", "EZK",
+    "DAN", "HOS", "JOL", "AMO", "OBA", "JON", "MIC", "NAM", "HAB", "ZEP", "HAG", "ZEC", "MAL",
+    "MAT", "MRK", "LUK", "JHN", "ACT", "ROM", "1CO", "2CO", "GAL", "EPH", "PHP", "COL", "1TH",
+    "2TH", "1TI", "2TI", "TIT", "PHM", "HEB", "JAS", "1PE", "2PE", "1JN", "2JN", "3JN", "JUD",
+    "REV", "TOB", "JDT", "ESG", "WIS", "SIR", "BAR", "LJE", "S3Y", "SUS", "BEL", "1MA", "2MA",
+    "3MA", "4MA", "1ES", "2ES", "MAN", "PS2", "ODA", "PSS", "", "", "", "", "", "", "XXA", "XXB",
+    "XXC", "XXD", "XXE", "XXF", "XXG", "FRT", "BAK", "OTH", "", "EZA", "5EZ", "6EZ", "INT", "CNC",
+    "GLO", "TDX", "NDX", "DAG", "PS3", "2BA", "LBA", "JUB", "ENO", "1MQ", "2MQ", "3MQ", "REP",
+    "4BA", "LAO",
+];
+
+// Encodings of the Windows code pages Paratext projects use
+fn code_page_encoding(code_page: u32) -> Option<&'static Encoding> {
+    let label = match code_page {
+        UTF8_CODE_PAGE => "utf-8".to_string(),
+        1200 => "utf-16le".to_string(),
+        1201 => "utf-16be".to_string(),
+        874 | 1250..=1258 => format!("windows-{}", code_page),
+        932 => "shift_jis".to_string(),
+        936 => "gbk".to_string(),
+        949 => "euc-kr".to_string(),
+        950 => "big5".to_string(),
+        20866 => "koi8-r".to_string(),
+        21866 => "koi8-u".to_string(),
+        28591..=28606 => format!("iso-8859-{}", code_page - 28590),
+        _ => return None,
+    };
+    Encoding::for_label(label.as_bytes())
+}
@@ -1,6 +1,12 @@
+use crate::book_info::{book_info, BookInfo};
 use crate::globals::GLOBAL_TREE;
+use crate::stylesheet::{MarkerCatalog, MarkerInfo, StyleType, USFM_CATALOG};
 
+use once_cell::sync::Lazy;
+use std::sync::PoisonError;
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
+use tracing::debug;
 use tree_sitter::Parser;
 
 pub struct USFMParser {
@@ -9,6 +15,12 @@ pub struct USFMParser {
     pub errors: Vec<String>,  // Collects errors during parsing
 }
 
+/// Groups of markers, listed from a stylesheet: [`Filter::value`] reads the
+/// standard one and [`Filter::markers_in`] any other. Compared with the
+/// hand-written lists these replaced, Paragraphs gains `pb`, `tcc` and `thc`
+/// and drops `litl` (a character style) and `table` (not a marker),
+/// Characters gains `addpn`, `fm` and `ndx`, and Notes drops `efe`, which
+/// `usfm.sty` defines only as the end marker of `ef`.
 #[derive(Debug, Clone, EnumIter)]
 pub enum Filter {
     /// Identification and book headers
@@ -31,47 +43,73 @@ pub enum Filter {
     Text,
 }
 
+// Study Bible markers, which no other filter includes
+const STUDY_BIBLE_MARKERS: [&str; 2] = ["esb", "cat"];
+
+// Paragraph and character styles that USJ writes with a type of their own
+// (periph, figure, ref), which no filter includes
+const OWN_TYPE_MARKERS: [&str; 3] = ["periph", "fig", "ref"];
+
+// The markers of each filter in the standard catalog, in the order the
+// filters are declared
+static FILTER_MARKERS: Lazy<Vec<Vec<&'static str>>> = Lazy::new(|| {
+    Filter::iter()
+        .map(|filter| filter.markers_in(&USFM_CATALOG))
+        .collect()
+});
+
 impl Filter {
+    /// The markers of the filter in the standard catalog.
     pub fn value(&self) -> Vec<&'static str> {
-        match self {
-            Filter::BookHeaders => vec![
-                "ide", "usfm", "h", "toc", "toca", // identification
-                "imt", "is", "ip", "ipi", "im", "imi", "ipq", "imq", "ipr", "iq", "ib", "ili",
-                "iot", "io", "iex", "imte", "ie", // intro
-            ],
-
-            Filter::Titles => vec![
-                "mt", "mte", "cl", "cd", "ms", "mr", "s", "sr", "r", "d", "sp", "sd",
-            ],
-
-            Filter::Comments => vec!["sts", "rem", "lit", "restore"],
-
-            Filter::Paragraphs => vec![
-                "p", "m", "po", "pr", "cls", "pmo", "pm", "pmc", "pmr", "pi", "mi", "nb", "pc",
-                "ph", "q", "qr", "qc", "qa", "qm", "qd", "lh", "li", "lf", "lim", "litl", "tr",
-                "tc", "th", "tcr", "thr", "table", "b",
-            ],
-
-            Filter::Characters => vec![
-                // Special-text
-                "add", "bk", "dc", "ior", "iqt", "k", "litl", "nd", "ord", "pn", "png", "qac", "qs",
-                "qt", "rq", "sig", "sls", "tl", "wj", // character styling
-                "em", "bd", "bdit", "it", "no", "sc", "sup", // special-features
-                "rb", "pro", "w", "wh", "wa", "wg", // structured list entries
-                "lik", "liv", "jmp",
-            ],
-
-            Filter::Notes => vec![
-                // footnotes and crossrefs
-                "f", "fe", "ef", "efe", "x", "ex", "fr", "ft", "fk", "fq", "fqa", "fl", "fw", "fp",
-                "fv", "fdc", "xo", "xop", "xt", "xta", "xk", "xq", "xot", "xnt", "xdc",
-            ],
-
-            Filter::StudyBible => vec!["esb", "cat"],
-
-            Filter::BCV => vec!["id", "c", "v"],
-
-            Filter::Text => vec!["text-in-excluded-parent"],
+        FILTER_MARKERS[self.clone() as usize].clone()
+    }
+
+    /// The markers of the filter in `catalog`, e.g. one extended with a
+    /// project's `custom.sty`.
+    pub fn markers_in<'c>(&self, catalog: &'c MarkerCatalog) -> Vec<&'c str> {
+        let mut markers: Vec<&'c str> = match self {
+            // Structural groups that a stylesheet does not describe
+            Filter::BCV => return vec!["id", "c", "v"],
+            Filter::StudyBible => return STUDY_BIBLE_MARKERS.to_vec(),
+            Filter::Text => return vec!["text-in-excluded-parent"],
+            _ => catalog
+                .markers()
+                .filter(|info| self.includes(catalog, info))
+                .map(|info| info.base.as_str())
+                .collect(),
+        };
+        markers.sort_unstable();
+        markers.dedup();
+        markers
+    }
+
+    fn includes(&self, catalog: &MarkerCatalog, info: &MarkerInfo) -> bool {
+        let is_bcv = info.is_text_type("ChapterNumber")
+            || info.is_text_type("VerseNumber")
+            || (info.has_text_property("book") && info.occurs_under.is_empty());
+        let is_study = STUDY_BIBLE_MARKERS.contains(&info.base.as_str());
+        let is_own_type = OWN_TYPE_MARKERS.contains(&info.base.as_str());
+        let is_table_cell = info.occurs_under.iter().any(|parent| parent == "tr");
+        let is_header = info.has_text_property("book") || info.has_text_property("introduction");
+        if is_bcv || is_study || is_own_type {
+            return false;
+        }
+        match (self, info.style_type) {
+            (Filter::BookHeaders, Some(StyleType::Paragraph)) => is_header,
+            (Filter::Titles, Some(StyleType::Paragraph)) => {
+                !is_header && (info.is_text_type("Title") || info.is_text_type("Section"))
+            }
+            (Filter::Comments, Some(StyleType::Paragraph)) => {
+                !is_header && info.is_text_type("Other")
+            }
+            (Filter::Paragraphs, Some(StyleType::Paragraph)) => info.is_text_type("VerseText"),
+            (Filter::Paragraphs, Some(StyleType::Character)) => is_table_cell,
+            (Filter::Characters, Some(StyleType::Character)) => {
+                !is_table_cell && !catalog.is_note_content(&info.marker)
+            }
+            (Filter::Notes, Some(StyleType::Note)) => true,
+            (Filter::Notes, Some(StyleType::Character)) => catalog.is_note_content(&info.marker),
+            _ => false,
         }
     }
 }
@@ -81,7 +119,7 @@ impl USFMParser {
         let mut parser = Parser::new();
         parser
             .set_language(&tree_sitter_usfm3::language())
-            .expect("Error loading Rust grammar");
+            .map_err(|e| format!("Error loading USFM grammar: {}", e))?;
         Ok(Self {
             parser,
             usfm: None,
@@ -91,13 +129,8 @@ impl USFMParser {
 
     //parsing function
     pub fn parse_usfm(&mut self, usfm: &str) -> Result<tree_sitter::Tree, String> {
-        self.usfm = Some(usfm.to_string());
-        self.errors.clear();
-
-        if let Some(tree) = self.parser.parse(usfm, None) {
+        if let Some(tree) = self.parse_tree(usfm) {
             let root_node = tree.root_node();
-            let mut global_tree = GLOBAL_TREE.lock().unwrap();
-            *global_tree = Some(tree.clone());
             if root_node.has_error() {
                 let mut error_messages = Vec::new();
                 //let  _cursor = root_node.walk();
@@ -109,11 +142,13 @@ impl USFMParser {
                             "At Point(row={}, column={}):{}",
                             start.row,
                             start.column,
-                            &usfm[node.start_byte()..node.end_byte()]
+                            usfm.get(node.start_byte()..node.end_byte())
+                                .unwrap_or_default()
                         ));
                     }
                 });
 
+                debug!(errors = error_messages.len(), "syntax errors");
                 let error = format!("Errors present:\n\t{}", error_messages.join("\n\t"));
                 self.errors.push(error.clone());
                 return Err(error);
@@ -126,6 +161,22 @@ impl USFMParser {
         }
     }
 
+    /// Parses and keeps the tree for the USJ generator, syntax errors and all.
+    pub fn parse_tree(&mut self, usfm: &str) -> Option<tree_sitter::Tree> {
+        self.usfm = Some(usfm.to_string());
+        self.errors.clear();
+        let tree = self.parser.parse(usfm, None)?;
+        // A thread that panicked while holding the lock left nothing half-written
+        let mut global_tree = GLOBAL_TREE.lock().unwrap_or_else(PoisonError::into_inner);
+        *global_tree = Some(tree.clone());
+        Some(tree)
+    }
+
+    /// The header of the parsed book, read without converting it.
+    pub fn book_info(&self) -> Option<BookInfo> {
+        book_info(self.usfm.as_deref()?, &USFM_CATALOG)
+    }
+
     fn visit_nodes<F>(&self, node: &tree_sitter::Node, visit: &mut F)
     where
         F: FnMut(&tree_sitter::Node),
@@ -0,0 +1,218 @@
+use crate::diagnostics::Diagnostic;
+use crate::scanner::{scan_markers, word_after, MarkerToken};
+use crate::stylesheet::{base_marker, MarkerCatalog, MarkerInfo, StyleType};
+
+// How many of the allowed parents to spell out in a message
+const MAX_LISTED_PARENTS: usize = 8;
+
+#[derive(Default)]
+struct Context {
+    chapter: Option<String>,
+    verse_in_chapter: bool,
+    paragraph: Option<String>,
+    // Heading not yet followed by body text, which may take a section introduction
+    heading: Option<String>,
+    cell: Option<String>,
+    note: Option<String>,
+    // The opening marker just before the current one (`\vp` follows `\v`)
+    previous: Option<String>,
+}
+
+impl Context {
+    // Paragraph-level markers sit under the book (`id`) until the first chapter
+    fn book_level(&self) -> &str {
+        if self.chapter.is_some() {
+            "c"
+        } else {
+            "id"
+        }
+    }
+
+    // The innermost open paragraph, table cell or note
+    fn inline_parent(&self) -> &str {
+        self.note
+            .as_deref()
+            .or(self.cell.as_deref())
+            .or(self.paragraph.as_deref())
+            .unwrap_or_else(|| self.book_level())
+    }
+}
+
+/// Checks every marker against the `\OccursUnder` rules of the catalog and
+/// reports those used outside the contexts their stylesheet allows.
+pub fn check_placement(usfm: &str, catalog: &MarkerCatalog) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut context = Context::default();
+
+    for token in scan_markers(usfm) {
+        if token.marker.starts_with('z') {
+            continue;
+        }
+        if token.closing {
+            if context.note.as_deref() == Some(token.marker.as_str()) {
+                context.note = None;
+            }
+            continue;
+        }
+        let Some(info) = catalog.get(&token.marker) else {
+            continue;
+        };
+
+        match info.style_type {
+            Some(StyleType::Paragraph) => {
+                check_paragraph(&token, info, &context, &mut diagnostics);
+                match info.marker.as_str() {
+                    "id" => {}
+                    "c" => {
+                        context.chapter = Some(word_after(usfm, &token).to_string());
+                        context.verse_in_chapter = false;
+                        context.paragraph = Some("c".to_string());
+                        context.heading = None;
+                    }
+                    "cl" => {}
+                    _ if info.is_text_type("ChapterNumber") => {}
+                    _ => {
+                        if info.is_text_type("Section") {
+                            context.heading = Some(token.marker.clone());
+                        } else if info.is_text_type("VerseText") {
+                            context.heading = None;
+                        }
+                        context.paragraph = Some(token.marker.clone());
+                    }
+                }
+                context.cell = None;
+                context.note = None;
+            }
+            Some(StyleType::Note) => {
+                check_inline(&token, info, &context, catalog, &mut diagnostics);
+                context.note = Some(token.marker.clone());
+            }
+            Some(StyleType::Character) => {
+                check_inline(&token, info, &context, catalog, &mut diagnostics);
+                if token.marker == "v" {
+                    context.verse_in_chapter = true;
+                }
+                if info.occurs_under.iter().any(|parent| parent == "tr") {
+                    context.cell = Some(token.marker.clone());
+                }
+            }
+            Some(StyleType::Milestone) | None => {}
+        }
+        context.previous = Some(token.marker);
+    }
+    diagnostics
+}
+
+fn check_paragraph(
+    token: &MarkerToken,
+    info: &MarkerInfo,
+    context: &Context,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    // \c always belongs to the book, whatever chapter precedes it
+    if info.marker == "c" {
+        return;
+    }
+    if info.marker == "cl" && context.verse_in_chapter {
+        let chapter = context.chapter.as_deref().unwrap_or_default();
+        diagnostics.push(Diagnostic::at_marker(
+            "marker-placement",
+            format!(
+                "\\cl must directly follow \\c {}, before the chapter's first verse",
+                chapter
+            ),
+            token,
+        ));
+        return;
+    }
+    let parent = context.book_level();
+    let in_section_intro = context
+        .heading
+        .as_deref()
+        .is_some_and(|heading| occurs_under(info, heading));
+    if info.occurs_under.is_empty() || occurs_under(info, parent) || in_section_intro {
+        return;
+    }
+    let message = if info.occurs_under.iter().all(|p| p == "id") {
+        format!(
+            "\\{} must occur in the book introduction, before the first \\c",
+            token.marker
+        )
+    } else if info.occurs_under.iter().all(|p| p == "c") {
+        format!("\\{} must occur after the first \\c", token.marker)
+    } else {
+        misplaced_message(token, info, parent)
+    };
+    diagnostics.push(Diagnostic::at_marker("marker-placement", message, token));
+}
+
+fn check_inline(
+    token: &MarkerToken,
+    info: &MarkerInfo,
+    context: &Context,
+    catalog: &MarkerCatalog,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let is_cell = info.occurs_under.iter().any(|p| p == "tr");
+    // A cell closes the previous one, so cells are checked against the row itself
+    let parent = match (is_cell, context.paragraph.as_deref()) {
+        (true, Some(row)) => row,
+        _ => context.inline_parent(),
+    };
+    let follows_parent = context
+        .previous
+        .as_deref()
+        .is_some_and(|previous| occurs_under(info, previous));
+    if info.occurs_under.is_empty() || occurs_under(info, parent) || follows_parent {
+        return;
+    }
+    let only_in_notes = info.occurs_under.iter().all(|p| catalog.is_note(p));
+    let message = if context.note.is_none() && only_in_notes {
+        format!(
+            "\\{} must occur inside a note ({})",
+            token.marker,
+            list_markers(&info.occurs_under)
+        )
+    } else if is_cell {
+        format!("\\{} must occur in a table row (\\tr)", token.marker)
+    } else {
+        misplaced_message(token, info, parent)
+    };
+    diagnostics.push(Diagnostic::at_marker("marker-placement", message, token));
+}
+
+// Levels are not significant for placement: `\v` listed under `q1` is also fine under `q5`
+fn occurs_under(info: &MarkerInfo, parent: &str) -> bool {
+    info.occurs_under
+        .iter()
+        .any(|allowed| allowed == parent || base_marker(allowed) == base_marker(parent))
+}
+
+fn misplaced_message(token: &MarkerToken, info: &MarkerInfo, parent: &str) -> String {
+    format!(
+        "\\{} is not allowed under \\{}; expected under {}",
+        token.marker,
+        parent,
+        list_markers(&info.occurs_under)
+    )
+}
+
+fn list_markers(markers: &[String]) -> String {
+    let mut bases: Vec<&str> = Vec::new();
+    for marker in markers {
+        let base = base_marker(marker);
+        if !bases.contains(&base) {
+            bases.push(base);
+        }
+    }
+    let listed: Vec<String> = bases
+        .iter()
+        .take(MAX_LISTED_PARENTS)
+        .map(|base| format!("\\{}", base))
+        .collect();
+    if bases.len() > MAX_LISTED_PARENTS {
+        format!("{} and {} more", listed.join(", "), bases.len() - MAX_LISTED_PARENTS)
+    } else {
+        listed.join(", ")
+    }
+}
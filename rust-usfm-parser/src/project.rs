@@ -0,0 +1,248 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::book_info::{book_info, BookInfo};
+use crate::books::{is_book_code, sort_by_canon};
+use crate::diagnostics::Diagnostic;
+use crate::parser::USFMParser;
+use crate::scanner::scan_markers;
+use crate::stylesheet::{MarkerCatalog, USFM_CATALOG};
+use crate::usj_generator::{write_usj_with, UsjOptions};
+use crate::validator::Validator;
+use crate::verse_text::extract_verse_text;
+
+// Extensions of the files a directory is searched for, compared ignoring case
+const USFM_EXTENSIONS: [&str; 2] = ["usfm", "sfm"];
+
+/// One book of a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectBook {
+    /// Book code from `\id`, in upper case when it is a known code
+    pub code: String,
+    pub path: PathBuf,
+    pub usfm: String,
+    pub info: BookInfo,
+}
+
+/// A book with what a project-wide operation gave for it.
+pub type BookOutput<'a, T> = (&'a ProjectBook, T);
+
+/// Files that give the same book code; the first is the one in
+/// [`Project::books`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateBook {
+    pub code: String,
+    pub paths: Vec<PathBuf>,
+}
+
+/// Counts for one book, or for a whole project.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Statistics {
+    pub books: usize,
+    pub chapters: usize,
+    /// `\v` markers; a span such as `\v 1-3` counts once
+    pub verses: usize,
+    /// Words of verse text, as separated by whitespace
+    pub words: usize,
+}
+
+impl std::ops::AddAssign for Statistics {
+    fn add_assign(&mut self, other: Self) {
+        self.books += other.books;
+        self.chapters += other.chapters;
+        self.verses += other.verses;
+        self.words += other.words;
+    }
+}
+
+/// The USFM books of a folder, in canonical order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Project {
+    pub books: Vec<ProjectBook>,
+    pub duplicates: Vec<DuplicateBook>,
+    /// Files without an `\id`
+    pub unidentified: Vec<PathBuf>,
+}
+
+impl Project {
+    /// Loads the `.usfm` and `.sfm` files of a directory (not its
+    /// subdirectories).
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Self, String> {
+        let dir = dir.as_ref();
+        let entries =
+            fs::read_dir(dir).map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?;
+        let mut paths = Vec::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?
+                .path();
+            let is_usfm = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    USFM_EXTENSIONS
+                        .iter()
+                        .any(|usfm| ext.eq_ignore_ascii_case(usfm))
+                });
+            if is_usfm && path.is_file() {
+                paths.push(path);
+            }
+        }
+        Self::from_files(paths)
+    }
+
+    /// Loads the files matching a glob pattern such as `books/*.usfm`.
+    pub fn load_glob(pattern: &str) -> Result<Self, String> {
+        let paths = glob::glob(pattern)
+            .map_err(|e| format!("Invalid pattern \"{}\": {}", pattern, e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Self::from_files(paths)
+    }
+
+    pub fn from_files<I, P>(paths: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let sources = paths
+            .into_iter()
+            .map(|path| {
+                let path = path.as_ref().to_path_buf();
+                let usfm = fs::read_to_string(&path)
+                    .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+                Ok((path, usfm))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self::from_sources(sources))
+    }
+
+    /// Builds a project from files already read, given with their paths.
+    pub fn from_sources(sources: Vec<(PathBuf, String)>) -> Self {
+        Self::from_sources_with(sources, &USFM_CATALOG)
+    }
+
+    /// Builds a project as [`Project::from_sources`] does, reading each
+    /// book's header with the markers of `catalog`.
+    pub fn from_sources_with(mut sources: Vec<(PathBuf, String)>, catalog: &MarkerCatalog) -> Self {
+        sources.sort();
+        let mut project = Project::default();
+        for (path, usfm) in sources {
+            match book_info(&usfm, catalog) {
+                Some(info) => project.add_book(path, usfm, info),
+                None => project.unidentified.push(path),
+            }
+        }
+        sort_by_canon(&mut project.books, |book| &book.code);
+        sort_by_canon(&mut project.duplicates, |duplicate| &duplicate.code);
+        project
+    }
+
+    fn add_book(&mut self, path: PathBuf, usfm: String, info: BookInfo) {
+        let code = match info.code.to_uppercase() {
+            upper if is_book_code(&upper) => upper,
+            _ => info.code.clone(),
+        };
+        let Some(first) = self.books.iter().find(|book| book.code == code) else {
+            self.books.push(ProjectBook {
+                code,
+                path,
+                usfm,
+                info,
+            });
+            return;
+        };
+        match self
+            .duplicates
+            .iter_mut()
+            .find(|duplicate| duplicate.code == code)
+        {
+            Some(duplicate) => duplicate.paths.push(path),
+            None => {
+                let paths = vec![first.path.clone(), path];
+                self.duplicates.push(DuplicateBook { code, paths });
+            }
+        }
+    }
+
+    pub fn book(&self, code: &str) -> Option<&ProjectBook> {
+        self.books.iter().find(|book| book.code == code)
+    }
+
+    /// Converts every book to USJ. A book the grammar cannot parse gives
+    /// the parse errors instead.
+    pub fn to_usj(&self) -> Result<Vec<BookOutput<'_, Result<String, String>>>, String> {
+        self.to_usj_with(&USFM_CATALOG)
+    }
+
+    /// Converts every book to USJ as [`Project::to_usj`] does, with the
+    /// markers of `catalog`.
+    pub fn to_usj_with(
+        &self,
+        catalog: &MarkerCatalog,
+    ) -> Result<Vec<BookOutput<'_, Result<String, String>>>, String> {
+        let mut parser = USFMParser::new().map_err(|e| e.to_string())?;
+        let options = UsjOptions {
+            catalog,
+            ..UsjOptions::default()
+        };
+        Ok(self
+            .books
+            .iter()
+            .map(|book| {
+                let usj = parser.parse_usfm(&book.usfm).and_then(|tree| {
+                    let mut usj = Vec::new();
+                    write_usj_with(&tree, &book.usfm, &mut usj, options)
+                        .map_err(|e| e.to_string())?;
+                    String::from_utf8(usj).map_err(|e| e.to_string())
+                });
+                (book, usj)
+            })
+            .collect())
+    }
+
+    /// Lints every book with the validator's stylesheet and configuration.
+    pub fn lint(
+        &self,
+        validator: &mut Validator,
+    ) -> Result<Vec<BookOutput<'_, Vec<Diagnostic>>>, String> {
+        self.books
+            .iter()
+            .map(|book| Ok((book, validator.lint(&book.usfm)?)))
+            .collect()
+    }
+
+    /// Counts for each book, in canonical order.
+    pub fn book_statistics(&self) -> Vec<BookOutput<'_, Statistics>> {
+        self.books
+            .iter()
+            .map(|book| (book, book_statistics(&book.usfm)))
+            .collect()
+    }
+
+    /// Counts for the whole project.
+    pub fn statistics(&self) -> Statistics {
+        let mut total = Statistics::default();
+        for (_, statistics) in self.book_statistics() {
+            total += statistics;
+        }
+        total
+    }
+}
+
+fn book_statistics(usfm: &str) -> Statistics {
+    let chapters = scan_markers(usfm)
+        .iter()
+        .filter(|token| token.marker == "c" && !token.closing)
+        .count();
+    let verses = extract_verse_text(usfm, &USFM_CATALOG);
+    Statistics {
+        books: 1,
+        chapters,
+        verses: verses.len(),
+        words: verses
+            .iter()
+            .map(|verse| verse.text.split_whitespace().count())
+            .sum(),
+    }
+}
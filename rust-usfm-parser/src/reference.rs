@@ -0,0 +1,527 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::attributes::parse_attributes;
+use crate::books::{book, book_name, Versification, BOOKS};
+use crate::diagnostics::Diagnostic;
+use crate::scanner::scan_markers;
+use crate::stylesheet::MarkerCatalog;
+
+// Common abbreviations that are not the start of the book's English name
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("gn", "GEN"),
+    ("ex", "EXO"),
+    ("lv", "LEV"),
+    ("nm", "NUM"),
+    ("dt", "DEU"),
+    ("jg", "JDG"),
+    ("ps", "PSA"),
+    ("pss", "PSA"),
+    ("prv", "PRO"),
+    ("qoh", "ECC"),
+    ("sos", "SNG"),
+    ("ezek", "EZK"),
+    ("mt", "MAT"),
+    ("mk", "MRK"),
+    ("mr", "MRK"),
+    ("lk", "LUK"),
+    ("jn", "JHN"),
+    ("phil", "PHP"),
+    ("phlm", "PHM"),
+    ("jas", "JAS"),
+    ("jude", "JUD"),
+    ("rv", "REV"),
+];
+
+// `1:2-3`, `4a`, `5.6b–7:8` and the like, after the book
+static RANGE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(\d+)(?:\s*[:.]\s*(\d+))?([a-z]?)(?:\s*[-\u{2013}\u{2014}]\s*(\d+)(?:\s*[:.]\s*(\d+))?([a-z]?))?$",
+    )
+    .unwrap()
+});
+
+// `4`, `4a`, `1-3` or `2b-6a`, as written after `\v`
+static VERSE_NUMBER_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\d+)([a-z]?)(?:[-\u{2013}](\d+)([a-z]?))?$").unwrap());
+
+/// The code of a book given by its code, English name or a common
+/// abbreviation, ignoring case, spaces and full stops (`Mat`, `1 Cor.`).
+/// A partial name matches the first book, in canonical order, it starts.
+pub fn book_code(name: &str) -> Option<&'static str> {
+    let key = name_key(name);
+    if key.is_empty() {
+        return None;
+    }
+    BOOKS
+        .iter()
+        .find(|book| book.code.eq_ignore_ascii_case(&key))
+        .or_else(|| BOOKS.iter().find(|book| name_key(book.name) == key))
+        .map(|book| book.code)
+        .or_else(|| {
+            ABBREVIATIONS
+                .iter()
+                .find(|(abbreviation, _)| *abbreviation == key)
+                .map(|(_, code)| *code)
+        })
+        .or_else(|| {
+            let (number, letters) = key
+                .split_at(key.len() - key.trim_start_matches(|c: char| c.is_ascii_digit()).len());
+            if letters.chars().count() < 2 || (number.is_empty() && letters.chars().count() < 3) {
+                return None;
+            }
+            BOOKS
+                .iter()
+                .find(|book| name_key(book.name).starts_with(&key))
+                .map(|book| book.code)
+        })
+}
+
+fn is_single_chapter(code: &str) -> bool {
+    book(code).and_then(|book| book.chapter_count(Versification::Original)) == Some(1)
+}
+
+fn name_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// A chapter, or a verse with an optional segment letter, in a book.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub book: String,
+    pub chapter: u32,
+    pub verse: Option<u32>,
+    /// Part of the verse, such as the `a` of `4a`
+    pub segment: Option<String>,
+}
+
+impl Location {
+    fn key(&self) -> (u32, u32) {
+        (self.chapter, self.verse.unwrap_or(0))
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = ReferenceRange {
+            start: self.clone(),
+            end: self.clone(),
+        };
+        write!(f, "{}", range)
+    }
+}
+
+/// A passage from `start` to `end`, both included. A single verse or
+/// chapter has the same start and end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceRange {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl ReferenceRange {
+    pub fn is_single(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl fmt::Display for ReferenceRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let formatted = format_references(std::slice::from_ref(self), &ReferenceStyle::default());
+        write!(f, "{}", formatted)
+    }
+}
+
+/// The number of a `\v`: a verse (`4`), part of one (`4a`) or a span
+/// (`1-3`, `6b-11`). A single verse has the same start and end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerseNumber {
+    pub start: u32,
+    pub end: u32,
+    /// Segment letter of the first verse, such as the `b` of `6b-11`
+    pub start_segment: Option<String>,
+    /// Segment letter of the last verse, such as the `a` of `2-6a`
+    pub end_segment: Option<String>,
+}
+
+impl VerseNumber {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let captures = VERSE_NUMBER_PATTERN
+            .captures(text.trim())
+            .ok_or_else(|| format!("\"{}\" is not a verse number", text))?;
+        let number = |index: usize| {
+            captures
+                .get(index)
+                .map(|number| number.as_str().parse::<u32>())
+                .transpose()
+                .map_err(|_| format!("\"{}\" is not a verse number", text))
+        };
+        let segment = |index: usize| {
+            captures
+                .get(index)
+                .map(|segment| segment.as_str().to_string())
+                .filter(|segment| !segment.is_empty())
+        };
+
+        let start = number(1)?.unwrap_or_default();
+        let (end, end_segment) = match number(3)? {
+            Some(end) => (end, segment(4)),
+            None => (start, segment(2)),
+        };
+        let verse = Self {
+            start,
+            end,
+            start_segment: segment(2),
+            end_segment,
+        };
+        if (verse.end, verse.end_segment.as_deref()) < (verse.start, verse.start_segment.as_deref())
+        {
+            return Err(format!("\"{}\" ends before it starts", text));
+        }
+        Ok(verse)
+    }
+
+    /// Whether it numbers more than one verse.
+    pub fn is_span(&self) -> bool {
+        self.end > self.start
+    }
+
+    /// The verses it covers, including any it only covers part of.
+    pub fn verses(&self) -> RangeInclusive<u32> {
+        self.start..=self.end
+    }
+
+    pub fn covers(&self, verse: u32) -> bool {
+        self.verses().contains(&verse)
+    }
+
+    /// The passage it numbers in a chapter of a book.
+    pub fn range(&self, book: &str, chapter: u32) -> ReferenceRange {
+        let location = |verse: u32, segment: &Option<String>| Location {
+            book: book.to_string(),
+            chapter,
+            verse: Some(verse),
+            segment: segment.clone(),
+        };
+        ReferenceRange {
+            start: location(self.start, &self.start_segment),
+            end: location(self.end, &self.end_segment),
+        }
+    }
+}
+
+impl FromStr for VerseNumber {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        Self::parse(text)
+    }
+}
+
+impl fmt::Display for VerseNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.start)?;
+        if let Some(segment) = &self.start_segment {
+            write!(f, "{}", segment)?;
+        }
+        if (self.end, &self.end_segment) != (self.start, &self.start_segment) {
+            write!(f, "-{}", self.end)?;
+            if let Some(segment) = &self.end_segment {
+                write!(f, "{}", segment)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How books are written when formatting references.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookFormat {
+    /// `1CO`
+    Code,
+    /// `1 Corinthians`
+    Name,
+}
+
+/// Punctuation and book names used by [`format_references`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceStyle {
+    pub book: BookFormat,
+    /// Between chapter and verse
+    pub chapter_verse: String,
+    /// Between the start and end of a range
+    pub range: String,
+    /// Between verses of the same chapter
+    pub verse_list: String,
+    /// Between other references
+    pub list: String,
+}
+
+impl Default for ReferenceStyle {
+    /// `GEN 1:1-3, 5; 2:4`, as in `\ref` targets.
+    fn default() -> Self {
+        Self {
+            book: BookFormat::Code,
+            chapter_verse: ":".to_string(),
+            range: "-".to_string(),
+            verse_list: ", ".to_string(),
+            list: "; ".to_string(),
+        }
+    }
+}
+
+impl ReferenceStyle {
+    /// `Genesis 1:1–3, 5; 2:4`
+    pub fn english() -> Self {
+        Self {
+            book: BookFormat::Name,
+            range: "\u{2013}".to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Parses references such as `GEN 1:1-3; 2:4`, `Mat 5.3` or `1Co 13:4a-7`.
+/// Every reference needs a book, unless an earlier one in the list has it.
+pub fn parse_references(text: &str) -> Result<Vec<ReferenceRange>, String> {
+    parse_references_in(text, None)
+}
+
+/// Parses references, taking `book` for those that do not name one (as in
+/// the `1:4` of an `\xo` or `\fr`).
+pub fn parse_references_in(text: &str, book: Option<&str>) -> Result<Vec<ReferenceRange>, String> {
+    let mut ranges = Vec::new();
+    let mut book = book.map(str::to_string);
+    if text.trim().is_empty() {
+        return Err("Empty reference".to_string());
+    }
+
+    for group in text.split(';').map(str::trim) {
+        if group.is_empty() {
+            return Err(format!("Empty reference in \"{}\"", text));
+        }
+        let (name, rest) = split_book(group);
+        if let Some(name) = name {
+            let code = book_code(name).ok_or_else(|| format!("Unknown book \"{}\"", name))?;
+            book = Some(code.to_string());
+        }
+        let book = book
+            .as_deref()
+            .ok_or_else(|| format!("\"{}\" does not name a book", group))?;
+
+        // Verses after a comma belong to the chapter before it
+        let mut previous: Option<Location> = None;
+        for item in rest.split(',').map(str::trim) {
+            let range = parse_range(item, book, previous.as_ref())
+                .ok_or_else(|| format!("\"{}\" is not a reference", group))?;
+            if range.end.book == range.start.book && range.end.key() < range.start.key() {
+                return Err(format!("\"{}\" ends before it starts", group));
+            }
+            previous = Some(range.end.clone());
+            ranges.push(range);
+        }
+    }
+    Ok(ranges)
+}
+
+// Splits a book name from the chapter and verse after it
+fn split_book(group: &str) -> (Option<&str>, &str) {
+    let first = group.split_whitespace().next().unwrap_or_default();
+    if BOOKS
+        .iter()
+        .any(|book| book.code.eq_ignore_ascii_case(first))
+    {
+        return (Some(first), group[first.len()..].trim());
+    }
+    // The name runs up to the first digit after a letter (`1 Cor 13`)
+    let mut seen_letter = false;
+    for (index, c) in group.char_indices() {
+        if c.is_ascii_digit() && seen_letter {
+            return (Some(group[..index].trim()), group[index..].trim());
+        }
+        seen_letter |= c.is_alphabetic();
+    }
+    if seen_letter {
+        (Some(group), "")
+    } else {
+        (None, group)
+    }
+}
+
+fn parse_range(item: &str, book: &str, previous: Option<&Location>) -> Option<ReferenceRange> {
+    let captures = RANGE_PATTERN.captures(item)?;
+    let number = |index: usize| {
+        captures.get(index).map(|number| {
+            number
+                .as_str()
+                .parse::<u32>()
+                .ok()
+                .filter(|&number| number > 0)
+        })
+    };
+    let segment = |index: usize| {
+        captures
+            .get(index)
+            .map(|segment| segment.as_str().to_string())
+            .filter(|segment| !segment.is_empty())
+    };
+    let location = |chapter: u32, verse: Option<u32>, segment: Option<String>| Location {
+        book: book.to_string(),
+        chapter,
+        verse,
+        segment,
+    };
+
+    let first = number(1)??;
+    let start = match (number(2), previous) {
+        (Some(verse), _) => location(first, Some(verse?), segment(3)),
+        // `5` after `1:3,` is verse 5 of chapter 1
+        (None, Some(previous)) if previous.verse.is_some() => {
+            location(previous.chapter, Some(first), segment(3))
+        }
+        // Books of one chapter may leave it out
+        (None, _) if is_single_chapter(book) => location(1, Some(first), segment(3)),
+        (None, _) => location(first, None, segment(3)),
+    };
+    let end = match (number(4), number(5)) {
+        (None, _) => start.clone(),
+        (Some(chapter), Some(verse)) => location(chapter?, Some(verse?), segment(6)),
+        // `1:1-3` ends at a verse, `1-3` at a chapter
+        (Some(last), None) if start.verse.is_some() => {
+            location(start.chapter, Some(last?), segment(6))
+        }
+        (Some(last), None) => location(last?, None, segment(6)),
+    };
+    Some(ReferenceRange { start, end })
+}
+
+/// Writes references back as text, leaving out the book (and chapter) when
+/// it is the same as in the reference before.
+pub fn format_references(ranges: &[ReferenceRange], style: &ReferenceStyle) -> String {
+    let mut formatted = String::new();
+    let mut previous: Option<&Location> = None;
+
+    for range in ranges {
+        let start = &range.start;
+        match previous {
+            Some(previous)
+                if previous.book == start.book
+                    && previous.chapter == start.chapter
+                    && previous.verse.is_some()
+                    && start.verse.is_some() =>
+            {
+                formatted.push_str(&style.verse_list);
+                push_verse(&mut formatted, start);
+            }
+            Some(previous) if previous.book == start.book => {
+                formatted.push_str(&style.list);
+                push_chapter_verse(&mut formatted, start, style);
+            }
+            _ => {
+                if previous.is_some() {
+                    formatted.push_str(&style.list);
+                }
+                push_book(&mut formatted, &start.book, style);
+                push_chapter_verse(&mut formatted, start, style);
+            }
+        }
+
+        let end = &range.end;
+        if !range.is_single() {
+            formatted.push_str(&style.range);
+            if end.book != start.book {
+                push_book(&mut formatted, &end.book, style);
+                push_chapter_verse(&mut formatted, end, style);
+            } else if end.chapter == start.chapter && start.verse.is_some() {
+                push_verse(&mut formatted, end);
+            } else {
+                push_chapter_verse(&mut formatted, end, style);
+            }
+        }
+        previous = Some(end);
+    }
+    formatted
+}
+
+fn push_book(formatted: &mut String, book: &str, style: &ReferenceStyle) {
+    match style.book {
+        BookFormat::Name => formatted.push_str(book_name(book).unwrap_or(book)),
+        BookFormat::Code => formatted.push_str(book),
+    }
+    formatted.push(' ');
+}
+
+fn push_chapter_verse(formatted: &mut String, location: &Location, style: &ReferenceStyle) {
+    formatted.push_str(&location.chapter.to_string());
+    if location.verse.is_some() {
+        formatted.push_str(&style.chapter_verse);
+        push_verse(formatted, location);
+    }
+}
+
+fn push_verse(formatted: &mut String, location: &Location) {
+    if let Some(verse) = location.verse {
+        formatted.push_str(&verse.to_string());
+    }
+    formatted.push_str(location.segment.as_deref().unwrap_or_default());
+}
+
+/// Checks that the `loc` of `\ref` and the `href` or `link-href` of `\xt`
+/// are references this module can read.
+pub fn check_references(usfm: &str, catalog: &MarkerCatalog) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let tokens = scan_markers(usfm);
+    for (index, token) in tokens.iter().enumerate() {
+        let names: &[&str] = match token.marker.as_str() {
+            _ if token.closing => continue,
+            "ref" => &["loc"],
+            "xt" => &["href", "link-href"],
+            _ => continue,
+        };
+        // \xt in a note needs no closer, so its text ends at the next marker
+        let end = tokens
+            .get(index + 1)
+            .map_or(usfm.len(), |next| next.start_byte);
+        let Some((_, list)) = usfm[token.end_byte..end].rsplit_once('|') else {
+            continue;
+        };
+        for (name, value) in parse_attributes(list, &token.marker, catalog) {
+            if !names.contains(&name.as_str()) {
+                continue;
+            }
+            let Some(target) = reference_target(&value) else {
+                continue;
+            };
+            if let Err(problem) = parse_references(target) {
+                diagnostics.push(Diagnostic::at_marker(
+                    "invalid-reference",
+                    format!(
+                        "\\{} {} \"{}\" is not a valid reference: {}",
+                        token.marker, name, value, problem
+                    ),
+                    token,
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+// The scripture reference in a link target: `GEN 1:1`, or `prj:RSV52 GEN 1:1`
+// for another project. Links to articles (`#...`) and web pages are not.
+fn reference_target(value: &str) -> Option<&str> {
+    let value = value.trim();
+    if value.starts_with('#') || value.contains("://") {
+        None
+    } else if let Some(project) = value.strip_prefix("prj:") {
+        project.split_once(' ').map(|(_, target)| target)
+    } else {
+        Some(value)
+    }
+}
@@ -0,0 +1,1029 @@
+//! A RELAX NG validator for the subset of the XML syntax used by
+//! `schemas/usx.rng`, following James Clark's derivative algorithm
+//! (<https://relaxng.org/jclark/derivative.html>). Patterns are interned so
+//! that equal patterns share an id, which keeps derivatives small and lets
+//! them be memoized.
+
+use regex::Regex;
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::fmt;
+
+const RNG_NS: &str = "http://relaxng.org/ns/structure/1.0";
+
+// How many alternatives to list when something is not allowed
+const MAX_EXPECTED: usize = 8;
+
+/// A validation error, positioned by the one-based line and column of the
+/// offending element, attribute or text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelaxNgError {
+    pub message: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for RelaxNgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "At line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+#[derive(Debug)]
+enum NameClass {
+    Name(String, String),
+    AnyName(Option<Box<NameClass>>),
+    NsName(String, Option<Box<NameClass>>),
+    Choice(Box<NameClass>, Box<NameClass>),
+}
+
+impl NameClass {
+    fn contains(&self, ns: &str, local: &str) -> bool {
+        let excluded = |except: &Option<Box<NameClass>>| {
+            except
+                .as_ref()
+                .is_some_and(|except| except.contains(ns, local))
+        };
+        match self {
+            NameClass::Name(name_ns, name) => name_ns == ns && name == local,
+            NameClass::AnyName(except) => !excluded(except),
+            NameClass::NsName(name_ns, except) => name_ns == ns && !excluded(except),
+            NameClass::Choice(a, b) => a.contains(ns, local) || b.contains(ns, local),
+        }
+    }
+
+    fn names(&self, names: &mut Vec<String>) {
+        match self {
+            NameClass::Name(_, name) => names.push(name.clone()),
+            NameClass::AnyName(_) | NameClass::NsName(..) => names.push("*".to_string()),
+            NameClass::Choice(a, b) => {
+                a.names(names);
+                b.names(names);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Datatype {
+    String,
+    Token,
+    Integer,
+    Boolean,
+    // Anything else is accepted as is
+    Other,
+}
+
+impl Datatype {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "string" => Datatype::String,
+            "token" | "NMTOKEN" | "normalizedString" => Datatype::Token,
+            "integer" | "int" | "positiveInteger" | "nonNegativeInteger" => Datatype::Integer,
+            "boolean" => Datatype::Boolean,
+            _ => Datatype::Other,
+        }
+    }
+
+    fn allows(&self, value: &str) -> bool {
+        match self {
+            Datatype::Integer => value.trim().parse::<i64>().is_ok(),
+            Datatype::Boolean => matches!(value.trim(), "true" | "false" | "1" | "0"),
+            _ => true,
+        }
+    }
+
+    fn equal(&self, a: &str, b: &str) -> bool {
+        match self {
+            Datatype::String => a == b,
+            Datatype::Integer => a.trim().parse::<i64>().ok() == b.trim().parse::<i64>().ok(),
+            _ => normalize(a) == normalize(b),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Param {
+    Pattern(Regex),
+    MinLength(usize),
+    MaxLength(usize),
+    Length(usize),
+}
+
+impl Param {
+    fn allows(&self, value: &str) -> bool {
+        let length = value.chars().count();
+        match self {
+            Param::Pattern(regex) => regex.is_match(value),
+            Param::MinLength(min) => length >= *min,
+            Param::MaxLength(max) => length <= *max,
+            Param::Length(exact) => length == *exact,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Data {
+    datatype: Datatype,
+    params: Vec<Param>,
+    except: Option<Id>,
+}
+
+type Id = u32;
+
+const EMPTY: Id = 0;
+const NOT_ALLOWED: Id = 1;
+const TEXT: Id = 2;
+
+// Name classes and data are referred to by their index in the schema
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Pattern {
+    Empty,
+    NotAllowed,
+    Text,
+    Choice(Id, Id),
+    Interleave(Id, Id),
+    Group(Id, Id),
+    OneOrMore(Id),
+    After(Id, Id),
+    Attribute(usize, Id),
+    Element(usize, Id),
+    Data(usize),
+    Value(Datatype, String),
+    Ref(usize),
+}
+
+#[derive(Clone)]
+struct Arena {
+    patterns: Vec<Pattern>,
+    ids: HashMap<Pattern, Id>,
+}
+
+impl Arena {
+    fn new() -> Self {
+        let mut arena = Self {
+            patterns: Vec::new(),
+            ids: HashMap::new(),
+        };
+        for pattern in [Pattern::Empty, Pattern::NotAllowed, Pattern::Text] {
+            arena.intern(pattern);
+        }
+        arena
+    }
+
+    fn intern(&mut self, pattern: Pattern) -> Id {
+        if let Some(id) = self.ids.get(&pattern) {
+            return *id;
+        }
+        let id = self.patterns.len() as Id;
+        self.patterns.push(pattern.clone());
+        self.ids.insert(pattern, id);
+        id
+    }
+
+    fn get(&self, id: Id) -> &Pattern {
+        &self.patterns[id as usize]
+    }
+
+    // Constructors that simplify as they go
+
+    fn choice(&mut self, a: Id, b: Id) -> Id {
+        if a == NOT_ALLOWED || a == b {
+            return b;
+        }
+        if b == NOT_ALLOWED {
+            return a;
+        }
+        // Choices are kept flat and ordered so equal sets get the same id
+        let mut alternatives = Vec::new();
+        self.alternatives(a, &mut alternatives);
+        self.alternatives(b, &mut alternatives);
+        alternatives.sort_unstable();
+        alternatives.dedup();
+        let mut alternatives = alternatives.into_iter().rev();
+        let last = alternatives.next().unwrap_or(NOT_ALLOWED);
+        alternatives.fold(last, |rest, alternative| {
+            self.intern(Pattern::Choice(alternative, rest))
+        })
+    }
+
+    fn alternatives(&self, id: Id, alternatives: &mut Vec<Id>) {
+        match self.get(id) {
+            Pattern::Choice(a, b) => {
+                let (a, b) = (*a, *b);
+                self.alternatives(a, alternatives);
+                self.alternatives(b, alternatives);
+            }
+            _ => alternatives.push(id),
+        }
+    }
+
+    fn group(&mut self, a: Id, b: Id) -> Id {
+        match (a, b) {
+            (NOT_ALLOWED, _) | (_, NOT_ALLOWED) => NOT_ALLOWED,
+            (EMPTY, _) => b,
+            (_, EMPTY) => a,
+            _ => self.intern(Pattern::Group(a, b)),
+        }
+    }
+
+    fn interleave(&mut self, a: Id, b: Id) -> Id {
+        match (a, b) {
+            (NOT_ALLOWED, _) | (_, NOT_ALLOWED) => NOT_ALLOWED,
+            (EMPTY, _) => b,
+            (_, EMPTY) => a,
+            _ => self.intern(Pattern::Interleave(a, b)),
+        }
+    }
+
+    fn after(&mut self, a: Id, b: Id) -> Id {
+        match (a, b) {
+            (NOT_ALLOWED, _) | (_, NOT_ALLOWED) => NOT_ALLOWED,
+            _ => self.intern(Pattern::After(a, b)),
+        }
+    }
+
+    fn one_or_more(&mut self, a: Id) -> Id {
+        match a {
+            NOT_ALLOWED => NOT_ALLOWED,
+            _ => self.intern(Pattern::OneOrMore(a)),
+        }
+    }
+}
+
+/// A compiled RELAX NG schema.
+pub struct Schema {
+    arena: Arena,
+    start: Id,
+    defines: Vec<Id>,
+    name_classes: Vec<NameClass>,
+    data: Vec<Data>,
+}
+
+impl Schema {
+    /// Compiles a schema written in the RELAX NG XML syntax.
+    pub fn parse(rng: &str) -> Result<Self, String> {
+        let doc = Document::parse(rng).map_err(|e| format!("Invalid schema XML: {}", e))?;
+        let grammar = doc.root_element();
+        if !is_rng(&grammar, "grammar") {
+            return Err("The schema must have a <grammar> root element".to_string());
+        }
+        let mut compiler = Compiler {
+            arena: Arena::new(),
+            index: HashMap::new(),
+            name_classes: Vec::new(),
+            data: Vec::new(),
+        };
+        for define in rng_children(&grammar).filter(|node| is_rng(node, "define")) {
+            let name = define.attribute("name").ok_or("<define> without a name")?;
+            let next = compiler.index.len();
+            compiler.index.entry(name.to_string()).or_insert(next);
+        }
+        let mut defines = vec![NOT_ALLOWED; compiler.index.len()];
+        let mut start = None;
+        for child in rng_children(&grammar) {
+            match child.tag_name().name() {
+                "start" => start = Some(compiler.sequence(&child)?),
+                "define" => {
+                    let index = compiler.index[child.attribute("name").unwrap_or_default()];
+                    let body = compiler.sequence(&child)?;
+                    defines[index] = compiler.arena.choice(defines[index], body);
+                }
+                other => return Err(format!("Unsupported grammar content <{}>", other)),
+            }
+        }
+        Ok(Self {
+            start: start.ok_or("The schema has no <start>")?,
+            defines,
+            arena: compiler.arena,
+            name_classes: compiler.name_classes,
+            data: compiler.data,
+        })
+    }
+
+    /// Validates an XML document, returning every error found. An `Err` means
+    /// the document is not well-formed XML.
+    pub fn validate(&self, xml: &str) -> Result<Vec<RelaxNgError>, String> {
+        let doc = Document::parse(xml).map_err(|e| format!("Invalid XML: {}", e))?;
+        let mut validation = Validation {
+            schema: self,
+            doc: &doc,
+            arena: self.arena.clone(),
+            nullable: HashMap::new(),
+            text_derivs: HashMap::new(),
+            open_derivs: HashMap::new(),
+            attribute_derivs: HashMap::new(),
+            close_derivs: HashMap::new(),
+            errors: Vec::new(),
+        };
+        validation.element(self.start, &doc.root_element());
+        Ok(validation.errors)
+    }
+}
+
+struct Compiler {
+    arena: Arena,
+    index: HashMap<String, usize>,
+    name_classes: Vec<NameClass>,
+    data: Vec<Data>,
+}
+
+impl Compiler {
+    // The children of a schema element taken as a group
+    fn sequence(&mut self, node: &Node) -> Result<Id, String> {
+        self.fold(node, Arena::group)
+    }
+
+    fn fold(&mut self, node: &Node, combine: fn(&mut Arena, Id, Id) -> Id) -> Result<Id, String> {
+        let mut pattern = None;
+        for child in rng_children(node) {
+            let next = self.pattern(&child)?;
+            pattern = Some(match pattern {
+                Some(previous) => combine(&mut self.arena, previous, next),
+                None => next,
+            });
+        }
+        Ok(pattern.unwrap_or(EMPTY))
+    }
+
+    fn pattern(&mut self, node: &Node) -> Result<Id, String> {
+        let pattern = match node.tag_name().name() {
+            "element" | "attribute" => {
+                let is_element = node.tag_name().name() == "element";
+                let mut children = rng_children(node);
+                let name_class = match node.attribute("name") {
+                    Some(name) => {
+                        // Unprefixed attribute names are never in a namespace
+                        let ns = if is_element {
+                            inherited_ns(node)
+                        } else {
+                            String::new()
+                        };
+                        NameClass::Name(ns, name.to_string())
+                    }
+                    None => {
+                        let first = children
+                            .next()
+                            .ok_or("Element or attribute without a name")?;
+                        self.name_class(&first)?
+                    }
+                };
+                let mut content = None;
+                for child in children {
+                    let next = self.pattern(&child)?;
+                    content = Some(match content {
+                        Some(previous) => self.arena.group(previous, next),
+                        None => next,
+                    });
+                }
+                self.name_classes.push(name_class);
+                let name_class = self.name_classes.len() - 1;
+                if is_element {
+                    Pattern::Element(name_class, content.unwrap_or(EMPTY))
+                } else {
+                    Pattern::Attribute(name_class, content.unwrap_or(TEXT))
+                }
+            }
+            "group" => return self.fold(node, Arena::group),
+            "interleave" => return self.fold(node, Arena::interleave),
+            "choice" => return self.fold(node, Arena::choice),
+            "optional" => {
+                let content = self.sequence(node)?;
+                return Ok(self.arena.choice(content, EMPTY));
+            }
+            "zeroOrMore" => {
+                let content = self.sequence(node)?;
+                let repeated = self.arena.one_or_more(content);
+                return Ok(self.arena.choice(repeated, EMPTY));
+            }
+            "oneOrMore" => {
+                let content = self.sequence(node)?;
+                return Ok(self.arena.one_or_more(content));
+            }
+            "mixed" => {
+                let content = self.sequence(node)?;
+                return Ok(self.arena.interleave(content, TEXT));
+            }
+            "empty" => return Ok(EMPTY),
+            "text" | "list" => return Ok(TEXT),
+            "notAllowed" => return Ok(NOT_ALLOWED),
+            "ref" => {
+                let name = node.attribute("name").unwrap_or_default();
+                let index = self
+                    .index
+                    .get(name)
+                    .ok_or_else(|| format!("Reference to undefined pattern '{}'", name))?;
+                Pattern::Ref(*index)
+            }
+            "value" => {
+                // Without a type, values are compared as tokens
+                let datatype = node
+                    .attribute("type")
+                    .map_or(Datatype::Token, Datatype::from_name);
+                Pattern::Value(datatype, node.text().unwrap_or_default().to_string())
+            }
+            "data" => {
+                let datatype = Datatype::from_name(node.attribute("type").unwrap_or_default());
+                let mut params = Vec::new();
+                let mut except = None;
+                for child in rng_children(node) {
+                    let value = child.text().unwrap_or_default();
+                    let length = || value.trim().parse::<usize>().map_err(|e| e.to_string());
+                    match (child.tag_name().name(), child.attribute("name")) {
+                        ("param", Some("pattern")) => {
+                            // XML Schema patterns always match the whole value
+                            let regex = Regex::new(&format!("^(?:{})$", value))
+                                .map_err(|e| format!("Invalid pattern '{}': {}", value, e))?;
+                            params.push(Param::Pattern(regex));
+                        }
+                        ("param", Some("minLength")) => params.push(Param::MinLength(length()?)),
+                        ("param", Some("maxLength")) => params.push(Param::MaxLength(length()?)),
+                        ("param", Some("length")) => params.push(Param::Length(length()?)),
+                        ("param", _) => {}
+                        ("except", _) => except = Some(self.fold(&child, Arena::choice)?),
+                        (other, _) => {
+                            return Err(format!("Unsupported <data> content <{}>", other))
+                        }
+                    }
+                }
+                self.data.push(Data {
+                    datatype,
+                    params,
+                    except,
+                });
+                Pattern::Data(self.data.len() - 1)
+            }
+            other => return Err(format!("Unsupported pattern <{}>", other)),
+        };
+        Ok(self.arena.intern(pattern))
+    }
+
+    fn name_class(&self, node: &Node) -> Result<NameClass, String> {
+        let except = match rng_children(node).find(|child| is_rng(child, "except")) {
+            Some(except) => Some(Box::new(self.name_class_choice(&except)?)),
+            None => None,
+        };
+        match node.tag_name().name() {
+            "name" => Ok(NameClass::Name(
+                inherited_ns(node),
+                node.text().unwrap_or_default().trim().to_string(),
+            )),
+            "anyName" => Ok(NameClass::AnyName(except)),
+            "nsName" => Ok(NameClass::NsName(inherited_ns(node), except)),
+            "choice" => self.name_class_choice(node),
+            other => Err(format!("Unsupported name class <{}>", other)),
+        }
+    }
+
+    fn name_class_choice(&self, node: &Node) -> Result<NameClass, String> {
+        let mut name_class: Option<NameClass> = None;
+        for child in rng_children(node) {
+            let next = self.name_class(&child)?;
+            name_class = Some(match name_class {
+                Some(previous) => NameClass::Choice(Box::new(previous), Box::new(next)),
+                None => next,
+            });
+        }
+        name_class.ok_or_else(|| "Empty name class choice".to_string())
+    }
+}
+
+fn is_rng(node: &Node, name: &str) -> bool {
+    node.tag_name().namespace() == Some(RNG_NS) && node.tag_name().name() == name
+}
+
+// Schema elements, skipping annotations in foreign namespaces
+fn rng_children<'a, 'input>(node: &Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(|child| child.is_element() && child.tag_name().namespace() == Some(RNG_NS))
+}
+
+// The `ns` attribute in effect for a name
+fn inherited_ns(node: &Node) -> String {
+    node.ancestors()
+        .filter(|ancestor| ancestor.is_element())
+        .find_map(|ancestor| ancestor.attribute("ns"))
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn normalize(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn is_whitespace(text: &str) -> bool {
+    text.chars().all(char::is_whitespace)
+}
+
+// What to wrap around the content of an `After` reached by a start tag
+#[derive(Clone, Copy)]
+enum Wrap {
+    InterleaveLeft(Id),
+    InterleaveRight(Id),
+    Group(Id),
+    After(Id),
+}
+
+struct Validation<'s, 'd, 'input> {
+    schema: &'s Schema,
+    doc: &'d Document<'input>,
+    arena: Arena,
+    nullable: HashMap<Id, bool>,
+    text_derivs: HashMap<(Id, String), Id>,
+    open_derivs: HashMap<(Id, String, String), Id>,
+    attribute_derivs: HashMap<(Id, String, String, String, bool), Id>,
+    close_derivs: HashMap<(Id, bool), Id>,
+    errors: Vec<RelaxNgError>,
+}
+
+impl Validation<'_, '_, '_> {
+    fn error(&mut self, position: usize, message: String) {
+        let pos = self.doc.text_pos_at(position);
+        self.errors.push(RelaxNgError {
+            message,
+            line: pos.row,
+            column: pos.col,
+        });
+    }
+
+    fn pattern(&self, id: Id) -> Pattern {
+        self.arena.get(id).clone()
+    }
+
+    fn nullable(&mut self, id: Id) -> bool {
+        if let Some(nullable) = self.nullable.get(&id) {
+            return *nullable;
+        }
+        let nullable = match self.pattern(id) {
+            Pattern::Empty | Pattern::Text => true,
+            Pattern::Group(a, b) | Pattern::Interleave(a, b) => {
+                self.nullable(a) && self.nullable(b)
+            }
+            Pattern::Choice(a, b) => self.nullable(a) || self.nullable(b),
+            Pattern::OneOrMore(a) => self.nullable(a),
+            Pattern::Ref(index) => self.nullable(self.schema.defines[index]),
+            _ => false,
+        };
+        self.nullable.insert(id, nullable);
+        nullable
+    }
+
+    fn text_deriv(&mut self, id: Id, text: &str) -> Id {
+        let key = (id, text.to_string());
+        if let Some(derived) = self.text_derivs.get(&key) {
+            return *derived;
+        }
+        let derived = match self.pattern(id) {
+            Pattern::Choice(a, b) => {
+                let (a, b) = (self.text_deriv(a, text), self.text_deriv(b, text));
+                self.arena.choice(a, b)
+            }
+            Pattern::Interleave(a, b) => {
+                let left = self.text_deriv(a, text);
+                let left = self.arena.interleave(left, b);
+                let right = self.text_deriv(b, text);
+                let right = self.arena.interleave(a, right);
+                self.arena.choice(left, right)
+            }
+            Pattern::Group(a, b) => {
+                let derived = self.text_deriv(a, text);
+                let derived = self.arena.group(derived, b);
+                if self.nullable(a) {
+                    let rest = self.text_deriv(b, text);
+                    self.arena.choice(derived, rest)
+                } else {
+                    derived
+                }
+            }
+            Pattern::After(a, b) => {
+                let derived = self.text_deriv(a, text);
+                self.arena.after(derived, b)
+            }
+            Pattern::OneOrMore(a) => {
+                let derived = self.text_deriv(a, text);
+                let rest = self.arena.choice(id, EMPTY);
+                self.arena.group(derived, rest)
+            }
+            Pattern::Text => TEXT,
+            Pattern::Value(datatype, value) if datatype.equal(&value, text) => EMPTY,
+            Pattern::Data(index) => {
+                let data = &self.schema.data[index];
+                let excluded = match data.except {
+                    Some(except) => {
+                        let derived = self.text_deriv(except, text);
+                        self.nullable(derived)
+                    }
+                    None => false,
+                };
+                let data = &self.schema.data[index];
+                let value = match data.datatype {
+                    Datatype::String => text.to_string(),
+                    _ => normalize(text),
+                };
+                let allowed = data.datatype.allows(&value)
+                    && data.params.iter().all(|param| param.allows(&value));
+                if allowed && !excluded {
+                    EMPTY
+                } else {
+                    NOT_ALLOWED
+                }
+            }
+            Pattern::Ref(index) => self.text_deriv(self.schema.defines[index], text),
+            _ => NOT_ALLOWED,
+        };
+        self.text_derivs.insert(key, derived);
+        derived
+    }
+
+    fn apply_after(&mut self, id: Id, wrap: Wrap) -> Id {
+        match self.pattern(id) {
+            Pattern::After(a, b) => {
+                let wrapped = match wrap {
+                    Wrap::InterleaveLeft(other) => self.arena.interleave(b, other),
+                    Wrap::InterleaveRight(other) => self.arena.interleave(other, b),
+                    Wrap::Group(other) => self.arena.group(b, other),
+                    Wrap::After(other) => self.arena.after(b, other),
+                };
+                self.arena.after(a, wrapped)
+            }
+            Pattern::Choice(a, b) => {
+                let (a, b) = (self.apply_after(a, wrap), self.apply_after(b, wrap));
+                self.arena.choice(a, b)
+            }
+            _ => NOT_ALLOWED,
+        }
+    }
+
+    fn start_tag_open_deriv(&mut self, id: Id, ns: &str, local: &str) -> Id {
+        let key = (id, ns.to_string(), local.to_string());
+        if let Some(derived) = self.open_derivs.get(&key) {
+            return *derived;
+        }
+        let derived = match self.pattern(id) {
+            Pattern::Choice(a, b) => {
+                let a = self.start_tag_open_deriv(a, ns, local);
+                let b = self.start_tag_open_deriv(b, ns, local);
+                self.arena.choice(a, b)
+            }
+            Pattern::Element(name_class, content)
+                if self.schema.name_classes[name_class].contains(ns, local) =>
+            {
+                self.arena.after(content, EMPTY)
+            }
+            Pattern::Interleave(a, b) => {
+                let left = self.start_tag_open_deriv(a, ns, local);
+                let left = self.apply_after(left, Wrap::InterleaveLeft(b));
+                let right = self.start_tag_open_deriv(b, ns, local);
+                let right = self.apply_after(right, Wrap::InterleaveRight(a));
+                self.arena.choice(left, right)
+            }
+            Pattern::OneOrMore(a) => {
+                let rest = self.arena.choice(id, EMPTY);
+                let derived = self.start_tag_open_deriv(a, ns, local);
+                self.apply_after(derived, Wrap::Group(rest))
+            }
+            Pattern::Group(a, b) => {
+                let derived = self.start_tag_open_deriv(a, ns, local);
+                let derived = self.apply_after(derived, Wrap::Group(b));
+                if self.nullable(a) {
+                    let rest = self.start_tag_open_deriv(b, ns, local);
+                    self.arena.choice(derived, rest)
+                } else {
+                    derived
+                }
+            }
+            Pattern::After(a, b) => {
+                let derived = self.start_tag_open_deriv(a, ns, local);
+                self.apply_after(derived, Wrap::After(b))
+            }
+            Pattern::Ref(index) => self.start_tag_open_deriv(self.schema.defines[index], ns, local),
+            _ => NOT_ALLOWED,
+        };
+        self.open_derivs.insert(key, derived);
+        derived
+    }
+
+    // With `any_value`, an attribute matches by name whatever its value
+    fn att_deriv(&mut self, id: Id, ns: &str, local: &str, value: &str, any_value: bool) -> Id {
+        let key = (
+            id,
+            ns.to_string(),
+            local.to_string(),
+            value.to_string(),
+            any_value,
+        );
+        if let Some(derived) = self.attribute_derivs.get(&key) {
+            return *derived;
+        }
+        let derived = match self.pattern(id) {
+            Pattern::After(a, b) => {
+                let derived = self.att_deriv(a, ns, local, value, any_value);
+                self.arena.after(derived, b)
+            }
+            Pattern::Choice(a, b) => {
+                let a = self.att_deriv(a, ns, local, value, any_value);
+                let b = self.att_deriv(b, ns, local, value, any_value);
+                self.arena.choice(a, b)
+            }
+            Pattern::Group(a, b) => {
+                let left = self.att_deriv(a, ns, local, value, any_value);
+                let left = self.arena.group(left, b);
+                let right = self.att_deriv(b, ns, local, value, any_value);
+                let right = self.arena.group(a, right);
+                self.arena.choice(left, right)
+            }
+            Pattern::Interleave(a, b) => {
+                let left = self.att_deriv(a, ns, local, value, any_value);
+                let left = self.arena.interleave(left, b);
+                let right = self.att_deriv(b, ns, local, value, any_value);
+                let right = self.arena.interleave(a, right);
+                self.arena.choice(left, right)
+            }
+            Pattern::OneOrMore(a) => {
+                let derived = self.att_deriv(a, ns, local, value, any_value);
+                let rest = self.arena.choice(id, EMPTY);
+                self.arena.group(derived, rest)
+            }
+            Pattern::Attribute(name_class, content)
+                if self.schema.name_classes[name_class].contains(ns, local)
+                    && (any_value || self.value_matches(content, value)) =>
+            {
+                EMPTY
+            }
+            Pattern::Ref(index) => {
+                self.att_deriv(self.schema.defines[index], ns, local, value, any_value)
+            }
+            _ => NOT_ALLOWED,
+        };
+        self.attribute_derivs.insert(key, derived);
+        derived
+    }
+
+    fn value_matches(&mut self, id: Id, value: &str) -> bool {
+        if self.nullable(id) && is_whitespace(value) {
+            return true;
+        }
+        let derived = self.text_deriv(id, value);
+        self.nullable(derived)
+    }
+
+    // With `recover`, required attributes that were not given are ignored
+    fn start_tag_close_deriv(&mut self, id: Id, recover: bool) -> Id {
+        if let Some(derived) = self.close_derivs.get(&(id, recover)) {
+            return *derived;
+        }
+        let derived = match self.pattern(id) {
+            Pattern::After(a, b) => {
+                let derived = self.start_tag_close_deriv(a, recover);
+                self.arena.after(derived, b)
+            }
+            Pattern::Choice(a, b) => {
+                let (a, b) = (
+                    self.start_tag_close_deriv(a, recover),
+                    self.start_tag_close_deriv(b, recover),
+                );
+                self.arena.choice(a, b)
+            }
+            Pattern::Group(a, b) => {
+                let (a, b) = (
+                    self.start_tag_close_deriv(a, recover),
+                    self.start_tag_close_deriv(b, recover),
+                );
+                self.arena.group(a, b)
+            }
+            Pattern::Interleave(a, b) => {
+                let (a, b) = (
+                    self.start_tag_close_deriv(a, recover),
+                    self.start_tag_close_deriv(b, recover),
+                );
+                self.arena.interleave(a, b)
+            }
+            Pattern::OneOrMore(a) => {
+                let derived = self.start_tag_close_deriv(a, recover);
+                self.arena.one_or_more(derived)
+            }
+            Pattern::Attribute(..) if recover => EMPTY,
+            Pattern::Attribute(..) => NOT_ALLOWED,
+            // References to elements are left for the children to derive
+            Pattern::Ref(index) => match self.pattern(self.schema.defines[index]) {
+                Pattern::Element(..) => id,
+                _ => self.start_tag_close_deriv(self.schema.defines[index], recover),
+            },
+            _ => id,
+        };
+        self.close_derivs.insert((id, recover), derived);
+        derived
+    }
+
+    fn end_tag_deriv(&mut self, id: Id, recover: bool) -> Id {
+        match self.pattern(id) {
+            Pattern::Choice(a, b) => {
+                let (a, b) = (
+                    self.end_tag_deriv(a, recover),
+                    self.end_tag_deriv(b, recover),
+                );
+                self.arena.choice(a, b)
+            }
+            Pattern::After(a, b) if recover || self.nullable(a) => b,
+            _ => NOT_ALLOWED,
+        }
+    }
+
+    // Derives `id` by a whole element, reporting what goes wrong on the way
+    // and skipping the offending part to carry on
+    fn element(&mut self, id: Id, node: &Node) -> Id {
+        let ns = node.tag_name().namespace().unwrap_or_default();
+        let local = node.tag_name().name();
+        let position = node.range().start;
+        let mut current = self.start_tag_open_deriv(id, ns, local);
+        if current == NOT_ALLOWED {
+            let expected = self.expected_elements(id);
+            self.error(
+                position,
+                format!("Element <{}> is not allowed here{}", local, expected),
+            );
+            return id;
+        }
+
+        for attribute in node.attributes() {
+            let attribute_ns = attribute.namespace().unwrap_or_default();
+            let (name, value) = (attribute.name(), attribute.value());
+            let derived = self.att_deriv(current, attribute_ns, name, value, false);
+            if derived != NOT_ALLOWED {
+                current = derived;
+                continue;
+            }
+            let any_value = self.att_deriv(current, attribute_ns, name, value, true);
+            if any_value == NOT_ALLOWED {
+                self.error(
+                    attribute.position(),
+                    format!("Attribute {} is not allowed on <{}>", name, local),
+                );
+            } else {
+                self.error(
+                    attribute.position(),
+                    format!(
+                        "Invalid value \"{}\" for attribute {} of <{}>",
+                        value, name, local
+                    ),
+                );
+                current = any_value;
+            }
+        }
+        let closed = self.start_tag_close_deriv(current, false);
+        current = if closed == NOT_ALLOWED {
+            let missing = self.required_attributes(current);
+            let message = match missing.is_empty() {
+                true => format!("Element <{}> is missing a required attribute", local),
+                false => format!(
+                    "Element <{}> is missing required attribute {}",
+                    local,
+                    missing.join(", ")
+                ),
+            };
+            self.error(position, message);
+            self.start_tag_close_deriv(current, true)
+        } else {
+            closed
+        };
+
+        let reported = self.errors.len();
+        current = self.children(current, node);
+        let ended = self.end_tag_deriv(current, false);
+        // Content left incomplete by a skipped child was reported already
+        if ended == NOT_ALLOWED && self.errors.len() > reported {
+            return self.end_tag_deriv(current, true);
+        }
+        if ended == NOT_ALLOWED {
+            let expected = self.expected_elements(current);
+            self.error(
+                position,
+                format!("Element <{}> is incomplete{}", local, expected),
+            );
+            return self.end_tag_deriv(current, true);
+        }
+        ended
+    }
+
+    fn children(&mut self, id: Id, node: &Node) -> Id {
+        let children: Vec<Node> = node
+            .children()
+            .filter(|child| child.is_element() || child.is_text())
+            .collect();
+        let local = node.tag_name().name();
+        // A lone text may be empty or whitespace; among elements whitespace is ignored
+        if children.iter().all(|child| child.is_text()) {
+            let text: String = children.iter().filter_map(|child| child.text()).collect();
+            let derived = self.text_deriv(id, &text);
+            if is_whitespace(&text) {
+                return self.arena.choice(id, derived);
+            }
+            if derived == NOT_ALLOWED {
+                self.error(
+                    children[0].range().start,
+                    format!("Text \"{}\" is not allowed in <{}>", text.trim(), local),
+                );
+                return id;
+            }
+            return derived;
+        }
+
+        let mut current = id;
+        for child in children {
+            if child.is_element() {
+                current = self.element(current, &child);
+                continue;
+            }
+            let text = child.text().unwrap_or_default();
+            if is_whitespace(text) {
+                continue;
+            }
+            let derived = self.text_deriv(current, text);
+            if derived == NOT_ALLOWED {
+                self.error(
+                    child.range().start,
+                    format!("Text \"{}\" is not allowed in <{}>", text.trim(), local),
+                );
+            } else {
+                current = derived;
+            }
+        }
+        current
+    }
+
+    // Names of the elements `id` accepts next, as the end of an error message
+    fn expected_elements(&mut self, id: Id) -> String {
+        let mut names = Vec::new();
+        self.first_elements(id, &mut names, 0);
+        names.sort();
+        names.dedup();
+        match names.len() {
+            0 => String::new(),
+            count if count > MAX_EXPECTED => format!(
+                "; expected <{}> or {} more",
+                names[..MAX_EXPECTED].join(">, <"),
+                count - MAX_EXPECTED
+            ),
+            _ => format!("; expected <{}>", names.join(">, <")),
+        }
+    }
+
+    fn first_elements(&mut self, id: Id, names: &mut Vec<String>, depth: usize) {
+        if depth > 64 {
+            return;
+        }
+        match self.pattern(id) {
+            Pattern::Element(name_class, _) => self.schema.name_classes[name_class].names(names),
+            Pattern::Choice(a, b) | Pattern::Interleave(a, b) => {
+                self.first_elements(a, names, depth + 1);
+                self.first_elements(b, names, depth + 1);
+            }
+            Pattern::Group(a, b) => {
+                self.first_elements(a, names, depth + 1);
+                if self.nullable(a) {
+                    self.first_elements(b, names, depth + 1);
+                }
+            }
+            Pattern::OneOrMore(a) | Pattern::After(a, _) => {
+                self.first_elements(a, names, depth + 1)
+            }
+            Pattern::Ref(index) => {
+                self.first_elements(self.schema.defines[index], names, depth + 1)
+            }
+            _ => {}
+        }
+    }
+
+    fn required_attributes(&self, id: Id) -> Vec<String> {
+        let mut names = Vec::new();
+        match self.arena.get(id) {
+            Pattern::Attribute(name_class, _) => {
+                self.schema.name_classes[*name_class].names(&mut names)
+            }
+            Pattern::Group(a, b) | Pattern::Interleave(a, b) => {
+                names.extend(self.required_attributes(*a));
+                names.extend(self.required_attributes(*b));
+            }
+            // Required only if every alternative requires it
+            Pattern::Choice(a, b) => {
+                let right = self.required_attributes(*b);
+                names.extend(
+                    self.required_attributes(*a)
+                        .into_iter()
+                        .filter(|name| right.contains(name)),
+                );
+            }
+            Pattern::OneOrMore(a) | Pattern::After(a, _) => {
+                names.extend(self.required_attributes(*a))
+            }
+            _ => {}
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+}
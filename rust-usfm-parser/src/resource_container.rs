@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::books::{book, book_name, Testament};
+use crate::paratext::paratext_book_number;
+use crate::project::{Project, ProjectBook};
+
+/// Name of the manifest at the root of a resource container.
+pub const MANIFEST_FILE: &str = "manifest.yaml";
+
+// Version of the Resource Container specification written manifests follow
+const RC_VERSION: &str = "rc0.2";
+
+// Versification unfoldingWord gives its Bibles
+const RC_VERSIFICATION: &str = "ufw";
+
+/// A Door43 resource container manifest. Fields not listed here are
+/// ignored when reading and left out when writing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub dublin_core: DublinCore,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checking: Option<Checking>,
+    #[serde(default)]
+    pub projects: Vec<RcProject>,
+}
+
+/// What a container holds and who made it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DublinCore {
+    pub conformsto: String,
+    pub contributor: Vec<String>,
+    pub creator: String,
+    pub description: String,
+    /// MIME type of the content, e.g. `text/usfm`
+    pub format: String,
+    /// Short name of the resource, e.g. `ult`
+    pub identifier: String,
+    pub issued: String,
+    pub language: RcLanguage,
+    pub modified: String,
+    pub publisher: String,
+    pub relation: Vec<String>,
+    pub rights: String,
+    /// Resources this one was translated or adapted from
+    pub source: Vec<RcSource>,
+    /// Kind of resource, e.g. `Bible` or `Aligned Bible`
+    pub subject: String,
+    pub title: String,
+    /// `bundle` for a container of books
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub version: String,
+}
+
+impl Default for DublinCore {
+    fn default() -> Self {
+        Self {
+            conformsto: RC_VERSION.to_string(),
+            contributor: Vec::new(),
+            creator: String::new(),
+            description: String::new(),
+            format: "text/usfm".to_string(),
+            identifier: String::new(),
+            issued: String::new(),
+            language: RcLanguage::default(),
+            modified: String::new(),
+            publisher: String::new(),
+            relation: Vec::new(),
+            rights: String::new(),
+            source: Vec::new(),
+            subject: "Bible".to_string(),
+            title: String::new(),
+            kind: "bundle".to_string(),
+            version: "1".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RcLanguage {
+    pub identifier: String,
+    pub title: String,
+    /// `ltr` or `rtl`
+    pub direction: String,
+}
+
+impl Default for RcLanguage {
+    fn default() -> Self {
+        Self {
+            identifier: String::new(),
+            title: String::new(),
+            direction: "ltr".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RcSource {
+    pub identifier: String,
+    pub language: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Checking {
+    pub checking_entity: Vec<String>,
+    pub checking_level: String,
+}
+
+/// One entry of `projects`: a book and the file it is in.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RcProject {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub versification: Option<String>,
+    /// Book code in lower case, e.g. `gen`
+    pub identifier: String,
+    pub sort: u32,
+    /// Relative to the container, e.g. `./01-GEN.usfm`
+    pub path: String,
+    /// `bible-ot` or `bible-nt` for books of the Bible
+    pub categories: Vec<String>,
+}
+
+impl RcProject {
+    pub fn is_usfm(&self) -> bool {
+        Path::new(&self.path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("usfm"))
+    }
+}
+
+impl Manifest {
+    pub fn from_yaml(yaml: &str) -> Result<Self, String> {
+        serde_yaml::from_str(yaml).map_err(|e| format!("Invalid {}: {}", MANIFEST_FILE, e))
+    }
+
+    pub fn to_yaml(&self) -> Result<String, String> {
+        serde_yaml::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// A manifest listing the project's books as the files
+    /// [`export_resource_container`] writes.
+    pub fn for_project(project: &Project, dublin_core: DublinCore) -> Self {
+        let projects = project
+            .books
+            .iter()
+            .zip(1..)
+            .map(|(project_book, sort)| {
+                let categories = match book(&project_book.code).map(|book| book.testament) {
+                    Some(Testament::Old) => vec!["bible-ot".to_string()],
+                    Some(Testament::New) => vec!["bible-nt".to_string()],
+                    _ => Vec::new(),
+                };
+                let info = &project_book.info;
+                let title = info
+                    .short_name
+                    .clone()
+                    .or_else(|| info.long_name.clone())
+                    .or_else(|| book_name(&project_book.code).map(str::to_string))
+                    .unwrap_or_else(|| project_book.code.clone());
+                RcProject {
+                    title,
+                    versification: Some(RC_VERSIFICATION.to_string()),
+                    identifier: project_book.code.to_lowercase(),
+                    sort,
+                    path: format!("./{}", book_file_name(project_book)),
+                    categories,
+                }
+            })
+            .collect();
+        Self {
+            dublin_core,
+            checking: None,
+            projects,
+        }
+    }
+}
+
+/// A resource container folder and its manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceContainer {
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl ResourceContainer {
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, String> {
+        let dir = dir.as_ref().to_path_buf();
+        let path = dir.join(MANIFEST_FILE);
+        let yaml = fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Ok(Self {
+            dir,
+            manifest: Manifest::from_yaml(&yaml)?,
+        })
+    }
+
+    /// Paths of the projects in USFM files, in the manifest's sort order.
+    pub fn usfm_files(&self) -> Vec<PathBuf> {
+        let mut projects: Vec<_> = self
+            .manifest
+            .projects
+            .iter()
+            .filter(|project| project.is_usfm())
+            .collect();
+        projects.sort_by_key(|project| project.sort);
+        projects
+            .into_iter()
+            .map(|project| {
+                self.dir
+                    .join(project.path.strip_prefix("./").unwrap_or(&project.path))
+            })
+            .collect()
+    }
+
+    /// Loads the USFM projects as a project.
+    pub fn project(&self) -> Result<Project, String> {
+        Project::from_files(self.usfm_files())
+    }
+}
+
+/// Writes the project's books and a `manifest.yaml` listing them to a
+/// folder, returning the manifest.
+pub fn export_resource_container<P: AsRef<Path>>(
+    project: &Project,
+    dir: P,
+    dublin_core: DublinCore,
+) -> Result<Manifest, String> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    for project_book in &project.books {
+        let path = dir.join(book_file_name(project_book));
+        fs::write(&path, &project_book.usfm)
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    }
+    let manifest = Manifest::for_project(project, dublin_core);
+    let path = dir.join(MANIFEST_FILE);
+    fs::write(&path, manifest.to_yaml()?)
+        .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(manifest)
+}
+
+// Named as unfoldingWord names books, e.g. `41-MAT.usfm`
+fn book_file_name(project_book: &ProjectBook) -> String {
+    match paratext_book_number(&project_book.code) {
+        Some(number) => format!("{}-{}.usfm", number, project_book.code),
+        None => format!("{}.usfm", project_book.code),
+    }
+}
@@ -0,0 +1,64 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static MARKER_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\\(\+?)([A-Za-z][A-Za-z0-9-]*)(\*?)").unwrap());
+
+/// A marker occurrence in USFM source, found without building a syntax tree so
+/// that it also works on input the grammar cannot parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkerToken {
+    /// Marker name without the backslash, `+` or `*` (`\+nd*` -> `nd`)
+    pub marker: String,
+    /// Written with a `+` prefix, i.e. nested inside another character marker
+    pub nested: bool,
+    /// Written with a `*` suffix, i.e. a closing marker
+    pub closing: bool,
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub row: usize,
+    pub column: usize,
+}
+
+pub fn scan_markers(usfm: &str) -> Vec<MarkerToken> {
+    let mut tokens = Vec::new();
+    let mut row = 0;
+    let mut line_start = 0;
+    let mut scanned = 0;
+
+    for capture in MARKER_PATTERN.captures_iter(usfm) {
+        let whole = capture.get(0).unwrap();
+        for (offset, _) in usfm[scanned..whole.start()].match_indices('\n') {
+            row += 1;
+            line_start = scanned + offset + 1;
+        }
+        scanned = whole.start();
+
+        tokens.push(MarkerToken {
+            marker: capture[2].to_string(),
+            nested: !capture[1].is_empty(),
+            closing: !capture[3].is_empty(),
+            start_byte: whole.start(),
+            end_byte: whole.end(),
+            row,
+            column: whole.start() - line_start,
+        });
+    }
+    tokens
+}
+
+/// Row and column (both zero based, column in bytes) of a byte offset.
+pub(crate) fn point_at(usfm: &str, byte: usize) -> (usize, usize) {
+    let before = &usfm[..byte.min(usfm.len())];
+    let row = before.matches('\n').count();
+    let column = before.rfind('\n').map_or(before.len(), |pos| before.len() - pos - 1);
+    (row, column)
+}
+
+/// The first word following a marker, e.g. the number of `\c 3` or `\v 4-5`.
+pub fn word_after<'a>(usfm: &'a str, token: &MarkerToken) -> &'a str {
+    usfm[token.end_byte..]
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+}
@@ -1,91 +1,315 @@
 use once_cell::sync::Lazy;
 use serde_json::Value;
 
+use crate::relaxng;
+use crate::stylesheet::{StyleType, USFM_CATALOG};
+
+// Markers of these style types that USJ writes as an object of their own
+// type (book, chapter, verse, sidebar, periph, table row, figure, ref) or as a
+// property, rather than as a para, char or ms
+const OWN_TYPE_MARKERS: [&str; 10] = [
+    "id", "c", "cp", "v", "esb", "periph", "tr", "fig", "ref", "cat",
+];
+
+// The catalog markers of a style type that USJ writes with that type.
+// Table cells are written as `table:cell` and are left out as well.
+fn markers_of(style_type: StyleType) -> Vec<&'static str> {
+    let mut markers: Vec<&str> = USFM_CATALOG
+        .markers()
+        .filter(|info| info.style_type == Some(style_type))
+        .filter(|info| !info.occurs_under.iter().any(|parent| parent == "tr"))
+        .map(|info| info.marker.as_str())
+        .filter(|marker| !OWN_TYPE_MARKERS.contains(marker))
+        .collect();
+    markers.sort_unstable();
+    markers
+}
+
 pub static USJ_SCHEMA: Lazy<Value> = Lazy::new(|| {
     serde_json::json!({
         "$schema": "http://json-schema.org/draft-07/schema",
         "$id": "https://github.com/usfm-bible/tcdocs/blob/main/grammar/usj.js",
         "title": "Unified Scripture JSON",
-        "description": "The JSON variant of USFM and USX data models",
+        "description": "The JSON variant of USFM and USX data models, version 3.1",
         "type": "object",
         "$defs": {
+            "content": {
+                "description": "Text and marker objects inside a marker object",
+                "type": "array",
+                "items": {
+                    "if": {"type": "string"},
+                    "else": {"$ref": "#/$defs/markerObject"}
+                }
+            },
             "markerObject": {
+                "description": "A node, checked against the definition for its type",
                 "type": "object",
                 "properties": {
                     "type": {
                         "description": "The kind/category of node or element this is",
-                        "type": "string"
-                    },
-                    "marker": {
-                        "description": "The corresponding marker in USFM or style in USX",
-                        "type": "string"
+                        "enum": [
+                            "book", "chapter", "verse", "para", "char", "note", "ms", "figure",
+                            "sidebar", "periph", "table", "table:row", "table:cell", "ref",
+                            "unmatched", "optbreak"
+                        ]
+                    }
+                },
+                "required": ["type"],
+                "allOf": [
+                    {"if": {"$ref": "#/$defs/isType/book"}, "then": {"$ref": "#/$defs/book"}},
+                    {"if": {"$ref": "#/$defs/isType/chapter"}, "then": {"$ref": "#/$defs/chapter"}},
+                    {"if": {"$ref": "#/$defs/isType/verse"}, "then": {"$ref": "#/$defs/verse"}},
+                    {"if": {"$ref": "#/$defs/isType/para"}, "then": {"$ref": "#/$defs/para"}},
+                    {"if": {"$ref": "#/$defs/isType/char"}, "then": {"$ref": "#/$defs/char"}},
+                    {"if": {"$ref": "#/$defs/isType/note"}, "then": {"$ref": "#/$defs/note"}},
+                    {"if": {"$ref": "#/$defs/isType/ms"}, "then": {"$ref": "#/$defs/ms"}},
+                    {"if": {"$ref": "#/$defs/isType/figure"}, "then": {"$ref": "#/$defs/figure"}},
+                    {"if": {"$ref": "#/$defs/isType/sidebar"}, "then": {"$ref": "#/$defs/sidebar"}},
+                    {"if": {"$ref": "#/$defs/isType/periph"}, "then": {"$ref": "#/$defs/periph"}},
+                    {"if": {"$ref": "#/$defs/isType/table"}, "then": {"$ref": "#/$defs/table"}},
+                    {"if": {"$ref": "#/$defs/isType/tableRow"}, "then": {"$ref": "#/$defs/tableRow"}},
+                    {"if": {"$ref": "#/$defs/isType/tableCell"}, "then": {"$ref": "#/$defs/tableCell"}},
+                    {"if": {"$ref": "#/$defs/isType/ref"}, "then": {"$ref": "#/$defs/ref"}},
+                    {"if": {"$ref": "#/$defs/isType/unmatched"}, "then": {"$ref": "#/$defs/unmatched"}}
+                ]
+            },
+            "isType": {
+                "book": {"properties": {"type": {"const": "book"}}, "required": ["type"]},
+                "chapter": {"properties": {"type": {"const": "chapter"}}, "required": ["type"]},
+                "verse": {"properties": {"type": {"const": "verse"}}, "required": ["type"]},
+                "para": {"properties": {"type": {"const": "para"}}, "required": ["type"]},
+                "char": {"properties": {"type": {"const": "char"}}, "required": ["type"]},
+                "note": {"properties": {"type": {"const": "note"}}, "required": ["type"]},
+                "ms": {"properties": {"type": {"const": "ms"}}, "required": ["type"]},
+                "figure": {"properties": {"type": {"const": "figure"}}, "required": ["type"]},
+                "sidebar": {"properties": {"type": {"const": "sidebar"}}, "required": ["type"]},
+                "periph": {"properties": {"type": {"const": "periph"}}, "required": ["type"]},
+                "table": {"properties": {"type": {"const": "table"}}, "required": ["type"]},
+                "tableRow": {"properties": {"type": {"const": "table:row"}}, "required": ["type"]},
+                "tableCell": {"properties": {"type": {"const": "table:cell"}}, "required": ["type"]},
+                "ref": {"properties": {"type": {"const": "ref"}}, "required": ["type"]},
+                "unmatched": {"properties": {"type": {"const": "unmatched"}}, "required": ["type"]}
+            },
+            "book": {
+                "properties": {
+                    "marker": {"const": "id"},
+                    "code": {
+                        "description": "The 3-letter book code in id element",
+                        "type": "string",
+                        "pattern": "^[0-9A-Z]{3}$"
                     },
                     "content": {
+                        "description": "The text after the book code",
                         "type": "array",
-                        "items": {
-                            "anyOf": [
-                                {"type": "string"},
-                                {"$ref": "#/$defs/markerObject"}
-                            ]
-                        }
+                        "items": {"type": "string"}
+                    }
+                },
+                "required": ["marker", "code"]
+            },
+            "chapter": {
+                "properties": {
+                    "marker": {"const": "c"},
+                    "number": {
+                        "description": "Chapter number",
+                        "type": "string",
+                        "pattern": "^[0-9]+$"
                     },
                     "sid": {
-                        "description": "Indicates the Book-chapter-verse value",
-                        "type": "string"
+                        "description": "Book and chapter, as in GEN 1, with no book before an \\id",
+                        "type": "string",
+                        "pattern": "^([A-Z1-4]{3})? ?[0-9]+$"
                     },
-                    "number": {
-                        "description": "Chapter number or verse number",
+                    "altnumber": {
+                        "description": "Alternate chapter number",
                         "type": "string"
                     },
-                    "code": {
-                        "description": "The 3-letter book code in id element",
-                        "pattern": "^[0-9A-Z]{3}$",
+                    "pubnumber": {
+                        "description": "Published character of the chapter",
                         "type": "string"
+                    }
+                },
+                "required": ["marker", "number", "sid"]
+            },
+            "verse": {
+                "properties": {
+                    "marker": {"const": "v"},
+                    "number": {
+                        "description": "Verse number, range or segment, as in 1, 1-2 or 1a",
+                        "type": "string",
+                        "pattern": "^[0-9]+[\\p{L}\\p{Mn}]*(\u{200f}?[\\-,][0-9]+[\\p{L}\\p{Mn}]*)*$"
+                    },
+                    "sid": {
+                        "description": "Book, chapter and verse, as in GEN 1:1",
+                        "type": "string",
+                        "pattern": "^([A-Z1-4]{3})? ?[a-z0-9,\\-:\\p{L}\\p{Mn}]*$"
                     },
                     "altnumber": {
-                        "description": "Alternate chapter number or verse number",
+                        "description": "Alternate verse number",
                         "type": "string"
                     },
                     "pubnumber": {
-                        "description": "Published character of chapter or verse",
+                        "description": "Published character of the verse",
                         "type": "string"
-                    },
+                    }
+                },
+                "required": ["marker", "number", "sid"]
+            },
+            "para": {
+                "properties": {
+                    "marker": {"$ref": "#/$defs/paraMarker"},
+                    "content": {"$ref": "#/$defs/content"}
+                },
+                "required": ["marker"]
+            },
+            "char": {
+                "properties": {
+                    "marker": {"$ref": "#/$defs/charMarker"},
+                    "content": {"$ref": "#/$defs/content"}
+                },
+                "required": ["marker"]
+            },
+            "note": {
+                "properties": {
+                    "marker": {"enum": ["f", "fe", "ef", "x", "ex"]},
                     "caller": {
                         "description": "Caller character for footnotes and cross-refs",
-                        "type": "string"
+                        "type": "string",
+                        "minLength": 1
                     },
-                    "align": {
-                        "description": "Alignment of table cells",
+                    "category": {
+                        "description": "Category of extended study bible notes",
                         "type": "string"
                     },
+                    "content": {"$ref": "#/$defs/content"}
+                },
+                "required": ["marker", "caller"]
+            },
+            "ms": {
+                "properties": {
+                    "marker": {"$ref": "#/$defs/milestoneMarker"},
+                    "sid": {"type": "string"},
+                    "eid": {"type": "string"}
+                },
+                "required": ["marker"]
+            },
+            "figure": {
+                "properties": {
+                    "marker": {"const": "fig"},
+                    "file": {"type": "string"},
+                    "size": {"type": "string"},
+                    "ref": {"type": "string"},
+                    "content": {"$ref": "#/$defs/content"}
+                },
+                "required": ["marker"]
+            },
+            "sidebar": {
+                "properties": {
+                    "marker": {"const": "esb"},
                     "category": {
                         "description": "Category of extended study bible sections",
                         "type": "string"
+                    },
+                    "content": {"$ref": "#/$defs/content"}
+                },
+                "required": ["marker"]
+            },
+            "periph": {
+                "properties": {
+                    "id": {"type": "string"},
+                    "alt": {"type": "string"},
+                    "content": {"$ref": "#/$defs/content"}
+                },
+                "required": ["id"]
+            },
+            "table": {
+                "properties": {
+                    "content": {
+                        "type": "array",
+                        "items": {"$ref": "#/$defs/markerObject"}
+                    }
+                }
+            },
+            "tableRow": {
+                "properties": {
+                    "marker": {"const": "tr"},
+                    "content": {
+                        "type": "array",
+                        "items": {"$ref": "#/$defs/markerObject"}
                     }
                 },
-                "required": ["type"]
+                "required": ["marker"]
+            },
+            "tableCell": {
+                "properties": {
+                    "marker": {
+                        "type": "string",
+                        "pattern": "^t[hc][rc]?[1-9][0-9]*(-[1-9][0-9]*)?$"
+                    },
+                    "align": {
+                        "description": "Alignment of table cells",
+                        "enum": ["start", "center", "end"]
+                    },
+                    "colspan": {"type": "string"},
+                    "content": {"$ref": "#/$defs/content"}
+                },
+                "required": ["marker", "align"]
+            },
+            "ref": {
+                "properties": {
+                    "loc": {
+                        "description": "The reference the text points to",
+                        "type": "string"
+                    },
+                    "gen": {"type": "string"},
+                    "content": {"$ref": "#/$defs/content"}
+                },
+                "required": ["loc"]
+            },
+            "unmatched": {
+                "properties": {
+                    "marker": {"type": "string"}
+                },
+                "required": ["marker"]
+            },
+            "paraMarker": {
+                "type": "string",
+                "if": {"$ref": "#/$defs/customMarker"},
+                "else": {"enum": markers_of(StyleType::Paragraph)}
+            },
+            "charMarker": {
+                "type": "string",
+                "if": {"$ref": "#/$defs/customMarker"},
+                "else": {"enum": markers_of(StyleType::Character)}
+            },
+            "milestoneMarker": {
+                "type": "string",
+                "if": {"$ref": "#/$defs/customMarker"},
+                "else": {"enum": markers_of(StyleType::Milestone)}
+            },
+            "customMarker": {
+                "description": "Markers starting with z are left to projects to define",
+                "type": "string",
+                "pattern": "^z"
             }
         },
         "properties": {
-            "type": {
-                "description": "The kind of node/element/marker this is",
-                "type": "string"
-            },
+            "type": {"const": "USJ"},
             "version": {
                 "description": "The USJ spec version",
-                "type": "string"
+                "type": "string",
+                "pattern": "^3\\.[0-9]+$"
             },
             "content": {
                 "description": "The JSON representation of scripture contents",
-                "type": "array",
-                "items": {
-                    "anyOf": [
-                        {"type": "string"},
-                        {"$ref": "#/$defs/markerObject"}
-                    ]
-                }
+                "$ref": "#/$defs/content"
             }
         },
         "required": ["type", "version", "content"]
     })
 });
+
+/// The USX grammar from `schemas/usx.rng`, compiled once.
+pub static USX_SCHEMA: Lazy<relaxng::Schema> = Lazy::new(|| {
+    relaxng::Schema::parse(include_str!("../../schemas/usx.rng"))
+        .expect("schemas/usx.rng should compile")
+});
@@ -0,0 +1,82 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tree_sitter::Node;
+
+/// Where in the USFM a USJ element or text came from. Rows and columns are
+/// zero-based, as in diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct SourceSpan {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub start_row: usize,
+    pub start_column: usize,
+    pub end_row: usize,
+    pub end_column: usize,
+}
+
+impl SourceSpan {
+    pub(crate) fn of(node: &Node) -> Self {
+        Self {
+            start_byte: node.start_byte(),
+            end_byte: node.end_byte(),
+            start_row: node.start_position().row,
+            start_column: node.start_position().column,
+            end_row: node.end_position().row,
+            end_column: node.end_position().column,
+        }
+    }
+
+    pub fn contains(&self, byte: usize) -> bool {
+        (self.start_byte..self.end_byte).contains(&byte)
+    }
+}
+
+/// The spans of the elements and texts of a USJ document, keyed by path: the
+/// indices to follow through nested `content` lists from the root.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    // Paths sort in document order
+    spans: BTreeMap<Vec<usize>, SourceSpan>,
+}
+
+impl SourceMap {
+    pub(crate) fn insert(&mut self, path: Vec<usize>, span: SourceSpan) {
+        self.spans.insert(path, span);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Every path with its span, in document order.
+    pub fn iter(&self) -> impl Iterator<Item = (&[usize], SourceSpan)> {
+        self.spans
+            .iter()
+            .map(|(path, span)| (path.as_slice(), *span))
+    }
+
+    pub fn span(&self, path: &[usize]) -> Option<SourceSpan> {
+        self.spans.get(path).copied()
+    }
+
+    /// The path of the innermost element or text whose source contains a
+    /// byte offset.
+    pub fn path_at(&self, byte: usize) -> Option<&[usize]> {
+        self.spans
+            .iter()
+            .filter(|(_, span)| span.contains(byte))
+            .max_by_key(|(path, _)| path.len())
+            .map(|(path, _)| path.as_slice())
+    }
+}
+
+/// The item of a USJ document at a [`SourceMap`] path.
+pub fn usj_at<'a>(usj: &'a Value, path: &[usize]) -> Option<&'a Value> {
+    path.iter()
+        .try_fold(usj, |value, &index| value.get("content")?.get(index))
+}
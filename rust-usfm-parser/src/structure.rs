@@ -0,0 +1,179 @@
+use crate::books::{book, is_book_code, Versification};
+use crate::diagnostics::Diagnostic;
+use crate::reference::VerseNumber;
+use crate::scanner::{scan_markers, word_after, MarkerToken};
+
+// Headers a book is expected to have, with what each one is for
+const HEADER_MARKERS: [(&str, &str); 4] = [
+    ("h", "running header"),
+    ("toc1", "long book name"),
+    ("toc2", "short book name"),
+    ("mt", "main title"),
+];
+
+/// Checks that chapters and verses are numbered in order without repeats or
+/// gaps, and that a book has a known code and carries its header markers.
+pub fn check_structure(usfm: &str) -> Vec<Diagnostic> {
+    let tokens = scan_markers(usfm);
+    let mut diagnostics = check_book_code(usfm, &tokens);
+    diagnostics.extend(check_headers(&tokens));
+    let mut chapter: Option<u32> = None;
+    let mut verse: Option<u32> = None;
+    let mut verse_label = "";
+
+    for token in tokens.iter().filter(|token| !token.closing) {
+        match token.marker.as_str() {
+            "c" => {
+                let number = word_after(usfm, token);
+                let Ok(number) = number.parse::<u32>() else {
+                    diagnostics.push(Diagnostic::at_marker(
+                        "chapter-sequence",
+                        format!("\\c needs a chapter number, found \"{}\"", number),
+                        token,
+                    ));
+                    continue;
+                };
+                if let Some(message) = sequence_problem("Chapter", chapter, number) {
+                    diagnostics.push(Diagnostic::at_marker("chapter-sequence", message, token));
+                }
+                chapter = Some(number);
+                verse = None;
+                verse_label = "";
+            }
+            "v" => {
+                let number = word_after(usfm, token);
+                let (first, last, segment) = match VerseNumber::parse(number) {
+                    Ok(verse) => (verse.start, verse.end, verse.end_segment),
+                    Err(error) => {
+                        diagnostics.push(Diagnostic::at_marker("verse-sequence", error, token));
+                        continue;
+                    }
+                };
+                // \v 4a followed by \v 4b continues the same verse
+                let next_segment =
+                    verse == Some(first) && number != verse_label && segment.is_some();
+                if let Some(message) = sequence_problem("Verse", verse, first) {
+                    if !next_segment {
+                        diagnostics.push(Diagnostic::at_marker("verse-sequence", message, token));
+                    }
+                }
+                verse = Some(last.max(verse.unwrap_or_default()));
+                verse_label = number;
+            }
+            _ => {}
+        }
+    }
+    diagnostics
+}
+
+/// Checks that chapter numbers stay within the number of chapters the book
+/// has in a versification.
+pub fn check_chapter_count(usfm: &str, versification: Versification) -> Vec<Diagnostic> {
+    let tokens = scan_markers(usfm);
+    let Some(id) = tokens.iter().find(|token| token.marker == "id") else {
+        return Vec::new();
+    };
+    let code = word_after(usfm, id);
+    let Some(count) = book(code).and_then(|book| book.chapter_count(versification)) else {
+        return Vec::new();
+    };
+    tokens
+        .iter()
+        .filter(|token| token.marker == "c" && !token.closing)
+        .filter_map(|token| {
+            let number = word_after(usfm, token).parse::<u32>().ok()?;
+            (number > count).then(|| {
+                Diagnostic::at_marker(
+                    "chapter-out-of-range",
+                    format!(
+                        "Chapter {} is beyond the {} chapters of {} in the {} versification",
+                        number,
+                        count,
+                        code,
+                        versification.name()
+                    ),
+                    token,
+                )
+            })
+        })
+        .collect()
+}
+
+fn check_book_code(usfm: &str, tokens: &[MarkerToken]) -> Vec<Diagnostic> {
+    let Some(id) = tokens.iter().find(|token| token.marker == "id") else {
+        return Vec::new();
+    };
+    let code = word_after(usfm, id);
+    if is_book_code(code) {
+        return Vec::new();
+    }
+    let upper = code.to_uppercase();
+    let message = if is_book_code(&upper) {
+        format!("Book code \"{}\" should be \"{}\"", code, upper)
+    } else {
+        format!("\\id has unknown book code \"{}\"", code)
+    };
+    vec![Diagnostic::at_marker("unknown-book-code", message, id)]
+}
+
+fn check_headers(tokens: &[MarkerToken]) -> Vec<Diagnostic> {
+    // Only whole books are expected to have headers
+    let Some(id) = tokens.iter().find(|token| token.marker == "id") else {
+        return Vec::new();
+    };
+    HEADER_MARKERS
+        .iter()
+        .filter(|(header, _)| {
+            !tokens.iter().any(|token| {
+                token.marker == *header
+                    || (token.marker.starts_with(header)
+                        && token.marker[header.len()..]
+                            .chars()
+                            .all(|c| c.is_ascii_digit()))
+            })
+        })
+        .map(|(header, purpose)| {
+            Diagnostic::at_marker(
+                "missing-header-marker",
+                format!("Missing \\{} ({})", header, purpose),
+                id,
+            )
+        })
+        .collect()
+}
+
+fn sequence_problem(kind: &str, previous: Option<u32>, first: u32) -> Option<String> {
+    let noun = kind.to_lowercase();
+    let previous = previous?;
+    if first == previous {
+        Some(format!("{} {} is repeated", kind, first))
+    } else if first < previous {
+        Some(format!(
+            "{} {} is out of order; it follows {} {}",
+            kind, first, noun, previous
+        ))
+    } else if first == previous + 2 {
+        Some(format!(
+            "{} {} follows {} {}; {} {} is missing",
+            kind,
+            first,
+            noun,
+            previous,
+            noun,
+            previous + 1
+        ))
+    } else if first > previous + 2 {
+        Some(format!(
+            "{} {} follows {} {}; {}s {}-{} are missing",
+            kind,
+            first,
+            noun,
+            previous,
+            noun,
+            previous + 1,
+            first - 1
+        ))
+    } else {
+        None
+    }
+}
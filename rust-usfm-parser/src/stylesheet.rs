@@ -0,0 +1,284 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// The standard USFM 3 stylesheet shipped with the crate.
+pub const USFM_STY: &str = include_str!("usfm.sty");
+
+/// Placement rules the crate applies on top of `usfm.sty`, with the reason
+/// for each in the file.
+pub const USFM_OVERRIDES_STY: &str = include_str!("usfm_overrides.sty");
+
+/// Catalog built from the embedded `usfm.sty` and its overrides.
+pub static USFM_CATALOG: Lazy<MarkerCatalog> = Lazy::new(|| {
+    let mut catalog =
+        MarkerCatalog::from_stylesheet(USFM_STY).expect("Embedded usfm.sty should be valid");
+    catalog
+        .extend_from_stylesheet(USFM_OVERRIDES_STY)
+        .expect("Embedded usfm_overrides.sty should be valid");
+    catalog
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StyleType {
+    Paragraph,
+    Character,
+    Note,
+    Milestone,
+}
+
+impl StyleType {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "paragraph" => Some(StyleType::Paragraph),
+            "character" => Some(StyleType::Character),
+            "note" => Some(StyleType::Note),
+            "milestone" => Some(StyleType::Milestone),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeSpec {
+    pub name: String,
+    /// Attributes written with a leading `?` in the stylesheet are optional
+    pub required: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct MarkerInfo {
+    pub marker: String,
+    /// Marker name with any trailing level number removed (`toc1` -> `toc`)
+    pub base: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub style_type: Option<StyleType>,
+    pub text_type: Option<String>,
+    pub text_properties: Vec<String>,
+    pub occurs_under: Vec<String>,
+    pub endmarker: Option<String>,
+    pub attributes: Vec<AttributeSpec>,
+}
+
+impl MarkerInfo {
+    fn new(marker: &str) -> Self {
+        Self {
+            marker: marker.to_string(),
+            base: base_marker(marker).to_string(),
+            name: None,
+            description: None,
+            style_type: None,
+            text_type: None,
+            text_properties: Vec::new(),
+            occurs_under: Vec::new(),
+            endmarker: None,
+            attributes: Vec::new(),
+        }
+    }
+
+    /// The attribute used when a value is given without a name (`\w word|lemma\w*`)
+    pub fn default_attribute(&self) -> Option<&str> {
+        self.attributes.first().map(|attrib| attrib.name.as_str())
+    }
+
+    pub fn has_text_property(&self, property: &str) -> bool {
+        self.text_properties
+            .iter()
+            .any(|prop| prop.eq_ignore_ascii_case(property))
+    }
+
+    pub fn is_text_type(&self, text_type: &str) -> bool {
+        self.text_type
+            .as_deref()
+            .is_some_and(|value| value.eq_ignore_ascii_case(text_type))
+    }
+}
+
+/// Marker definitions loaded from Paratext stylesheets (`usfm.sty`, `custom.sty`).
+#[derive(Debug, Clone, Default)]
+pub struct MarkerCatalog {
+    markers: HashMap<String, MarkerInfo>,
+}
+
+impl MarkerCatalog {
+    pub fn from_stylesheet(sty: &str) -> Result<Self, String> {
+        let mut catalog = Self::default();
+        catalog.extend_from_stylesheet(sty)?;
+        Ok(catalog)
+    }
+
+    /// A copy of the standard catalog, ready to be extended with a project stylesheet.
+    pub fn usfm() -> Self {
+        USFM_CATALOG.clone()
+    }
+
+    /// Applies a stylesheet on top of the current definitions. As in Paratext,
+    /// fields given for an existing marker replace only those fields.
+    pub fn extend_from_stylesheet(&mut self, sty: &str) -> Result<(), String> {
+        let mut current: Option<String> = None;
+
+        for (line_no, raw_line) in sty.lines().enumerate() {
+            let line = raw_line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some(line) = line.strip_prefix('\\') else {
+                return Err(format!(
+                    "At line {}: expected a field starting with '\\', found '{}'",
+                    line_no + 1,
+                    line
+                ));
+            };
+            let (field, value) = match line.split_once(char::is_whitespace) {
+                Some((field, value)) => (field, value.trim()),
+                None => (line, ""),
+            };
+            let field = field.to_ascii_lowercase();
+
+            if field == "marker" {
+                if value.is_empty() {
+                    return Err(format!("At line {}: \\Marker without a name", line_no + 1));
+                }
+                self.markers
+                    .entry(value.to_string())
+                    .or_insert_with(|| MarkerInfo::new(value));
+                current = Some(value.to_string());
+                continue;
+            }
+
+            let Some(info) = current.as_ref().and_then(|m| self.markers.get_mut(m)) else {
+                return Err(format!(
+                    "At line {}: field \\{} appears before any \\Marker",
+                    line_no + 1,
+                    field
+                ));
+            };
+            match field.as_str() {
+                "name" => info.name = Some(value.to_string()),
+                "description" => info.description = Some(value.to_string()),
+                "styletype" => {
+                    info.style_type = Some(StyleType::parse(value).ok_or_else(|| {
+                        format!("At line {}: unknown \\StyleType '{}'", line_no + 1, value)
+                    })?);
+                }
+                "texttype" => info.text_type = Some(value.to_string()),
+                "textproperties" => {
+                    info.text_properties = value.split_whitespace().map(String::from).collect()
+                }
+                "occursunder" => {
+                    info.occurs_under = value.split_whitespace().map(String::from).collect()
+                }
+                "endmarker" => {
+                    info.endmarker = (!value.is_empty()).then(|| value.to_string());
+                }
+                "attributes" => {
+                    info.attributes = value
+                        .split_whitespace()
+                        .map(|attrib| match attrib.strip_prefix('?') {
+                            Some(name) => AttributeSpec {
+                                name: name.to_string(),
+                                required: false,
+                            },
+                            None => AttributeSpec {
+                                name: attrib.to_string(),
+                                required: true,
+                            },
+                        })
+                        .collect()
+                }
+                // Formatting properties (fonts, spacing, colours, ...) are not used here
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads a project `custom.sty` on top of the current definitions.
+    pub fn load_custom<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let sty = fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("Failed to read {}: {}", path.as_ref().display(), e))?;
+        self.extend_from_stylesheet(&sty)
+    }
+
+    /// Looks up a marker as written in USFM (`\nd`, `+nd`, `nd*`), falling back to
+    /// the unnumbered form for levels the stylesheet does not list (`q5` -> `q`,
+    /// `qt3-s` -> `qt-s`) and to the first level for markers only listed with
+    /// numbers (`toc` -> `toc1`).
+    pub fn get(&self, marker: &str) -> Option<&MarkerInfo> {
+        let marker = marker
+            .trim()
+            .trim_start_matches('\\')
+            .trim_start_matches('+')
+            .trim_end_matches('*');
+        if let Some(info) = self.markers.get(marker) {
+            return Some(info);
+        }
+        for suffix in ["-s", "-e"] {
+            if let Some(stem) = marker.strip_suffix(suffix) {
+                return self.markers.get(&format!("{}{}", base_marker(stem), suffix));
+            }
+        }
+        self.markers
+            .get(base_marker(marker))
+            .or_else(|| self.markers.get(&format!("{}1", marker)))
+    }
+
+    pub fn contains(&self, marker: &str) -> bool {
+        self.get(marker).is_some()
+    }
+
+    /// Finds the marker closed by `endmarker`, e.g. `esbe` -> `esb`.
+    pub fn opener_of(&self, endmarker: &str) -> Option<&MarkerInfo> {
+        let endmarker = endmarker.trim().trim_start_matches('\\').trim_start_matches('+');
+        self.markers
+            .values()
+            .find(|info| info.endmarker.as_deref() == Some(endmarker))
+    }
+
+    pub fn style_type(&self, marker: &str) -> Option<StyleType> {
+        self.get(marker).and_then(|info| info.style_type)
+    }
+
+    pub fn is_paragraph(&self, marker: &str) -> bool {
+        self.style_type(marker) == Some(StyleType::Paragraph)
+    }
+
+    pub fn is_character(&self, marker: &str) -> bool {
+        self.style_type(marker) == Some(StyleType::Character)
+    }
+
+    pub fn is_note(&self, marker: &str) -> bool {
+        self.style_type(marker) == Some(StyleType::Note)
+    }
+
+    pub fn is_milestone(&self, marker: &str) -> bool {
+        self.style_type(marker) == Some(StyleType::Milestone)
+    }
+
+    /// Character markers that structure note text (`\ft`, `\xo`, ...)
+    pub fn is_note_content(&self, marker: &str) -> bool {
+        self.get(marker).is_some_and(|info| {
+            info.style_type == Some(StyleType::Character) && info.is_text_type("NoteText")
+        })
+    }
+
+    pub fn default_attribute(&self, marker: &str) -> Option<&str> {
+        self.get(marker).and_then(|info| info.default_attribute())
+    }
+
+    pub fn markers(&self) -> impl Iterator<Item = &MarkerInfo> {
+        self.markers.values()
+    }
+}
+
+/// Strips a trailing level number from a marker name (`s1` -> `s`, `toca2` -> `toca`).
+pub fn base_marker(marker: &str) -> &str {
+    let base = marker.trim_end_matches(|c: char| c.is_ascii_digit());
+    if base.is_empty() {
+        marker
+    } else {
+        base
+    }
+}
@@ -0,0 +1,393 @@
+use unicode_general_category::{get_general_category, GeneralCategory};
+use unicode_script::{Script, UnicodeScript};
+
+use crate::diagnostics::Diagnostic;
+use crate::scanner::point_at;
+use crate::stylesheet::MarkerCatalog;
+use crate::verse_text::{extract_verse_text, VerseText};
+
+// Brackets paired by unmatched-punctuation besides the configured quotes
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+// Punctuation that should follow a word without a space
+const CLOSING_PUNCTUATION: &[char] = &[',', '.', ';', ':', '!', '?', ')', ']', '}'];
+
+// Punctuation that should be followed by a space before the next word
+const SPACED_PUNCTUATION: &[char] = &[',', ';', ':', '!', '?'];
+
+// Scripts written together in a single word (Japanese, Korean)
+const COMBINED_SCRIPTS: &[Script] = &[
+    Script::Han,
+    Script::Hiragana,
+    Script::Katakana,
+    Script::Hangul,
+    Script::Bopomofo,
+];
+
+/// Checks the verse text of a book for repeated words, spacing around
+/// punctuation, unpaired brackets and quotation marks, words mixing scripts
+/// and extra whitespace at the start or end of verses.
+pub fn check_text(usfm: &str, catalog: &MarkerCatalog, quotes: &[(char, char)]) -> Vec<Diagnostic> {
+    let verses = extract_verse_text(usfm, catalog);
+    let mut diagnostics = Vec::new();
+    for verse in &verses {
+        let words = words(&verse.text);
+        check_whitespace(usfm, verse, &mut diagnostics);
+        check_repeated_words(usfm, verse, &words, &mut diagnostics);
+        check_punctuation_spacing(usfm, verse, &mut diagnostics);
+        check_scripts(usfm, verse, &words, &mut diagnostics);
+    }
+    check_pairs(usfm, &verses, quotes, &mut diagnostics);
+    diagnostics
+}
+
+/// The unmatched-punctuation findings of [`check_text`] alone. Pairs run
+/// over the whole book, so a quotation may open in one chapter and close in
+/// the next.
+pub fn check_punctuation_pairs(
+    usfm: &str,
+    catalog: &MarkerCatalog,
+    quotes: &[(char, char)],
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    check_pairs(
+        usfm,
+        &extract_verse_text(usfm, catalog),
+        quotes,
+        &mut diagnostics,
+    );
+    diagnostics
+}
+
+// A diagnostic over `start..end` of a verse's text
+fn diagnostic(
+    usfm: &str,
+    verse: &VerseText,
+    rule: &'static str,
+    message: String,
+    start: usize,
+    end: usize,
+) -> Diagnostic {
+    let start_byte = verse.source_byte(start);
+    let end_byte = match verse.text[..end].chars().next_back() {
+        Some(last) if end > start => verse.source_byte(end - last.len_utf8()) + last.len_utf8(),
+        _ => start_byte,
+    };
+    let (row, column) = point_at(usfm, start_byte);
+    Diagnostic::new(rule, message, start_byte, end_byte, row, column)
+}
+
+// Letters, digits and combining marks such as the Devanagari virama
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric()
+        || matches!(
+            get_general_category(c),
+            GeneralCategory::NonspacingMark
+                | GeneralCategory::SpacingMark
+                | GeneralCategory::EnclosingMark
+        )
+}
+
+fn is_apostrophe(c: char) -> bool {
+    c == '\'' || c == '\u{2019}'
+}
+
+// Byte ranges of the words of a text; an apostrophe between letters is part
+// of the word, as in "don't"
+fn words(text: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, &(byte, c)) in chars.iter().enumerate() {
+        let joins = is_apostrophe(c)
+            && start.is_some()
+            && chars
+                .get(index + 1)
+                .is_some_and(|&(_, next)| next.is_alphabetic());
+        match (start, is_word_char(c) || joins) {
+            (None, true) => start = Some(byte),
+            (Some(word_start), false) => {
+                words.push((word_start, byte));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words.extend(start.map(|word_start| (word_start, text.len())));
+    words
+}
+
+fn check_whitespace(usfm: &str, verse: &VerseText, diagnostics: &mut Vec<Diagnostic>) {
+    let text = &verse.text;
+    let content = text.trim();
+    if content.is_empty() {
+        return;
+    }
+    // Only the run the verse starts or ends with counts: spaces left around a
+    // note or figure taken out of the text are not in the verse
+    let piece_end = |offset: usize| {
+        let index = verse.pieces.partition_point(|piece| piece.offset <= offset);
+        verse
+            .pieces
+            .get(index)
+            .map_or(text.len(), |piece| piece.offset)
+    };
+    let leading = (text.len() - text.trim_start().len()).min(piece_end(0));
+    if leading > 0 {
+        diagnostics.push(diagnostic(
+            usfm,
+            verse,
+            "verse-whitespace",
+            format!("Verse {} starts with whitespace", verse.reference()),
+            0,
+            leading,
+        ));
+    }
+    // A single space before the next verse or the end of the line is expected
+    let content_end = text.trim_end().len();
+    let trailing = &text[content_end..piece_end(content_end)];
+    let trailing_end = content_end + trailing.find(['\r', '\n']).unwrap_or(trailing.len());
+    let spaces = &text[content_end..trailing_end];
+    if spaces.len() > 1 || spaces == "\t" {
+        diagnostics.push(diagnostic(
+            usfm,
+            verse,
+            "verse-whitespace",
+            format!("Verse {} ends with extra whitespace", verse.reference()),
+            content_end,
+            trailing_end,
+        ));
+    }
+}
+
+fn check_repeated_words(
+    usfm: &str,
+    verse: &VerseText,
+    words: &[(usize, usize)],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let text = &verse.text;
+    for pair in words.windows(2) {
+        let ((first_start, first_end), (second_start, second_end)) = (pair[0], pair[1]);
+        let first = &text[first_start..first_end];
+        let second = &text[second_start..second_end];
+        let separated_by_space = text[first_end..second_start]
+            .chars()
+            .all(char::is_whitespace);
+        if separated_by_space
+            && first.chars().any(char::is_alphabetic)
+            && !first.chars().any(|c| c.is_numeric())
+            && first.to_lowercase() == second.to_lowercase()
+        {
+            diagnostics.push(diagnostic(
+                usfm,
+                verse,
+                "repeated-word",
+                format!("Repeated word \"{}\" in {}", second, verse.reference()),
+                second_start,
+                second_end,
+            ));
+        }
+    }
+}
+
+fn check_punctuation_spacing(usfm: &str, verse: &VerseText, diagnostics: &mut Vec<Diagnostic>) {
+    let text = &verse.text;
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    for (index, &(byte, c)) in chars.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|index| chars[index].1);
+        let next = chars.get(index + 1).map(|&(_, next)| next);
+
+        if CLOSING_PUNCTUATION.contains(&c) && previous == Some(' ') {
+            // Spaced ellipses (". . .") are written that way on purpose
+            let space_start = text[..byte].trim_end_matches(' ').len();
+            let ellipsis = c == '.'
+                && (next == Some('.')
+                    || text[byte + 1..].starts_with(" .")
+                    || text[..space_start].ends_with('.'));
+            let after_text = text[..space_start]
+                .chars()
+                .next_back()
+                .is_some_and(|c| !c.is_whitespace());
+            if after_text && !ellipsis {
+                diagnostics.push(diagnostic(
+                    usfm,
+                    verse,
+                    "punctuation-spacing",
+                    format!("Space before \"{}\" in {}", c, verse.reference()),
+                    space_start,
+                    byte + c.len_utf8(),
+                ));
+            }
+        }
+
+        let missing_space = match (previous, next) {
+            (Some(previous), Some(next)) if SPACED_PUNCTUATION.contains(&c) => {
+                previous.is_alphabetic() && next.is_alphabetic()
+            }
+            // "end.Then", but not abbreviations such as "e.g." or "U.S."
+            (Some(previous), Some(next)) if c == '.' => {
+                previous.is_lowercase() && next.is_uppercase()
+            }
+            _ => false,
+        };
+        if missing_space {
+            diagnostics.push(diagnostic(
+                usfm,
+                verse,
+                "punctuation-spacing",
+                format!("Missing space after \"{}\" in {}", c, verse.reference()),
+                byte,
+                byte + c.len_utf8(),
+            ));
+        }
+    }
+}
+
+fn check_scripts(
+    usfm: &str,
+    verse: &VerseText,
+    words: &[(usize, usize)],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for &(start, end) in words {
+        let word = &verse.text[start..end];
+        let mut scripts: Vec<Script> = Vec::new();
+        for c in word.chars().filter(|c| c.is_alphabetic()) {
+            let script = c.script();
+            if !matches!(script, Script::Common | Script::Inherited | Script::Unknown)
+                && !scripts.contains(&script)
+            {
+                scripts.push(script);
+            }
+        }
+        if scripts.len() < 2
+            || scripts
+                .iter()
+                .all(|script| COMBINED_SCRIPTS.contains(script))
+        {
+            continue;
+        }
+        let names: Vec<&str> = scripts.iter().map(|script| script.full_name()).collect();
+        let (last, rest) = names.split_last().unwrap();
+        diagnostics.push(diagnostic(
+            usfm,
+            verse,
+            "mixed-script",
+            format!(
+                "Word \"{}\" mixes {} and {} letters",
+                word,
+                rest.join(", "),
+                last
+            ),
+            start,
+            end,
+        ));
+    }
+}
+
+// An opening bracket or quotation mark waiting for its pair
+struct Opening {
+    open: char,
+    close: char,
+    byte: usize,
+    row: usize,
+    column: usize,
+}
+
+fn unclosed(opening: Opening) -> Diagnostic {
+    Diagnostic::new(
+        "unmatched-punctuation",
+        format!("Opening {} is not closed", opening.open),
+        opening.byte,
+        opening.byte + opening.open.len_utf8(),
+        opening.row,
+        opening.column,
+    )
+}
+
+// Pairs brackets and quotation marks across the whole book, so quotations
+// may run over several verses. A quotation continued in a new paragraph is
+// reopened without having been closed, as in English typography.
+fn check_pairs(
+    usfm: &str,
+    verses: &[VerseText],
+    quotes: &[(char, char)],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let pairs: Vec<(char, char)> = BRACKETS.iter().chain(quotes).copied().collect();
+    let mut stack: Vec<Opening> = Vec::new();
+
+    for verse in verses {
+        let chars: Vec<(usize, char)> = verse.text.char_indices().collect();
+        for (index, &(byte, c)) in chars.iter().enumerate() {
+            let previous = index.checked_sub(1).map(|index| chars[index].1);
+            let next = chars.get(index + 1).map(|&(_, next)| next);
+            let opens = pairs.iter().find(|&&(open, _)| open == c);
+            let closes = pairs.iter().find(|&&(_, close)| close == c);
+
+            // A mark used for both opening and closing closes when it is open
+            let toggle_closes = opens.is_some_and(|&(open, close)| {
+                open == close && stack.last().is_some_and(|top| top.open == open)
+            });
+            if let (Some(&(open, close)), false) = (opens, toggle_closes) {
+                let paragraph_start = verse.pieces.iter().any(|piece| {
+                    piece.paragraph_start
+                        && piece.offset <= byte
+                        && verse.text[piece.offset..byte].trim().is_empty()
+                });
+                if paragraph_start && stack.iter().any(|opening| opening.open == open) {
+                    continue;
+                }
+                let source = verse.source_byte(byte);
+                let (row, column) = point_at(usfm, source);
+                stack.push(Opening {
+                    open,
+                    close,
+                    byte: source,
+                    row,
+                    column,
+                });
+                continue;
+            }
+            let Some(&(open, close)) = closes else {
+                continue;
+            };
+            // "don't", or the possessive in "three days’ journey"
+            let in_word = previous.is_some_and(char::is_alphanumeric)
+                && next.is_some_and(char::is_alphanumeric);
+            let possessive = previous == Some('s')
+                && next == Some(' ')
+                && chars
+                    .get(index + 2)
+                    .is_some_and(|&(_, after)| after.is_lowercase());
+            if is_apostrophe(c) && (in_word || possessive) {
+                continue;
+            }
+            match stack.iter().rposition(|opening| opening.close == close) {
+                Some(position) => {
+                    for opening in stack.drain(position..).skip(1) {
+                        diagnostics.push(unclosed(opening));
+                    }
+                }
+                None => {
+                    // "disciples’" or "’tis"
+                    let apostrophe = is_apostrophe(c)
+                        && (previous.is_some_and(char::is_alphanumeric)
+                            || next.is_some_and(char::is_alphanumeric));
+                    if !apostrophe {
+                        diagnostics.push(diagnostic(
+                            usfm,
+                            verse,
+                            "unmatched-punctuation",
+                            format!("Closing {} has no opening {}", close, open),
+                            byte,
+                            byte + c.len_utf8(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    diagnostics.extend(stack.into_iter().map(unclosed));
+}
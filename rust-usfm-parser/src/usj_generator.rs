@@ -1,1119 +1,895 @@
+use crate::books::is_book_code;
+use crate::characters::to_nfc;
 use crate::globals::GLOBAL_TREE;
-
-extern crate lazy_static;
-
-use lazy_static::lazy_static;
-use roxmltree::Children;
-use serde::de::value;
-use serde_json::{self, json};
-use std::collections::HashMap;
-use std::collections::HashSet;
-use std::sync::Mutex;
-use std::sync::MutexGuard;
-use streaming_iterator::IntoStreamingIterator;
+use crate::reference::VerseNumber;
+use crate::source_map::{SourceMap, SourceSpan};
+use crate::stylesheet::{MarkerCatalog, StyleType, USFM_CATALOG};
+
+use once_cell::sync::Lazy;
+use serde_json::ser::{CompactFormatter, Formatter, PrettyFormatter};
+use std::borrow::Cow;
+use std::io;
+use std::sync::{MutexGuard, PoisonError};
 use streaming_iterator::StreamingIterator;
-use tree_sitter::TreeCursor;
-use tree_sitter::{Parser, Query, QueryCursor, TextProvider};
+use tracing::{debug_span, field, trace, Span};
+use tree_sitter::{Node, Query, QueryCursor};
 use tree_sitter_usfm3;
-const NOTE_MARKERS: [&str; 6] = ["f", "fe", "ef", "efe", "x", "ex"];
-const CHAR_STYLE_MARKERS: [&str; 55] = [
-    "add", "bk", "dc", "ior", "iqt", "k", "litl", "nd", "ord", "pn", "png", "qac", "qs", "qt",
-    "rq", "sig", "sls", "tl", "wj", "em", "bd", "bdit", "it", "no", "sc", "sup", "rb", "pro", "w",
-    "wh", "wa", "wg", "lik", "liv", "jmp", "fr", "ft", "fk", "fq", "fqa", "fl", "fw", "fp", "fv",
-    "fdc", "xo", "xop", "xt", "xta", "xk", "xq", "xot", "xnt", "xdc", "ref",
-];
-const PARA_STYLE_MARKERS: [&str; 49] = [
-    "ide", "h", "toc", "toca", //identification
-    "imt", "is", "ip", "ipi", "im", "imi", "ipq", "imq", "ipr", "iq", "ib", "ili", "iot", "io",
-    "iex", "imte", "ie", // intro
-    "mt", "mte", "cl", "cd", "ms", "mr", "s", "sr", "r", "d", "sp", "sd", //titles
-    "q", "qr", "qc", "qa", "qm", "qd", //poetry
-    "lh", "li", "lf", "lim", "litl", //lists
-    "sts", "rem", "lit", "restore", //comments
-    "b",
-];
 const TABLE_CELL_MARKERS: [&str; 4] = ["tc", "th", "tcr", "thr"];
-const DEFAULT_ATTRIB_MAP: [(&str, &str); 9] = [
-    ("w", "lemma"),
-    ("rb", "gloss"),
-    ("xt", "href"),
-    ("fig", "alt"),
-    ("xt_standalone", "href"),
-    ("xtNested", "href"),
-    ("ref", "loc"),
-    ("milestone", "who"),
-    ("k", "key"),
+// Paragraph-style markers whose grammar nodes are converted by the book, chapter
+// and paragraph handlers rather than generically
+const STRUCTURAL_PARA_NODES: [&str; 21] = [
+    "id", "usfm", "c", "cp", "p", "m", "po", "pr", "cls", "pmo", "pm", "pmc", "pmr", "pi", "mi",
+    "nb", "pc", "ph", "pb", "tr", "esb",
 ];
-const NESTED_CHAR_STYLE_MARKERS: [&str; 55] = [
-    "addNested",
-    "bkNested",
-    "dcNested",
-    "iorNested",
-    "iqtNested",
-    "kNested",
-    "litlNested",
-    "ndNested",
-    "ordNested",
-    "pnNested",
-    "pngNested",
-    "qacNested",
-    "qsNested",
-    "qtNested",
-    "rqNested",
-    "sigNested",
-    "slsNested",
-    "tlNested",
-    "wjNested",
-    "emNested",
-    "bdNested",
-    "bditNested",
-    "itNested",
-    "noNested",
-    "scNested",
-    "supNested",
-    "rbNested",
-    "proNested",
-    "wNested",
-    "whNested",
-    "waNested",
-    "wgNested",
-    "likNested",
-    "livNested",
-    "jmpNested",
-    "frNested",
-    "ftNested",
-    "fkNested",
-    "fqNested",
-    "fqaNested",
-    "flNested",
-    "fwNested",
-    "fpNested",
-    "fvNested",
-    "fdcNested",
-    "xoNested",
-    "xopNested",
-    "xtNested",
-    "xtaNested",
-    "xkNested",
-    "xqNested",
-    "xotNested",
-    "xntNested",
-    "xdcNested",
-    "refNested",
+// Children of a generic paragraph that belong inside it rather than after it
+const PARA_CONTENT_NODES: [&str; 8] = [
+    "text",
+    "footnote",
+    "crossref",
+    "verseText",
+    "v",
+    "b",
+    "milestone",
+    "zNameSpace",
 ];
 
-const MISC_MARKERS: [&str; 6] = ["fig", "cat", "esb", "b", "ph", "pi"];
+/// Layout of written USJ. Both keep keys in the order of the reference
+/// fixtures: `type`, `marker`, other properties, then `content`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UsjFormat {
+    /// Indented by two spaces, as `serde_json::to_string_pretty`
+    #[default]
+    Pretty,
+    /// On one line with no spaces
+    Compact,
+}
 
-lazy_static! {
-    static ref CHAPTER_NUMBER: Mutex<Option<String>> = Mutex::new(None);
+/// What [`write_usj_with`] writes and collects besides the USJ.
+#[derive(Debug, Clone, Copy)]
+pub struct UsjOptions<'c> {
+    pub format: UsjFormat,
+    /// Markers the conversion knows: their style types, default attributes
+    /// and any `\z` markers of a project's `custom.sty`
+    pub catalog: &'c MarkerCatalog,
+    /// Give each element a `span` property with the byte range and start of
+    /// its source. This extends USJ, so schema validation rejects it.
+    pub spans: bool,
+    /// Collect the span of every element and text in a [`SourceMap`]
+    pub source_map: bool,
+    /// Write text and attribute values in Unicode Normalization Form C
+    pub nfc: bool,
 }
 
-pub fn usj_generator(usfm: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let global_tree: MutexGuard<Option<tree_sitter::Tree>> = GLOBAL_TREE.lock().unwrap();
-    let tree = global_tree.as_ref().ok_or("Tree is not initialized")?;
+impl Default for UsjOptions<'_> {
+    fn default() -> Self {
+        Self {
+            format: UsjFormat::default(),
+            catalog: &USFM_CATALOG,
+            spans: false,
+            source_map: false,
+            nfc: false,
+        }
+    }
+}
 
-    let root_node = tree.root_node();
-
-    // Change the type of json_object to HashMap<String, serde_json::Value>
-    let mut json_object: HashMap<String, serde_json::Value> = HashMap::new();
-    json_object.insert("type".to_string(), json!("USJ")); // Use json! macro for consistency
-    json_object.insert("version".to_string(), json!("3.1")); // Use json! macro for consistency
-
-    let mut content = Vec::new();
-
-    // Traverse the tree and build the JSON object
-    node_2_usj(&root_node, &mut content, usfm);
-    // Insert the content as a serde_json::Value
-    json_object.insert(
-        "content".to_string(),
-        serde_json::to_value(content).unwrap(),
-    );
-
-    // Convert HashMap to pretty-printed JSON
-    let json_output = serde_json::to_string_pretty(&json_object).unwrap_or_else(|e| {
-        eprintln!("Failed to convert to JSON: {}", e);
-        String::new() // Return an empty string or handle the error as needed
-    });
-    Ok(json_output)
+/// The queries the handlers run, compiled once and shared by every
+/// conversion.
+struct Queries {
+    id: Query,
+    chapter: Query,
+    verse: Query,
+    alt_number: Query,
+    attribute_value: Query,
+    milestone: Query,
+    category: Query,
 }
-pub fn node_2_usj(
-    //verified
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
-    usfm: &str,
-) {
-    let node_type = node.kind();
-   
-    println!("Node Type: {}", node_type);
-    if node_type == "File" {
-        node_2_usj_id(&node, content, usfm);
-    } else if node_type == "chapter" {
-        node_2_usj_chapter(&node, content, usfm);
-    } else if ["cl", "cp", "cd", "vp"].contains(&node_type) {
-        node_2_usj_generic(&node, content, usfm);
-    } else if ["ca", "va"].contains(&node_type) {
-        node_2_usj_ca_va(&node, content, usfm);
-    } else if node_type == "v" {
-        let global_chapter_number = CHAPTER_NUMBER.lock().unwrap();
-        node_2_usj_verse(&node, content, usfm, &global_chapter_number);
-    } else if node_type == "verseText" {
-        for child in node.children(&mut node.walk()) {
-            node_2_usj(&child, content, usfm);
-        }
-    } else if ["paragraph", "pi", "ph"].contains(&node_type) {
-        node_2_usj_para(&node, content, usfm);
-    } else if NOTE_MARKERS.contains(&node_type) {
-        node_2_usj_notes(&node, content, usfm);
-    } else if CHAR_STYLE_MARKERS.contains(&node_type)
-        || NESTED_CHAR_STYLE_MARKERS.contains(&node_type)
-        || node_type == "xt_standalone"
-    {
-        node_2_usj_char(node, content, usfm);
-    } else if node_type.ends_with("Attribute") {
-        node_2_usj_attrib(&node, content, usfm);
-    } else if node_type == "text" {
-        let node_text = node
-            .utf8_text(usfm.as_bytes())
-            .expect("Failed to get node text")
-            .replace('\n', "")
-            .to_string();
-        if node_text != "" {
-            content.push(serde_json::Value::String(node_text));
-        }
-        let mut cursor = node.walk();
-        for child in node.children(&mut node.walk()) {
-            // cursor.goto_first_child();
-            node_2_usj_para(&child, content, usfm);
-        }
-    } else if ["table", "tr"].contains(&node_type) {
-        //+ self.TABLE_CELL_MARKERS:
-        node_2_usj_table(&node, content, usfm);
-    } else if ["zNameSpace", "milestone"].contains(&node_type) {
-        node_2_usj_milestone(&node, content, usfm);
-    } else if ["esb", "cat", "fig"].contains(&node_type) {
-        node_2_usj_special(&node, content, usfm);
-    } else if PARA_STYLE_MARKERS.contains(&node_type)
-        || PARA_STYLE_MARKERS.contains(&node_type.replace("\\", "").trim())
-    {
-        //node.type.replace("\\","").strip() in self.PARA_STYLE_MARKERS):
-        //  self.node_2_usj_generic(node, parent_json_obj)
-        node_2_usj_generic(node, content, usfm);
-    } else if ["", "|"].contains(&node_type.trim())
-    {
-         // skip white space nodes
+
+static QUERIES: Lazy<Queries> = Lazy::new(|| {
+    let language = tree_sitter_usfm3::language();
+    let query = |source: &str| Query::new(&language, source).expect("Invalid USJ query");
+    Queries {
+        id: query("(id (bookcode) @book-code (description)? @desc)"),
+        chapter: query(
+            "(c (chapterNumber) @chap-num (ca (chapterNumber) @alt-num)? (cp (text) @pub-num)?)",
+        ),
+        verse: query("(v (verseNumber) @vnum (va (verseNumber) @alt)? (vp (text) @vp)?)"),
+        alt_number: query("[(chapterNumber) (verseNumber)] @alt-num"),
+        attribute_value: query("(attributeValue) @attrib-val"),
+        milestone: query(
+            "[(milestoneTag) (milestoneStartTag) (milestoneEndTag) (zSpaceTag)] @ms-name",
+        ),
+        category: query("(category) @category"),
     }
+});
 
-    if node.children(&mut node.walk()).len() > 0 {
-        //println!("count:{}",node.child_count());
-        for child in node.children(&mut node.walk()) {
-            //println!("child:{}",child);
-            node_2_usj(&child, content, usfm);
-        }
+// Attributes and categories are written as properties of the element they
+// are in, before its content
+fn is_property_node(node_type: &str) -> bool {
+    node_type == "cat" || node_type.ends_with("Attribute")
+}
+
+// The marker whose default attribute applies to an unnamed attribute under `node`
+fn attribute_owner(node: &Node, usfm: &str) -> String {
+    match node.kind() {
+        "xt_standalone" => "xt".to_string(),
+        "milestone" => node
+            .named_child(0)
+            .map(|tag| node_text(&tag, usfm))
+            .unwrap_or_default()
+            .trim()
+            .trim_start_matches('\\')
+            .to_string(),
+        kind => kind.strip_suffix("Nested").unwrap_or(kind).to_string(),
     }
+}
 
-  
+fn node_text<'a>(node: &Node, usfm: &'a str) -> &'a str {
+    node.utf8_text(usfm.as_bytes()).unwrap_or_default()
 }
 
-pub fn node_2_usj_id(
-    //verified
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
-    usfm: &str,
-) {
-    let query_source = r#"
-            (id
-                (bookcode) @book-code
-                (description)? @desc
-            )
-            "#;
-    //let node_type = node.kind();
-    let node_text = node
-        .utf8_text(usfm.as_bytes())
-        .expect("Failed to get node text")
-        .to_string();
-    ////println!("nodeTYPE={}", node_text);
-
-    let query =
-        Query::new(&tree_sitter_usfm3::language(), query_source).expect("Failed to create query");
-    let mut cursor = QueryCursor::new();
-    let mut captures = cursor.matches(&query, *node, node_text.as_bytes());
-
-    let mut code = None;
-    let mut desc = None;
-
-    while let Some(capture) = captures.next() {
-        // Capture the chapter number
-        if let Some(code_capture) = capture.captures.get(0) {
-            if let Ok(code_text) = code_capture.node.utf8_text(usfm.as_bytes()) {
-                code = Some(code_text.trim().to_string());
-            }
-        }
+// The marker a tag node such as `\mt2 ` or `\+w` spells
+fn tag_marker(node: &Node, usfm: &str) -> String {
+    node_text(node, usfm)
+        .replace(['\\', '+'], "")
+        .trim()
+        .to_string()
+}
 
-        // Capture the altTrevorernative chapter number if it exists
-        if let Some(desc_capture) = capture.captures.get(1) {
-            if let Ok(desc_text) = desc_capture.node.utf8_text(usfm.as_bytes()) {
-                desc = Some(desc_text.trim().to_string());
-            }
-        }
-    }
+/// Converts the tree the last [`crate::parser::USFMParser::parse_usfm`] call
+/// produced.
+pub fn usj_generator(usfm: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let global_tree: MutexGuard<Option<tree_sitter::Tree>> =
+        GLOBAL_TREE.lock().unwrap_or_else(PoisonError::into_inner);
+    let tree = global_tree.as_ref().ok_or("Tree is not initialized")?;
+    usj_from_tree(tree, usfm)
+}
 
-    let mut book_json_obj = json!({
-        "type": "book",
-        "marker": "id",
-        "code": code.clone().unwrap_or_default(),
-        "content": desc.map_or_else(Vec::new, |d| vec![d]), // Wrap desc in a Vec
-    });
-    content.push(book_json_obj.clone());
+/// Converts a tree parsed from `usfm`. Unlike [`usj_generator`] this does
+/// not touch the global tree, so it can run on several threads at once.
+pub fn usj_from_tree(
+    tree: &tree_sitter::Tree,
+    usfm: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut usj = Vec::new();
+    write_usj(tree, usfm, &mut usj, UsjFormat::Pretty)?;
+    Ok(String::from_utf8(usj)?)
 }
 
-pub fn node_2_usj_chapter(
-    //verified
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
+/// Writes the USJ of a tree parsed from `usfm` as the tree is walked,
+/// without building it in memory first.
+pub fn write_usj<W: io::Write>(
+    tree: &tree_sitter::Tree,
     usfm: &str,
-) {
-    for child in node.children(&mut node.walk()) {
-        if child.kind() == "c" {
-            node_2_usj_c(&child, content, usfm);
-        } else {
-            node_2_usj(&child, content, usfm);
+    writer: W,
+    format: UsjFormat,
+) -> io::Result<()> {
+    let options = UsjOptions {
+        format,
+        ..UsjOptions::default()
+    };
+    write_usj_with(tree, usfm, writer, options).map(drop)
+}
+
+/// Writes USJ as [`write_usj`] does, optionally with the source span of each
+/// element. The source map is empty unless `options.source_map` is set.
+pub fn write_usj_with<W: io::Write>(
+    tree: &tree_sitter::Tree,
+    usfm: &str,
+    writer: W,
+    options: UsjOptions<'_>,
+) -> io::Result<SourceMap> {
+    match options.format {
+        UsjFormat::Pretty => {
+            UsjGenerator::new(usfm, writer, PrettyFormatter::new(), options).write(tree)
+        }
+        UsjFormat::Compact => {
+            UsjGenerator::new(usfm, writer, CompactFormatter, options).write(tree)
         }
     }
 }
 
-pub fn node_2_usj_c(
-    //verified
-    //verified
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
+/// Writes the USJ content items of some top-level nodes as a compact JSON
+/// array, for converting part of a book. `book_code` is the code from an
+/// `\id` outside the nodes; the code in effect afterwards is returned.
+pub(crate) fn write_usj_nodes<W: io::Write>(
+    nodes: &[Node],
     usfm: &str,
-) {
-    let query_source = r#"
-        (c
-            (chapterNumber) @chap-num
-            (ca (chapterNumber) @alt-num)?
-            (cp (text) @pub-num)?
-        )
-    "#;
-    let query =
-        Query::new(&tree_sitter_usfm3::language(), query_source).expect("Failed to create query");
-    let mut cursor = QueryCursor::new();
-
-    // Execute the query against the current node
-    let mut captures = cursor.matches(&query, *node, usfm.as_bytes());
-
-    let mut chapter_number: Option<String> = None;
-    let mut alt_number: Option<String> = None;
-    let mut publication_number: Option<String> = None;
-    let mut chap_ref: Option<String> = None;
-    let mut global_chapter_number = CHAPTER_NUMBER.lock().unwrap();
-    // Iterate over the captures returned by the query
-    while let Some(capture) = captures.next() {
-        // Capture the chapter number
-        if let Some(chap_num_capture) = capture.captures.get(0) {
-            if let Ok(num) = chap_num_capture.node.utf8_text(usfm.as_bytes()) {
-                chapter_number = Some(num.trim().to_string());
-            }
-        }
+    book_code: Option<String>,
+    writer: W,
+    catalog: &MarkerCatalog,
+) -> io::Result<Option<String>> {
+    let _span = debug_span!("book", code = book_code.as_deref()).entered();
+    let options = UsjOptions {
+        catalog,
+        ..UsjOptions::default()
+    };
+    let mut generator = UsjGenerator::new(usfm, writer, CompactFormatter, options);
+    generator.book_code = book_code;
+    generator.json.begin_array()?;
+    generator.counts.push(0);
+    for node in nodes {
+        generator.node_2_usj(node)?;
+    }
+    generator.json.end_array()?;
+    generator.json.writer.flush()?;
+    Ok(generator.book_code)
+}
 
-        // Capture the alternative chapter number if it exists
-        if let Some(alt_num_capture) = capture.captures.get(1) {
-            if let Ok(alt) = alt_num_capture.node.utf8_text(usfm.as_bytes()) {
-                alt_number = Some(alt.trim().to_string());
-            }
-        }
+// Writes JSON through a serde_json formatter, keeping track of which open
+// objects and arrays already have a member
+struct JsonWriter<W, F> {
+    writer: W,
+    formatter: F,
+    /// One entry per open object (`false`) or array (`true`), with whether
+    /// it has no member yet
+    open: Vec<(bool, bool)>,
+    /// Whether string values are normalized to NFC
+    nfc: bool,
+}
 
-        // Capture the publication number if it exists
-        if let Some(pub_num_capture) = capture.captures.get(2) {
-            if let Ok(pub_num) = pub_num_capture.node.utf8_text(usfm.as_bytes()) {
-                publication_number = Some(pub_num.trim().to_string());
-            }
+impl<W: io::Write, F: Formatter> JsonWriter<W, F> {
+    // Marks a new member of the innermost container, returning whether it is the first
+    fn next_member(&mut self) -> bool {
+        match self.open.last_mut() {
+            Some((_, first)) => std::mem::replace(first, false),
+            None => true,
         }
     }
 
-    // Construct the chapter reference (sid)
-    for child in &*content {
-        if child["type"] == "book" {
-            if let Some(code) = child.get("code") {
-                chap_ref = Some(format!(
-                    "{} {}",
-                    code.as_str().unwrap_or(""),
-                    chapter_number.clone().unwrap_or_default()
-                ));
-            }
-            break;
+    fn in_array(&self) -> bool {
+        self.open.last().is_some_and(|(array, _)| *array)
+    }
+
+    fn begin_value(&mut self) -> io::Result<()> {
+        if self.in_array() {
+            let first = self.next_member();
+            self.formatter.begin_array_value(&mut self.writer, first)?;
         }
+        Ok(())
     }
 
-    // Create the chapter JSON object
-    let mut chap_json_obj = json!({
-        "type": "chapter",
-        "marker": "c",
-        "number": chapter_number.clone().unwrap_or_default(),
-        "sid": chap_ref.clone().unwrap_or_default(),
-    });
-    *global_chapter_number = chap_ref.clone();
-    // Add alternative and publication numbers if they exist
-    if let Some(alt) = alt_number {
-        chap_json_obj["altnumber"] = json!(alt);
+    fn end_value(&mut self) -> io::Result<()> {
+        match self.open.last() {
+            Some((true, _)) => self.formatter.end_array_value(&mut self.writer),
+            Some((false, _)) => self.formatter.end_object_value(&mut self.writer),
+            None => Ok(()),
+        }
     }
-    if let Some(pub_num) = publication_number {
-        chap_json_obj["pubnumber"] = json!(pub_num);
+
+    fn begin_object(&mut self) -> io::Result<()> {
+        self.begin_value()?;
+        self.formatter.begin_object(&mut self.writer)?;
+        self.open.push((false, true));
+        Ok(())
     }
 
-    // Append the chapter JSON object to the parent content
-    content.push(chap_json_obj);
+    fn end_object(&mut self) -> io::Result<()> {
+        self.open.pop();
+        self.formatter.end_object(&mut self.writer)?;
+        self.end_value()
+    }
 
-    // Process child nodes of the current node
-    for child in node.children(&mut node.walk()) {
-        if let "cl" | "cd" = child.kind() {
-            node_2_usj(&child, content, usfm);
-        }
+    // Strings are escaped the same way whatever the layout
+    fn escaped(&mut self, value: &str) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, value).map_err(io::Error::from)
     }
-}
 
-pub fn node_2_usj_ca_va(
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
-    usfm: &str,
-) {
-    // Create a query to capture alternative numbers (chapterNumber and verseNumber)
-    let query_source = r#"
-    ('''([
-                                            (chapterNumber)
-                                            (verseNumber)
-                                            ] @alt-num)''')
-    "#;
-
-    let query = Query::new(&tree_sitter_usfm3::language(), query_source)
-        .expect("Failed to create query");
-    let mut cursor = QueryCursor::new();
-
-    // Execute the query against the current node
-    let mut captures = cursor.matches(&query, *node, usfm.as_bytes());
-
-    let mut alt_number = None;
-
-    // Iterate over the captures returned by the query
-    while let Some(capture) = captures.next() {
-        if let Some(alt_capture) = capture.captures.get(0) {
-            if let Ok(num) = alt_capture.node.utf8_text(usfm.as_bytes()) {
-                alt_number = Some(num.trim().to_string());
-            }
-        }
+    fn key(&mut self, key: &str) -> io::Result<()> {
+        let first = self.next_member();
+        self.formatter.begin_object_key(&mut self.writer, first)?;
+        self.escaped(key)?;
+        self.formatter.end_object_key(&mut self.writer)?;
+        self.formatter.begin_object_value(&mut self.writer)
     }
 
-    // Get the style from the node type
-    let style = node.kind().to_string();
+    fn begin_array(&mut self) -> io::Result<()> {
+        self.begin_value()?;
+        self.formatter.begin_array(&mut self.writer)?;
+        self.open.push((true, true));
+        Ok(())
+    }
 
-    // Create the character JSON object
-    let mut char_json_obj = json!({
-        "type": "char",
-        "marker": style,
+    fn end_array(&mut self) -> io::Result<()> {
+        self.open.pop();
+        self.formatter.end_array(&mut self.writer)?;
+        self.end_value()
+    }
 
-    });
+    fn number(&mut self, value: usize) -> io::Result<()> {
+        self.begin_value()?;
+        self.formatter.write_u64(&mut self.writer, value as u64)?;
+        self.end_value()
+    }
 
-    // Add alternative number if it exists
-    if let Some(alt) = alt_number {
-        char_json_obj["altnumber"] = json!(alt);
+    fn string(&mut self, value: &str) -> io::Result<()> {
+        let value = if self.nfc {
+            to_nfc(value)
+        } else {
+            Cow::Borrowed(value)
+        };
+        self.begin_value()?;
+        self.escaped(&value)?;
+        self.end_value()
     }
- 
 
-    content.push(char_json_obj);
+    fn property(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.key(key)?;
+        self.string(value)
+    }
 }
-pub fn node_2_usj_verse(
-    //verified
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
-    usfm: &str,
-
-    chapter_number: &Option<String>,
-) {
-    // Create a query to capture verse information
-
-    //    print_node(node, usfm, 0);
-    let query_source = r#"
-    (v
-        (verseNumber) @vnum
-        (va (verseNumber) @alt)?
-        (vp (text) @vp)?
-    )
-    "#;
-
-    ////println!("{:?}", chapter);
-    let query =
-        Query::new(&tree_sitter_usfm3::language(), query_source).expect("Failed to create query");
-    let mut cursor = QueryCursor::new();
-
-    // Execute the query against the current node
-    let mut captures = cursor.matches(&query, *node, usfm.as_bytes());
-
-    let mut verse_number = None;
-    let mut alt_number = None;
-    let mut publication_number = None;
-    let mut verse_text = String::new();
-
-    // Iterate over the captures returned by the query
-    while let Some(capture) = captures.next() {
-        // Capture the verse number
-        if let Some(vnum_capture) = capture.captures.get(0) {
-            if let Ok(num) = vnum_capture.node.utf8_text(usfm.as_bytes()) {
-                verse_number = Some(num.trim().to_string());
-            }
-        }
 
-        // Capture the alternative verse number if it exists
-        if let Some(alt_capture) = capture.captures.get(1) {
-            if let Ok(alt) = alt_capture.node.utf8_text(usfm.as_bytes()) {
-                alt_number = Some(alt.trim().to_string());
-            }
-        }
+/// State of one conversion. Every node is visited once, by the handler of
+/// its own kind or of its parent, and written as soon as it is visited.
+struct UsjGenerator<'a, W, F> {
+    usfm: &'a str,
+    catalog: &'a MarkerCatalog,
+    cursor: QueryCursor,
+    json: JsonWriter<W, F>,
+    /// Code from `\id`, for chapter sids
+    book_code: Option<String>,
+    /// Sid of the chapter being converted, e.g. `GEN 1`
+    chapter_sid: Option<String>,
+    /// Whether elements get a `span` property
+    spans: bool,
+    source_map: Option<SourceMap>,
+    /// Path of the element being written, as indices in content lists
+    path: Vec<usize>,
+    /// Items written so far to each open content list
+    counts: Vec<usize>,
+}
 
-        // Capture the publication number if it exists
-        if let Some(pub_capture) = capture.captures.get(2) {
-            if let Ok(pub_num) = pub_capture.node.utf8_text(usfm.as_bytes()) {
-                publication_number = Some(pub_num.trim().to_string());
-            }
+impl<'a, W: io::Write, F: Formatter> UsjGenerator<'a, W, F> {
+    fn new(usfm: &'a str, writer: W, formatter: F, options: UsjOptions<'a>) -> Self {
+        Self {
+            usfm,
+            catalog: options.catalog,
+            cursor: QueryCursor::new(),
+            json: JsonWriter {
+                writer,
+                formatter,
+                open: Vec::new(),
+                nfc: options.nfc,
+            },
+            book_code: None,
+            chapter_sid: None,
+            spans: options.spans,
+            source_map: options.source_map.then(SourceMap::default),
+            path: Vec::new(),
+            counts: Vec::new(),
         }
     }
 
-    // Create the verse JSON object
-    let ref_sid = format!(
-        "{}:{}",
-        chapter_number.as_ref().unwrap_or(&"0".to_string()), // Use unwrap_or directly on the Option
-        verse_number.clone().unwrap_or_default()
-    );
-    let mut verse_json_obj = json!({
-        "type": "verse",
-        "marker": "v",
-        "number": verse_number.clone().unwrap_or_default(),
-        "sid": ref_sid
-    });
-
-    // Add alternative and publication numbers if they exist
-    if let Some(alt) = alt_number {
-        verse_json_obj["altnumber"] = json!(alt);
+    fn write(mut self, tree: &tree_sitter::Tree) -> io::Result<SourceMap> {
+        // The id handler fills in the code
+        let _span = debug_span!("book", code = field::Empty).entered();
+        self.json.begin_object()?;
+        self.json.property("type", "USJ")?;
+        self.json.property("version", "3.1")?;
+        self.begin_content()?;
+        self.node_2_usj(&tree.root_node())?;
+        self.end_content()?;
+        self.json.end_object()?;
+        self.json.writer.flush()?;
+        Ok(self.source_map.unwrap_or_default())
     }
-    if let Some(pub_num) = publication_number {
-        verse_json_obj["pubnumber"] = json!(pub_num);
+
+    fn is_note_node(&self, node_type: &str) -> bool {
+        self.catalog.is_note(node_type)
     }
 
-    content.push(verse_json_obj);
-}
+    fn is_char_node(&self, node_type: &str) -> bool {
+        let marker = node_type.strip_suffix("Nested").unwrap_or(node_type);
+        self.catalog.style_type(marker) == Some(StyleType::Character)
+    }
 
-pub fn node_2_usj_para(
-    //verified
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
-    usfm: &str,
-) {
-    let children: Vec<_> = node.children(&mut node.walk()).collect();
+    fn is_para_node(&self, node_type: &str) -> bool {
+        !STRUCTURAL_PARA_NODES.contains(&node_type) && self.catalog.is_paragraph(node_type)
+    }
 
-    if children[0].kind().ends_with("Block") {
-        //  // Move to the first child of the block
-        let mut cursor = node.walk();
-        for child in node.children(&mut node.walk()) {
-            // cursor.goto_first_child();
-            node_2_usj_para(&child, content, usfm);
-        }
-    } else if node.kind() == "paragraph" {
-        let query_source = r#"(paragraph (_) @para-marker)"#;
-        let query = Query::new(&tree_sitter_usfm3::language(), query_source)
-            .expect("Failed to create query");
-        let mut cursor = QueryCursor::new();
-        let mut captures = cursor.matches(&query, *node, usfm.as_bytes());
-
-        if let Some(capture) = captures.next() {
-            if let Some(para_marker) = capture.captures.get(0) {
-                let para_marker = para_marker
-                    .node
-                    .utf8_text(usfm.as_bytes())
-                    .expect("Failed to get tag node text")
-                    .replace("\\", "")
-                    .replace("+", "")
-                    .trim()
-                    .split_whitespace() // Split by whitespace (spaces, newlines, etc.)
-                    .next() // Get the first element
-                    .unwrap_or("") // If there's no element, return an empty string
-                    .to_string(); // Convert to String
-
-                if para_marker == "b" {
-                    let b_json_obj = json!({
-                        "type": "para",
-                        "marker": para_marker,
-                    });
-                    content.push(b_json_obj);
-                } else if !para_marker.ends_with("Block") {
-                    let mut para_json_obj = json!({
-                        "type": "para",
-                        "marker": para_marker,
-                        "content": [],
-                    });
-
-                    for child in node.children(&mut node.walk()) {
-                        // cursor.goto_first_child();
-                        node_2_usj_para(
-                            &child,
-                            &mut para_json_obj["content"].as_array_mut().unwrap(),
-                            usfm,
-                        );
-                    }
-                    content.push(para_json_obj);
-                }
+    // Text of each capture of `query` under `node`, with its capture name
+    fn captures(&mut self, query: &'static Query, node: &Node) -> Vec<(&'static str, &'a str)> {
+        let names = query.capture_names();
+        let mut found = Vec::new();
+        let mut matches = self.cursor.matches(query, *node, self.usfm.as_bytes());
+        while let Some(query_match) = matches.next() {
+            for capture in query_match.captures {
+                found.push((
+                    names[capture.index as usize],
+                    node_text(&capture.node, self.usfm),
+                ));
             }
         }
-    } else if node.kind() == "pi" || node.kind() == "ph" {
-        let para_marker = node
-            .utf8_text(usfm.as_bytes())
-            .expect("Failed to get node text")
-            .trim_start_matches('\\')
-            .to_string();
-        let mut para_json_obj = json!({
-            "type": "para",
-            "marker": para_marker,
-            "content": [],
-        });
+        found
+    }
 
-        for child in node.children(&mut node.walk()) {
-            // cursor.goto_first_child();
-            node_2_usj_para(
-                &child,
-                &mut para_json_obj["content"].as_array_mut().unwrap(),
-                usfm,
-            );
-
-            let child_content = child.utf8_text(usfm.as_bytes()).unwrap_or("").to_string();
+    // The trimmed text of the first capture with a name
+    fn capture(&mut self, query: &'static Query, node: &Node, name: &str) -> Option<String> {
+        self.captures(query, node)
+            .into_iter()
+            .find(|(capture, _)| *capture == name)
+            .map(|(_, text)| text.trim().to_string())
+    }
+
+    // Takes the next place in the open content list for an item converted
+    // from `node`, and returns the item's span
+    fn enter_item(&mut self, node: &Node) -> SourceSpan {
+        let index = self.counts.last_mut().map_or(0, |count| {
+            *count += 1;
+            *count - 1
+        });
+        self.path.push(index);
+        let span = SourceSpan::of(node);
+        if let Some(source_map) = &mut self.source_map {
+            source_map.insert(self.path.clone(), span);
         }
+        span
+    }
 
-        content.push(para_json_obj);
+    // Opens an element converted from `node`; its properties and content follow
+    fn begin_element(&mut self, node: &Node, kind: &str, marker: Option<&str>) -> io::Result<()> {
+        let span = self.enter_item(node);
+        self.json.begin_object()?;
+        self.json.property("type", kind)?;
+        if let Some(marker) = marker {
+            self.json.property("marker", marker)?;
+        }
+        if self.spans {
+            self.json.key("span")?;
+            self.json.begin_object()?;
+            for (key, value) in [
+                ("start", span.start_byte),
+                ("end", span.end_byte),
+                ("row", span.start_row),
+                ("column", span.start_column),
+            ] {
+                self.json.key(key)?;
+                self.json.number(value)?;
+            }
+            self.json.end_object()?;
+        }
+        Ok(())
     }
-}
-pub fn node_2_usj_notes(
-    //verified
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
-    usfm: &str,
-) {
-    // Collect the children into a vector
-    let children: Vec<_> = node.children(&mut node.walk()).collect();
 
-    // Get the tag node and caller node
-    let tag_node = &children[0];
-    let caller_node = &children[1];
+    fn end_element(&mut self) -> io::Result<()> {
+        self.path.pop();
+        self.json.end_object()
+    }
 
-    // Extract the style from the tag node
-    let style = usfm[tag_node.start_byte()..tag_node.end_byte()]
-        .replace("\\", "")
-        .trim()
-        .to_string();
+    fn write_text(&mut self, node: &Node, text: &str) -> io::Result<()> {
+        self.enter_item(node);
+        self.path.pop();
+        self.json.string(text)
+    }
 
-    // Create the note JSON object
-    let mut note_json_obj = json!({
-        "type": "note",
-        "marker": style,
-        "content": [],
-    });
+    fn begin_content(&mut self) -> io::Result<()> {
+        self.counts.push(0);
+        self.json.key("content")?;
+        self.json.begin_array()
+    }
 
-    // Extract the caller text
-    let caller_text = usfm[caller_node.start_byte()..caller_node.end_byte()]
-        .trim()
-        .to_string();
-    note_json_obj["caller"] = json!(caller_text);
-
-    // Process the remaining children (from index 2 to the last child)
-    for child in &children[2..] {
-        node_2_usj(
-            child,
-            note_json_obj["content"].as_array_mut().unwrap(),
-            usfm,
-        );
+    fn end_content(&mut self) -> io::Result<()> {
+        self.counts.pop();
+        self.json.end_array()
     }
 
-    // Append the note JSON object to the parent content
-    content.push(note_json_obj);
-}
-pub fn node_2_usj_char(
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>, // Change back to Vec<Value>
-    usfm: &str,
-) {
-    // Ensure the node has children
-    if node.child_count() == 0 {
-        return;
+    // Writes an element whose content is the children of `node` from index
+    // `skip` on, leaving out `skip_last` at the end
+    fn write_element(
+        &mut self,
+        node: &Node,
+        kind: &str,
+        marker: Option<&str>,
+        skip: usize,
+        skip_last: usize,
+    ) -> io::Result<()> {
+        self.begin_element(node, kind, marker)?;
+        self.write_properties(node)?;
+        self.begin_content()?;
+        self.node_2_usj_children(node, skip, skip_last)?;
+        self.end_content()?;
+        self.end_element()
     }
 
-    // Get the tag node (first child)
-    let tag_node = node.children(&mut node.walk()).next().unwrap();
-    //println!("tag node.............{}",tag_node);
-    // Determine the range of children to process
-    let children: Vec<_> = node.children(&mut node.walk()).collect();
-    let mut children_range = children.len();
-    //println!("count child node.............{}", children_range);
-    if let Some(last_child) = node.child(children_range - 1) {
-        if last_child.kind().starts_with('\\') {
-            children_range -= 1; // Exclude the closing node if it starts with '\'
+    // Writes the attributes and category among the children of `node`
+    fn write_properties(&mut self, node: &Node) -> io::Result<()> {
+        for child in node.children(&mut node.walk()) {
+            match child.kind() {
+                "cat" => {
+                    if let Some(category) = self.capture(&QUERIES.category, &child, "category") {
+                        self.json.property("category", &category)?;
+                    }
+                }
+                kind if kind.ends_with("Attribute") => self.node_2_usj_attrib(&child, node)?,
+                _ => {}
+            }
         }
+        Ok(())
     }
 
-    // Extract the style from the tag node
-    let style = tag_node
-        .utf8_text(usfm.as_bytes())
-        .expect("Failed to get tag node text")
-        .replace("\\", "")
-        .replace("+", "")
-        .trim()
-        .to_string();
-
-    // Create the character JSON object
-    let mut char_json_obj = json!({
-        "type": "char",
-        "marker": style,
-        "content": []
-    });
-
-    // Get a mutable reference to the content array
-    let content_array = char_json_obj["content"]
-        .as_array_mut()
-        .expect("Expected content to be an array");
-
-    // Process child nodes (excluding the first and possibly the last)
-    for i in 0..children_range {
-        if let Some(child) = node.child(i) {
-            node_2_usj(&child, content_array, usfm); // Pass the mutable reference to the content array
+    // Converts the children of `node` from index `skip` on, leaving out
+    // `skip_last` at the end
+    fn node_2_usj_children(
+        &mut self,
+        node: &Node,
+        skip: usize,
+        skip_last: usize,
+    ) -> io::Result<()> {
+        let end = node.child_count().saturating_sub(skip_last);
+        let mut cursor = node.walk();
+        if !cursor.goto_first_child() {
+            return Ok(());
+        }
+        let mut index = 0;
+        while index < end {
+            if index >= skip {
+                self.node_2_usj(&cursor.node())?;
+            }
+            index += 1;
+            if !cursor.goto_next_sibling() {
+                break;
+            }
         }
+        Ok(())
     }
-    let mut child_cursor = node.walk();
-    child_cursor.goto_first_child(); // Move to the first child
-
-    // Process the remaining children
-    while child_cursor.goto_next_sibling() {
-        let child = child_cursor.node();
-        node_2_usj(
-            &child,
-            &mut char_json_obj["content"].as_array_mut().unwrap(),
-            usfm,
+
+    fn node_2_usj(&mut self, node: &Node) -> io::Result<()> {
+        let node_type = node.kind();
+        trace!(
+            kind = node_type,
+            start = node.start_byte(),
+            end = node.end_byte(),
+            "node"
         );
+        match node_type {
+            "id" => self.node_2_usj_id(node),
+            "chapter" => self.node_2_usj_chapter(node),
+            "cl" | "cp" | "cd" | "vp" => self.node_2_usj_generic(node),
+            "ca" | "va" => self.node_2_usj_ca_va(node),
+            "v" => self.node_2_usj_verse(node),
+            "verseText" => self.node_2_usj_children(node, 0, 0),
+            "paragraph" | "pi" | "ph" => self.node_2_usj_para(node),
+            "text" => self.node_2_usj_text(node),
+            "table" | "tr" => self.node_2_usj_table(node),
+            "milestone" => self.node_2_usj_milestone(node),
+            "zNameSpace" => self.node_2_usj_z(node),
+            "esb" | "fig" | "ref" => self.node_2_usj_special(node),
+            _ if is_property_node(node_type) => Ok(()),
+            _ if TABLE_CELL_MARKERS.contains(&node_type) => self.node_2_usj_table(node),
+            _ if self.is_note_node(node_type) => self.node_2_usj_notes(node),
+            _ if self.is_char_node(node_type) || node_type == "xt_standalone" => {
+                self.node_2_usj_char(node)
+            }
+            _ if self.is_para_node(node_type.replace('\\', "").trim()) => {
+                self.node_2_usj_generic(node)
+            }
+            // Skip white space nodes
+            _ if ["", "|"].contains(&node_type.trim()) => Ok(()),
+            _ => self.node_2_usj_children(node, 0, 0),
+        }
     }
 
-    // Append the character JSON object to the parent JSON object
-    content.push(char_json_obj); // Push the character JSON object directly to the Vec
-}
-
-pub fn node_2_usj_attrib(
-    //verified
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
-    usfm: &str,
-) {
-    // Get the attribute name node
-    let attrib_name_node = node.child(0).expect("Node should have at least one child");
-    let mut attrib_name = attrib_name_node
-        .utf8_text(usfm.as_bytes())
-        .unwrap()
-        .trim()
-        .to_string();
-
-    // Handle special cases for attribute names
-    if attrib_name == "|" {
-        attrib_name = DEFAULT_ATTRIB_MAP
-            .iter()
-            .find(|&&(key, _)| key == node.parent().unwrap().kind())
-            .map(|&(_, value)| value.to_string())
-            .unwrap_or(attrib_name);
-    }
-    if attrib_name == "src" {
-        attrib_name = "file".to_string();
+    fn node_2_usj_id(&mut self, node: &Node) -> io::Result<()> {
+        let captures = self.captures(&QUERIES.id, node);
+        let capture = |name: &str| {
+            captures
+                .iter()
+                .find(|(capture, _)| *capture == name)
+                .map(|(_, text)| text.trim())
+        };
+        // A known code written in lower case is stored in upper case
+        let code = capture("book-code").unwrap_or_default();
+        let code = match code.to_uppercase() {
+            upper if is_book_code(&upper) => upper,
+            _ => code.to_string(),
+        };
+        self.begin_element(node, "book", Some("id"))?;
+        self.json.property("code", &code)?;
+        self.begin_content()?;
+        let description_node = node
+            .children(&mut node.walk())
+            .find(|child| child.kind() == "description");
+        if let (Some(description), Some(description_node)) = (
+            capture("desc").filter(|desc| !desc.is_empty()),
+            description_node,
+        ) {
+            self.write_text(&description_node, description)?;
+        }
+        self.end_content()?;
+        self.end_element()?;
+        Span::current().record("code", code.as_str());
+        self.book_code = Some(code);
+        Ok(())
     }
 
-    // Query to capture attribute values
-    let query_source = r#"
-    ((attributeValue) @attrib-val)
-    "#;
-
-    let query =
-        Query::new(&tree_sitter_usfm3::language(), query_source).expect("Failed to create query");
-    let mut cursor = QueryCursor::new();
-
-    // Execute the query against the current node
-    let mut captures = cursor.matches(&query, *node, usfm.as_bytes());
-
-    // Initialize attrib_value
-    let attrib_value: String;
-
-    // Capture the attribute value
-    let mut value = "";
-    if let Some(capture) = captures.next() {
-        if let Some(attrib_value_capture) = capture.captures.get(0) {
-            value = attrib_value_capture
-                .node
-                .utf8_text(usfm.as_bytes())
-                .unwrap()
-                .trim();
+    fn node_2_usj_chapter(&mut self, node: &Node) -> io::Result<()> {
+        // The c handler fills in the number
+        let _span = debug_span!("chapter", number = field::Empty).entered();
+        for child in node.children(&mut node.walk()) {
+            if child.kind() == "c" {
+                self.node_2_usj_c(&child)?;
+            } else {
+                self.node_2_usj(&child)?;
+            }
         }
+        Ok(())
     }
-    if value.len() > 0 {
-        attrib_value = value.to_string(); // Assign the string value
-    } else {
-        attrib_value = "".to_string(); // Reset if the number is not greater than 0
-    }
-
-    // Create the JSON object
-    let mut attribute_json_obj = json!({
-        attrib_name: attrib_value
-    });
-    // println!("ATTRIB ::::{}",attrib_value);
-    // Append the JSON object to the content
-    content.push(attribute_json_obj);
-}
 
-pub fn node_2_usj_table(
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
-    usfm: &str,
-) {
-    let node_type = node.kind();
-    match node_type {
-        "table" => {
-            // Create a JSON object for the table
-            let mut table_json_obj = json!({
-                "type": "table",
-                "content": [],
-            });
-
-            // Process all children of the table
-            for child in node.named_children(&mut node.walk()) {
-                node_2_usj(
-                    &child,
-                    table_json_obj["content"].as_array_mut().unwrap(),
-                    usfm,
-                );
-            }
+    fn node_2_usj_c(&mut self, node: &Node) -> io::Result<()> {
+        let captures = self.captures(&QUERIES.chapter, node);
+        let capture = |name: &str| {
+            captures
+                .iter()
+                .find(|(capture, _)| *capture == name)
+                .map(|(_, text)| text.trim())
+        };
+        // Without an \id the book code is left out, as in ` 1`
+        let chapter_number = capture("chap-num").unwrap_or_default();
+        let sid = format!(
+            "{} {}",
+            self.book_code.as_deref().unwrap_or_default(),
+            chapter_number
+        );
 
-            // Append the table JSON object to the parent content
-            content.push(table_json_obj);
+        self.begin_element(node, "chapter", Some("c"))?;
+        self.json.property("number", chapter_number)?;
+        if let Some(alt) = capture("alt-num") {
+            self.json.property("altnumber", alt)?;
         }
+        if let Some(pub_num) = capture("pub-num") {
+            self.json.property("pubnumber", pub_num)?;
+        }
+        self.json.property("sid", &sid)?;
+        self.end_element()?;
+        Span::current().record("number", chapter_number);
+        self.chapter_sid = Some(sid);
 
-        "tr" => {
-            // Create a JSON object for the table row
-            let mut row_json_obj = json!({
-                "type": "table:row",
-                "marker": "tr",
-                "content": [],
-            });
-
-            // Process all children of the row, skipping the first child
-            for child in node.named_children(&mut node.walk()).skip(1) {
-                node_2_usj(
-                    &child,
-                    row_json_obj["content"].as_array_mut().unwrap(),
-                    usfm,
-                );
+        for child in node.children(&mut node.walk()) {
+            if let "cl" | "cd" = child.kind() {
+                self.node_2_usj(&child)?;
             }
+        }
+        Ok(())
+    }
 
-            // Append the row JSON object to the parent content
-            content.push(row_json_obj);
+    fn node_2_usj_ca_va(&mut self, node: &Node) -> io::Result<()> {
+        self.begin_element(node, "char", Some(node.kind()))?;
+        if let Some(number) = self.capture(&QUERIES.alt_number, node, "alt-num") {
+            self.json.property("altnumber", &number)?;
         }
+        self.end_element()
+    }
 
-        _ if TABLE_CELL_MARKERS.contains(&node_type) => {
-            // Handle table cell markers
-            let tag_node = node.named_child(0).expect("Expected a child node for tag");
-            let style = tag_node
-                .utf8_text(usfm.as_bytes())
-                .expect("Failed to get cell style")
-                .replace("\\", "")
-                .trim()
-                .to_string();
-
-            // Create a JSON object for the table cell
-            let mut cell_json_obj = json!({
-                "type": "table:cell",
-                "marker": style,
-                "content": [],
-            });
+    fn node_2_usj_verse(&mut self, node: &Node) -> io::Result<()> {
+        let captures = self.captures(&QUERIES.verse, node);
+        let capture = |name: &str| {
+            captures
+                .iter()
+                .find(|(capture, _)| *capture == name)
+                .map(|(_, text)| text.trim())
+        };
+
+        // The sid spells out the parsed number (`1-3`, `4a`) and keeps the
+        // text of a number that does not parse
+        let verse_number = capture("vnum").unwrap_or_default();
+        let sid_number = VerseNumber::parse(verse_number)
+            .map_or_else(|_| verse_number.to_string(), |number| number.to_string());
+        let sid = format!(
+            "{}:{}",
+            self.chapter_sid.as_deref().unwrap_or("0"),
+            sid_number
+        );
+        self.begin_element(node, "verse", Some("v"))?;
+        self.json.property("number", verse_number)?;
+        if let Some(alt) = capture("alt") {
+            self.json.property("altnumber", alt)?;
+        }
+        self.json.property("sid", &sid)?;
+        if let Some(pub_num) = capture("vp") {
+            self.json.property("pubnumber", pub_num)?;
+        }
+        self.end_element()
+    }
 
-            // Set alignment based on the style
-            if style.contains("r") {
-                cell_json_obj["align"] = json!("end");
-            } else {
-                cell_json_obj["align"] = json!("start");
+    fn node_2_usj_para(&mut self, node: &Node) -> io::Result<()> {
+        let Some(first) = node.child(0) else {
+            return Ok(());
+        };
+        if first.kind().ends_with("Block") {
+            for child in first.children(&mut first.walk()) {
+                self.node_2_usj_para(&child)?;
             }
-
-            // Process all children of the cell, skipping the first child
-            for child in node.named_children(&mut node.walk()).skip(1) {
-                node_2_usj(
-                    &child,
-                    cell_json_obj["content"].as_array_mut().unwrap(),
-                    usfm,
-                );
+        } else if node.kind() == "paragraph" {
+            let Some(marker_node) = node.named_child(0) else {
+                return Ok(());
+            };
+            let marker = marker_node.kind();
+            if marker == "b" {
+                self.begin_element(&marker_node, "para", Some("b"))?;
+                self.end_element()?;
+            } else if !marker.ends_with("Block") {
+                self.write_element(&marker_node, "para", Some(marker), 1, 0)?;
             }
-
-            // Append the cell JSON object to the parent content
-            content.push(cell_json_obj);
-        }
-
-        _ => {}
-    }
-}
-pub fn node_2_usj_milestone(
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
-    usfm: &str,
-) {
-    // Create a query to capture the milestone or zNameSpace name
-    let query_source = r#"
-    [
-        (milestoneTag) @ms-name
-        (milestoneStartTag) @ms-name
-        (milestoneEndTag) @ms-name
-        (zSpaceTag) @ms-name
-    ]
-    "#;
-    let query =
-        Query::new(&tree_sitter_usfm3::language(), query_source).expect("Failed to create query");
-    let mut cursor = QueryCursor::new();
-
-    // Execute the query against the current node
-    let mut captures = cursor.matches(&query, *node, usfm.as_bytes());
-
-    // Extract the style from the captures
-    let style = if let Some(capture) = captures.next() {
-        if let Some(ms_name_capture) = capture.captures.get(0) {
-            let style = ms_name_capture
-                .node
-                .utf8_text(usfm.as_bytes())
-                .expect("Failed to get milestone or zNameSpace text")
-                .replace("\\", "")
-                .trim()
-                .to_string();
-            style
         } else {
-            String::new()
+            // `\pi#` and `\ph#`, whose tag gives the level
+            let marker = tag_marker(&first, self.usfm);
+            self.write_element(node, "para", Some(&marker), 1, 0)?;
         }
-    } else {
-        String::new()
-    };
+        Ok(())
+    }
 
-    // Create the milestone or zNameSpace JSON object
-    let mut ms_json_obj = json!({
-        "type": "ms",
-        "marker": style,
-        "content": [],
-    });
-
-    // Process all children
-    for child in node.named_children(&mut node.walk()) {
-        if child.kind().ends_with("Attribute") {
-            node_2_usj(&child, ms_json_obj["content"].as_array_mut().unwrap(), usfm);
+    fn node_2_usj_text(&mut self, node: &Node) -> io::Result<()> {
+        let text = node_text(node, self.usfm).replace('\n', " ");
+        if !text.trim().is_empty() {
+            self.write_text(node, &text)?;
         }
+        Ok(())
     }
 
-    // If the content is empty, remove the content field
-    if ms_json_obj["content"].as_array().unwrap().is_empty() {
-        ms_json_obj.as_object_mut().unwrap().remove("content");
+    fn node_2_usj_notes(&mut self, node: &Node) -> io::Result<()> {
+        let (Some(tag_node), Some(caller_node)) = (node.child(0), node.child(1)) else {
+            return Ok(());
+        };
+        self.begin_element(node, "note", Some(&tag_marker(&tag_node, self.usfm)))?;
+        self.json
+            .property("caller", node_text(&caller_node, self.usfm).trim())?;
+        self.write_properties(node)?;
+        let closed = node
+            .child(node.child_count() - 1)
+            .is_some_and(|last| last.kind().ends_with('*'));
+        self.begin_content()?;
+        self.node_2_usj_children(node, 2, usize::from(closed))?;
+        self.end_content()?;
+        self.end_element()
     }
 
-    // Append the milestone or zNameSpace JSON object to the parent content
-    content.push(ms_json_obj);
-}
-pub fn node_2_usj_special(
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
-    usfm: &str,
-) {
-    let query_source = r#"
-                                ('''(
-                                 [(milestoneTag)
-                                 (milestoneStartTag)
-                                 (milestoneEndTag)
-                                 (zSpaceTag)
-                                 ] @ms-name)''')
-                                "#;
-    let node_type = node.kind();
-   if node_type=="esb" {
-            // Create a JSON object for the sidebar
-            let mut sidebar_json_obj = json!({
-                "type": "sidebar",
-                "marker": "esb",
-                "content": [],
-            });
-
-            // Create a TreeCursor to iterate through the children
-            let mut cursor = node.walk();
-            cursor.goto_first_child(); // Move to the first child
-
-            let mut index = 0; // Initialize an index counter
-
-            // Process all children except the first and last
-            while cursor.goto_next_sibling() {
-                let child = cursor.node();
-                index += 1; // Increment the index counter
+    fn node_2_usj_char(&mut self, node: &Node) -> io::Result<()> {
+        let Some(tag_node) = node.child(0) else {
+            return Ok(());
+        };
+        // Exclude the closing node if it starts with '\'
+        let closed = node.child_count() > 1
+            && node
+                .child(node.child_count() - 1)
+                .is_some_and(|last| last.kind().starts_with('\\'));
+        let marker = tag_marker(&tag_node, self.usfm);
+        self.write_element(node, "char", Some(&marker), 1, usize::from(closed))
+    }
 
-                // Skip the first and last child
-                if index > 0 && index < node.named_child_count() - 1 {
-                    node_2_usj(
-                        &child,
-                        sidebar_json_obj["content"].as_array_mut().unwrap(),
-                        usfm,
-                    );
-                }
+    fn node_2_usj_attrib(&mut self, node: &Node, owner: &Node) -> io::Result<()> {
+        let Some(name_node) = node.child(0) else {
+            return Ok(());
+        };
+        let mut name = node_text(&name_node, self.usfm).trim().to_string();
+        if name == "|" {
+            if let Some(default) = self
+                .catalog
+                .default_attribute(&attribute_owner(owner, self.usfm))
+            {
+                name = default.to_string();
             }
-
-            // Append the sidebar JSON object to the parent content
-            content.push(sidebar_json_obj);
         }
+        if name == "src" {
+            name = "file".to_string();
+        }
+        let value = self
+            .capture(&QUERIES.attribute_value, node, "attrib-val")
+            .unwrap_or_default();
+        self.json.property(&name, &value)
+    }
 
-        else if node_type=="cat"{
-            // Create a query to capture the category
-            let query_source = r#"
-                                        (category) @category
-                                    "#;
-            let query = Query::new(&tree_sitter_usfm3::language(), query_source)
-                .expect("Failed to create query");
-            let mut cursor = QueryCursor::new();
-
-            // Execute the query against the current node
-            let mut captures = cursor.matches(&query, *node, usfm.as_bytes());
-
-            // Extract the category from the captures
-            if let Some(capture) = captures.next() {
-                if let Some(category_capture) = capture.captures.get(0) {
-                    let category = category_capture
-                        .node
-                        .utf8_text(usfm.as_bytes())
-                        .expect("Failed to get category text")
-                        .trim()
-                        .to_string();
-                    // Add the category to the parent JSON object
-                    content.push(json!({
-                        "type": "category",
-                        "marker": "cat",
-                        "category": category,
-                    }));
-                }
+    fn node_2_usj_table(&mut self, node: &Node) -> io::Result<()> {
+        match node.kind() {
+            "table" => self.write_element(node, "table", None, 0, 0),
+            "tr" => self.write_element(node, "table:row", Some("tr"), 1, 0),
+            _ => {
+                let Some(tag_node) = node.child(0) else {
+                    return Ok(());
+                };
+                let marker = tag_marker(&tag_node, self.usfm);
+                let align = if marker.contains('r') { "end" } else { "start" };
+                self.begin_element(node, "table:cell", Some(&marker))?;
+                self.json.property("align", align)?;
+                self.write_properties(node)?;
+                self.begin_content()?;
+                self.node_2_usj_children(node, 1, 0)?;
+                self.end_content()?;
+                self.end_element()
             }
         }
+    }
 
-        else if node_type=="fig" {
-            // Create a JSON object for the figure
-            let mut fig_json_obj = json!({
-                "type": "figure",
-                "marker": "fig",
-                "content": [],
-            });
-
-            // Create a TreeCursor to iterate through the children
-            let mut cursor = node.walk();
-            cursor.goto_first_child(); // Move to the first child
-
-            let mut index = 0; // Initialize an index counter
-
-            // Process all children except the first and last
-            while cursor.goto_next_sibling() {
-                let child = cursor.node();
-                index += 1; // Increment the index counter
+    fn node_2_usj_milestone(&mut self, node: &Node) -> io::Result<()> {
+        let marker = self
+            .capture(&QUERIES.milestone, node, "ms-name")
+            .unwrap_or_default()
+            .replace('\\', "");
+        self.begin_element(node, "ms", Some(marker.trim()))?;
+        self.write_properties(node)?;
+        self.end_element()
+    }
 
-                // Skip the first and last child
-                if index > 0 && index < node.named_child_count() - 1 {
-                    node_2_usj(
-                        &child,
-                        fig_json_obj["content"].as_array_mut().unwrap(),
-                        usfm,
-                    );
-                }
+    // A `\z` marker, which the grammar does not know: a character or
+    // paragraph style when the catalog says so, and a milestone otherwise
+    fn node_2_usj_z(&mut self, node: &Node) -> io::Result<()> {
+        let Some(tag_node) = node.child(0) else {
+            return Ok(());
+        };
+        let marker = tag_marker(&tag_node, self.usfm);
+        let kind = match self.catalog.style_type(&marker) {
+            Some(StyleType::Character) => "char",
+            Some(StyleType::Paragraph) => "para",
+            _ => return self.node_2_usj_milestone(node),
+        };
+        self.begin_element(node, kind, Some(&marker))?;
+        self.write_properties(node)?;
+        self.begin_content()?;
+        for (index, child) in node.children(&mut node.walk()).enumerate().skip(1) {
+            if child.kind() != "text" {
+                self.node_2_usj(&child)?;
+                continue;
+            }
+            // The tag does not take the space after it, as other tags do
+            let text = node_text(&child, self.usfm).replace('\n', " ");
+            let text = match index {
+                1 => text.strip_prefix(' ').unwrap_or(&text),
+                _ => &text,
+            };
+            if !text.trim().is_empty() {
+                self.write_text(&child, text)?;
             }
-
-            // Append the figure JSON object to the parent content
-            content.push(fig_json_obj);
         }
-
+        self.end_content()?;
+        self.end_element()
     }
 
-pub fn node_2_usj_generic(
-    node: &tree_sitter::Node,
-    content: &mut Vec<serde_json::Value>,
-    usfm: &str,
-) {
-    // Get the first child node to determine the style
-    //let tag_node = node.child(0).expect("Expected a tag node");
-    // let mut binding=node.walk();
-    // let tag_node=node.children(&mut binding).next().unwrap();
-    let mut style_cap = node
-        .utf8_text(usfm.as_bytes())
-        .expect("Failed to get tag node text")
-        .replace("\\", "")
-        .replace("+", "")
-        .trim()
-        .to_string();
-    //println!("style_cap1:{:?}",style_cap);
-    // Clean up the style string
-    let style = if style_cap.starts_with('\\') {
-        style_cap.replace('\\', "").trim().to_string()
-    } else {
-        node.kind().to_string()
-    };
-    // println!("style:{}",style_cap);
-    let mut children_range_start = 1;
-
-    // Check if the second child is a numbered style
-    if node.named_child_count() > 1
-        && node
-            .named_child(1)
-            .expect("Expected a numbered child")
-            .kind()
-            .starts_with("numbered")
-    {
-        let num_node = node.named_child(1).expect("Expected a numbered child");
-        let num = num_node
-            .utf8_text(usfm.as_bytes())
-            .expect("Failed to get number text")
-            .to_string();
-        let style = format!("{}{}", style, num);
-        children_range_start = 2; // Start from the third child
+    fn node_2_usj_special(&mut self, node: &Node) -> io::Result<()> {
+        match node.kind() {
+            "esb" => self.write_element(node, "sidebar", Some("esb"), 1, 1),
+            "fig" => self.write_element(node, "figure", Some("fig"), 1, 1),
+            _ => self.write_element(node, "ref", None, 1, 1),
+        }
     }
 
-    // Create the paragraph JSON object
-    let mut para_json_obj = json!({
-        "type": "para",
-        "marker": style,
-        "content": [],
-    });
-
-    // Append the paragraph object to the parent content
-    content.push(para_json_obj.clone());
-
-    // Process the remaining children
-    for child in node.children(&mut node.walk()) {
-        if CHAR_STYLE_MARKERS.contains(&child.kind())
-            || NESTED_CHAR_STYLE_MARKERS.contains(&child.kind())
-            || [
-                "text",
-                "footnote",
-                "crossref",
-                "verseText",
-                "v",
-                "b",
-                "milestone",
-                "zNameSpace",
-            ]
-            .contains(&child.kind())
-        {
-            // Only nest these types inside the upper para style node
-            node_2_usj(
-                &child,
-                para_json_obj["content"].as_array_mut().unwrap(),
-                usfm,
-            );
+    fn node_2_usj_generic(&mut self, node: &Node) -> io::Result<()> {
+        let Some(tag_node) = node.child(0) else {
+            return Ok(());
+        };
+        let tag = node_text(&tag_node, self.usfm);
+        let mut marker = if tag.starts_with('\\') {
+            tag_marker(&tag_node, self.usfm)
         } else {
-            node_2_usj(&child, content, usfm);
+            node.kind().to_string()
+        };
+        // A level written as its own node, as in `\s 2`
+        let mut skip = 1;
+        if let Some(number) = node
+            .child(1)
+            .filter(|child| child.kind().starts_with("numbered"))
+        {
+            marker.push_str(node_text(&number, self.usfm).trim());
+            skip = 2;
         }
-    }
-}
-pub fn print_node(node: &tree_sitter::Node, usfm: &str, depth: usize) {
-    let indent = "  ".repeat(depth);
-    let node_text = node.utf8_text(usfm.as_bytes()).unwrap_or_default();
-    //println!("{}Node: {}, Text: {}", indent, node.kind(), node_text);
-
-    for child in node.children(&mut node.walk()) {
-        print_node(&child, usfm, depth + 1);
+        let kind = if node.kind() == "vp" { "char" } else { "para" };
+
+        // Only text, verses, notes and character markers go inside the
+        // paragraph; anything else follows it
+        let (inside, after): (Vec<_>, Vec<_>) = node
+            .children(&mut node.walk())
+            .skip(skip)
+            .partition(|child| {
+                self.is_char_node(child.kind()) || PARA_CONTENT_NODES.contains(&child.kind())
+            });
+        self.begin_element(node, kind, Some(&marker))?;
+        self.write_properties(node)?;
+        self.begin_content()?;
+        for child in &inside {
+            self.node_2_usj(child)?;
+        }
+        self.end_content()?;
+        self.end_element()?;
+        for child in &after {
+            self.node_2_usj(child)?;
+        }
+        Ok(())
     }
 }
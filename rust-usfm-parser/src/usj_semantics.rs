@@ -0,0 +1,194 @@
+use serde_json::Value;
+
+use crate::diagnostics::UsjError;
+use crate::stylesheet::{MarkerCatalog, StyleType};
+
+// What is known at a point of the document, walking it in order
+#[derive(Default)]
+struct Scope {
+    book: Option<String>,
+    chapter: Option<String>,
+}
+
+/// Checks what the schema cannot: verses outside chapters, `sid`s that
+/// disagree with the book, chapter or verse they are on, markers used as a
+/// node type of another kind and notes without a caller.
+pub fn validate_usj_semantics(usj: &Value, catalog: &MarkerCatalog) -> Vec<UsjError> {
+    let mut errors = Vec::new();
+    if let Some(content) = usj.get("content").and_then(Value::as_array) {
+        check_content(
+            content,
+            "/content",
+            &mut Scope::default(),
+            catalog,
+            &mut errors,
+        );
+    }
+    errors
+}
+
+fn check_content(
+    content: &[Value],
+    path: &str,
+    scope: &mut Scope,
+    catalog: &MarkerCatalog,
+    errors: &mut Vec<UsjError>,
+) {
+    for (index, node) in content.iter().enumerate() {
+        if node.is_object() {
+            check_node(node, &format!("{}/{}", path, index), scope, catalog, errors);
+        }
+    }
+}
+
+fn check_node(
+    node: &Value,
+    path: &str,
+    scope: &mut Scope,
+    catalog: &MarkerCatalog,
+    errors: &mut Vec<UsjError>,
+) {
+    let field = |name: &str| node.get(name).and_then(Value::as_str);
+    let mut error = |path: String, message: String| errors.push(UsjError { path, message });
+    let marker = field("marker").unwrap_or_default();
+
+    match field("type").unwrap_or_default() {
+        "book" => scope.book = field("code").map(str::to_string),
+        "chapter" => {
+            let number = field("number").unwrap_or_default();
+            if let Some(sid) = field("sid") {
+                if let Some(message) = sid_problem(sid, scope.book.as_deref(), number) {
+                    error(format!("{}/sid", path), message);
+                }
+            }
+            scope.chapter = Some(number.to_string());
+        }
+        "verse" => {
+            let number = field("number").unwrap_or_default();
+            match &scope.chapter {
+                None => error(
+                    path.to_string(),
+                    format!("Verse {} is not inside a chapter", number),
+                ),
+                Some(chapter) => {
+                    let expected = format!("{}:{}", chapter, number);
+                    if let Some(sid) = field("sid") {
+                        if let Some(message) = sid_problem(sid, scope.book.as_deref(), &expected) {
+                            error(format!("{}/sid", path), message);
+                        }
+                    }
+                }
+            }
+        }
+        "para" => check_kind(
+            marker,
+            StyleType::Paragraph,
+            "para",
+            path,
+            catalog,
+            &mut error,
+        ),
+        "char" => check_kind(
+            marker,
+            StyleType::Character,
+            "char",
+            path,
+            catalog,
+            &mut error,
+        ),
+        "ms" => check_kind(
+            marker,
+            StyleType::Milestone,
+            "ms",
+            path,
+            catalog,
+            &mut error,
+        ),
+        "note" => {
+            check_kind(marker, StyleType::Note, "note", path, catalog, &mut error);
+            if field("caller").is_none_or(|caller| caller.trim().is_empty()) {
+                error(path.to_string(), format!("Note \\{} has no caller", marker));
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(content) = node.get("content").and_then(Value::as_array) {
+        check_content(
+            content,
+            &format!("{}/content", path),
+            scope,
+            catalog,
+            errors,
+        );
+    }
+}
+
+// Flags a marker the stylesheet defines as another kind than the node type
+fn check_kind(
+    marker: &str,
+    expected: StyleType,
+    node_type: &str,
+    path: &str,
+    catalog: &MarkerCatalog,
+    error: &mut impl FnMut(String, String),
+) {
+    match catalog.style_type(marker) {
+        Some(style_type) if style_type != expected => error(
+            format!("{}/marker", path),
+            format!(
+                "\\{} is a {} marker and cannot be used as {}",
+                marker,
+                kind_name(style_type),
+                node_type
+            ),
+        ),
+        _ => {}
+    }
+}
+
+fn kind_name(style_type: StyleType) -> &'static str {
+    match style_type {
+        StyleType::Paragraph => "paragraph",
+        StyleType::Character => "character",
+        StyleType::Note => "note",
+        StyleType::Milestone => "milestone",
+    }
+}
+
+// Compares a `sid` such as `GEN 1:2` with the book code and the reference
+// (`1` for a chapter, `1:2` for a verse) it should be made of
+fn sid_problem(sid: &str, book: Option<&str>, reference: &str) -> Option<String> {
+    let (sid_book, sid_reference) = sid.split_once(' ').unwrap_or((sid, ""));
+    match book {
+        Some(book) if sid_book != book => Some(format!(
+            "sid \"{}\" does not match the book code {}",
+            sid, book
+        )),
+        Some(book) if !same_reference(sid_reference, reference) => Some(format!(
+            "sid \"{}\" should be \"{} {}\"",
+            sid, book, reference
+        )),
+        None if !same_reference(sid_reference, reference) => {
+            Some(format!("sid \"{}\" does not match {}", sid, reference))
+        }
+        _ => None,
+    }
+}
+
+// `91:1` and `091:01` are the same reference
+fn same_reference(a: &str, b: &str) -> bool {
+    let trim_zeros = |reference: &str| {
+        let mut trimmed = String::new();
+        let mut chars = reference.chars().peekable();
+        while let Some(c) = chars.next() {
+            let starts_number = !trimmed.ends_with(|c: char| c.is_ascii_digit());
+            if c == '0' && starts_number && chars.peek().is_some_and(char::is_ascii_digit) {
+                continue;
+            }
+            trimmed.push(c);
+        }
+        trimmed
+    };
+    trim_zeros(a) == trim_zeros(b)
+}
@@ -1,13 +1,32 @@
 use jsonschema::{Draft, JSONSchema};
 use serde_json::Value;
-
-use crate::schema::USJ_SCHEMA;
+use std::borrow::Cow;
+use std::path::Path;
+use tree_sitter::Tree;
+
+use crate::attributes::check_attributes;
+use crate::char_markers::analyze_char_markers;
+use crate::characters::check_characters;
+use crate::diagnostics::{Diagnostic, Severity, UsjError};
+use crate::lint::{mask_markers, LintConfig};
+use crate::milestones::pair_milestones;
+use crate::placement::check_placement;
+use crate::reference::check_references;
+use crate::relaxng::RelaxNgError;
+use crate::scanner::scan_markers;
+use crate::schema::{USJ_SCHEMA, USX_SCHEMA};
+use crate::structure::{check_chapter_count, check_structure};
+use crate::stylesheet::MarkerCatalog;
+use crate::text_checks::{check_punctuation_pairs, check_text};
+use crate::usj_semantics::validate_usj_semantics;
 
 pub struct Validator {
     schema: JSONSchema,
     message: String,
     pub parser: crate::parser::USFMParser, // Add the parser field
     pub errors: Vec<String>,               // Store the compiled schema (optional until needed)
+    pub catalog: MarkerCatalog,            // Markers known from usfm.sty and custom.sty
+    pub lint_config: LintConfig,           // Rule settings from usfm-lint.toml
 }
 
 impl Validator {
@@ -20,29 +39,54 @@ impl Validator {
         Ok(Self {
             schema,
             message: String::new(),
-            parser: crate::parser::USFMParser::new().unwrap(),
+            parser: crate::parser::USFMParser::new()?,
             errors: Vec::new(),
+            catalog: MarkerCatalog::usfm(),
+            lint_config: LintConfig::default(),
         })
     }
 
-    pub fn is_valid_usj(&mut self, usj: &str) -> Result<bool, String> {
+    /// Adds the marker definitions of a project stylesheet (`custom.sty`).
+    pub fn load_custom_stylesheet<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        self.catalog.load_custom(path)
+    }
+
+    /// Checks USJ against the USJ 3.1 schema. Every error is returned with
+    /// the JSON Pointer of the value it is about; none means the USJ is valid.
+    pub fn is_valid_usj(&mut self, usj: &str) -> Result<Vec<UsjError>, String> {
         let parsed_json: Value =
             serde_json::from_str(usj).map_err(|e| format!("Invalid JSON: {}", e))?;
 
-        let validation_result = self.schema.validate(&parsed_json);
-        match validation_result {
-            Ok(_) => {
-                self.message.clear();
-                Ok(true)
-            }
-            Err(errors) => {
-                self.message = errors
-                    .map(|error| format!("{}", error))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                Ok(false)
-            }
-        }
+        let errors = match self.schema.validate(&parsed_json) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.map(UsjError::from).collect(),
+        };
+        Ok(errors)
+    }
+
+    /// Checks USJ for content that is schema-valid but inconsistent, using
+    /// the markers of the stylesheets loaded.
+    pub fn validate_usj_semantics(&self, usj: &str) -> Result<Vec<UsjError>, String> {
+        let parsed_json: Value =
+            serde_json::from_str(usj).map_err(|e| format!("Invalid JSON: {}", e))?;
+        Ok(validate_usj_semantics(&parsed_json, &self.catalog))
+    }
+
+    /// Checks a USX document against `schemas/usx.rng`; the errors are kept
+    /// in the message, one per line.
+    pub fn is_valid_usx(&mut self, usx: &str) -> Result<bool, String> {
+        let errors = self.validate_usx(usx)?;
+        self.message = errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(errors.is_empty())
+    }
+
+    /// The positioned schema errors of a USX document.
+    pub fn validate_usx(&self, usx: &str) -> Result<Vec<RelaxNgError>, String> {
+        USX_SCHEMA.validate(usx)
     }
 
     pub fn get_message(&self) -> &str {
@@ -50,26 +94,22 @@ impl Validator {
     }
 
     pub fn is_valid_usfm(&mut self, usfm: &str) -> Result<bool, String> {
-        // Parse USFM and handle the Result
+        // Parsed through USFMParser so usj_generator finds the tree; its
+        // syntax errors are reported by the syntax-error rule below
         let tree = self
             .parser
-            .parse_usfm(&usfm)
-            .map_err(|e| format!("Failed to parse USFM: {}", e))?;
-
-        self.errors.clear();
-        let root_node = tree.root_node(); // Now `tree` has a `root_node` method
-
-        if root_node.has_error() {
-            self.errors.push(root_node.to_sexp());
-        }
-
-        let mut missing_nodes = Vec::new();
-        self.check_for_missing(&root_node, &mut missing_nodes); // Collect missing nodes
-
-        for node in missing_nodes {
-            self.errors
-                .push(format!("Missing node: {:?}", node.to_sexp()));
-        }
+            .parse_tree(usfm)
+            .ok_or("Failed to parse the USFM string.")?;
+        let diagnostics = match mask_markers(usfm, &self.lint_config.ignore_markers) {
+            Cow::Borrowed(usfm) => self.lint_tree(usfm, &tree),
+            // Blanking the ignored markers changes what the grammar sees
+            Cow::Owned(masked) => self.lint(&masked)?,
+        };
+        self.errors = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
 
         if !self.errors.is_empty() {
             Err(self.format_errors(usfm))
@@ -78,17 +118,118 @@ impl Validator {
         }
     }
 
-    fn check_for_missing<'a>(
+    /// Runs every check and returns what the lint configuration keeps, with
+    /// its severities, in document order.
+    pub fn lint(&mut self, usfm: &str) -> Result<Vec<Diagnostic>, String> {
+        let usfm = mask_markers(usfm, &self.lint_config.ignore_markers);
+        let usfm = usfm.as_ref();
+        let tree = self
+            .parser
+            .parser
+            .parse(usfm, None)
+            .ok_or("Failed to parse the USFM string.")?;
+        Ok(self.lint_tree(usfm, &tree))
+    }
+
+    // The checks of `lint` over text whose ignored markers are already
+    // blanked out, and its tree
+    fn lint_tree(&self, usfm: &str, tree: &Tree) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        self.find_syntax_errors(&tree.root_node(), usfm, &mut diagnostics);
+        diagnostics.extend(self.find_unknown_markers(usfm));
+        diagnostics.extend(self.check_placement(usfm));
+        diagnostics.extend(self.check_char_markers(usfm));
+        diagnostics.extend(self.check_milestones(usfm));
+        diagnostics.extend(check_structure(usfm));
+        if let Some(versification) = self.lint_config.versification {
+            diagnostics.extend(check_chapter_count(usfm, versification));
+        }
+        diagnostics.extend(check_attributes(usfm, &self.catalog));
+        diagnostics.extend(check_text(usfm, &self.catalog, &self.lint_config.quotes));
+        diagnostics.extend(check_characters(
+            usfm,
+            &self.catalog,
+            self.lint_config.allowed_characters.as_ref(),
+        ));
+        diagnostics.extend(check_references(usfm, &self.catalog));
+
+        let mut diagnostics = self.lint_config.apply(diagnostics);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.start_byte);
+        diagnostics
+    }
+
+    /// Reads rule settings from a `usfm-lint.toml` file.
+    pub fn load_lint_config<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        self.lint_config = LintConfig::load(path)?;
+        Ok(())
+    }
+
+    /// Markers used outside the contexts their stylesheet (`\OccursUnder`) allows.
+    pub fn check_placement(&self, usfm: &str) -> Vec<Diagnostic> {
+        check_placement(usfm, &self.catalog)
+    }
+
+    /// Unclosed, stray and crossed character and note markers.
+    pub fn check_char_markers(&self, usfm: &str) -> Vec<Diagnostic> {
+        analyze_char_markers(usfm, &self.catalog).diagnostics()
+    }
+
+    /// Orphaned, mismatched and improperly nested milestone pairs.
+    pub fn check_milestones(&self, usfm: &str) -> Vec<Diagnostic> {
+        pair_milestones(usfm, &self.catalog).diagnostics()
+    }
+
+    /// Unpaired brackets and quotation marks, paired over the whole book.
+    pub fn check_punctuation_pairs(&self, usfm: &str) -> Vec<Diagnostic> {
+        check_punctuation_pairs(usfm, &self.catalog, &self.lint_config.quotes)
+    }
+
+    // Markers not defined by the stylesheets. The \z namespace is reserved for
+    // project-specific markers and is always accepted.
+    fn find_unknown_markers(&self, usfm: &str) -> Vec<Diagnostic> {
+        scan_markers(usfm)
+            .iter()
+            .filter(|token| {
+                !token.marker.starts_with('z')
+                    && !self.catalog.contains(&token.marker)
+                    && self.catalog.opener_of(&token.marker).is_none()
+            })
+            .map(|token| {
+                Diagnostic::at_marker(
+                    "unknown-marker",
+                    format!("Unknown marker \\{}", token.marker),
+                    token,
+                )
+            })
+            .collect()
+    }
+
+    fn find_syntax_errors(
         &self,
-        node: &tree_sitter::Node<'a>,
-        errors: &mut Vec<tree_sitter::Node<'a>>,
+        node: &tree_sitter::Node,
+        usfm: &str,
+        diagnostics: &mut Vec<Diagnostic>,
     ) {
         for child in node.children(&mut node.walk()) {
-            if child.is_missing() {
-                errors.push(child);
+            let message = if child.is_missing() {
+                format!("Missing {}", child.kind())
+            } else if child.is_error() {
+                let text = usfm[child.start_byte()..child.end_byte()].trim();
+                let line = text.lines().next().unwrap_or_default();
+                format!("Syntax error at \"{}\"", line)
             } else {
-                self.check_for_missing(&child, errors);
-            }
+                self.find_syntax_errors(&child, usfm, diagnostics);
+                continue;
+            };
+            let start = child.start_position();
+            diagnostics.push(Diagnostic::new(
+                "syntax-error",
+                message,
+                child.start_byte(),
+                child.end_byte(),
+                start.row,
+                start.column,
+            ));
         }
     }
 
@@ -0,0 +1,189 @@
+use crate::reference::VerseNumber;
+use crate::scanner::{scan_markers, word_after, MarkerToken};
+use crate::stylesheet::{MarkerCatalog, StyleType};
+
+/// A run of verse text copied unchanged from the USFM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextPiece {
+    /// Byte offset of the run in [`VerseText::text`]
+    pub offset: usize,
+    /// Byte offset of the run in the USFM
+    pub source: usize,
+    /// Whether the run starts a paragraph (or poetry line) of the verse
+    pub paragraph_start: bool,
+}
+
+/// The readable text of one verse: no markers, verse numbers, attributes,
+/// notes or figures, and nothing from headings between its paragraphs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerseText {
+    pub chapter: String,
+    pub verse: String,
+    pub text: String,
+    pub pieces: Vec<TextPiece>,
+}
+
+impl VerseText {
+    /// The USFM byte an offset in `text` was copied from.
+    pub fn source_byte(&self, offset: usize) -> usize {
+        let index = self.pieces.partition_point(|piece| piece.offset <= offset);
+        match index.checked_sub(1).map(|index| &self.pieces[index]) {
+            Some(piece) => piece.source + offset - piece.offset,
+            None => self.pieces.first().map_or(0, |piece| piece.source),
+        }
+    }
+
+    /// The verse number, unless it is not one (such as `2-`).
+    pub fn number(&self) -> Option<VerseNumber> {
+        VerseNumber::parse(&self.verse).ok()
+    }
+
+    /// `chapter:verse`, as used in messages.
+    pub fn reference(&self) -> String {
+        format!("{}:{}", self.chapter, self.verse)
+    }
+}
+
+// Markers whose content is not verse text: notes and markers such as \fig,
+// \vp or \cat
+fn skips_content(marker: &str, catalog: &MarkerCatalog) -> bool {
+    catalog.get(marker).is_some_and(|info| {
+        info.style_type == Some(StyleType::Note)
+            || (info.style_type == Some(StyleType::Character) && !info.is_text_type("VerseText"))
+    })
+}
+
+struct Extractor<'a> {
+    usfm: &'a str,
+    verses: Vec<VerseText>,
+    current: Option<VerseText>,
+    // Set by a paragraph marker until the first run of text after it
+    paragraph_start: bool,
+}
+
+impl Extractor<'_> {
+    fn add_text(&mut self, start: usize, end: usize) {
+        let Some(verse) = self.current.as_mut() else {
+            return;
+        };
+        // Attributes after `|` are not text
+        let run = &self.usfm[start..end];
+        let run = run.split('|').next().unwrap_or_default();
+        if run.is_empty() {
+            return;
+        }
+        verse.pieces.push(TextPiece {
+            offset: verse.text.len(),
+            source: start,
+            paragraph_start: std::mem::take(&mut self.paragraph_start),
+        });
+        verse.text.push_str(run);
+    }
+
+    fn end_verse(&mut self) {
+        self.verses.extend(self.current.take());
+    }
+}
+
+/// Collects the text of every verse, in order.
+pub fn extract_verse_text(usfm: &str, catalog: &MarkerCatalog) -> Vec<VerseText> {
+    let tokens = scan_markers(usfm);
+    let mut extractor = Extractor {
+        usfm,
+        verses: Vec::new(),
+        current: None,
+        paragraph_start: false,
+    };
+    let mut chapter = String::new();
+    // Text is taken from `cursor` up to the next marker
+    let mut cursor = 0;
+    // Inside a heading or another paragraph that is not verse text
+    let mut paused = false;
+    // The marker whose content is being skipped, with its nesting depth
+    let mut skipping: Option<(&MarkerToken, usize)> = None;
+
+    for token in &tokens {
+        if let Some((open, depth)) = skipping.as_mut() {
+            if token.marker == open.marker {
+                *depth = if token.closing {
+                    *depth - 1
+                } else {
+                    *depth + 1
+                };
+            }
+            if *depth == 0 {
+                skipping = None;
+                cursor = token.end_byte;
+            }
+            continue;
+        }
+        if !paused {
+            extractor.add_text(cursor.min(token.start_byte), token.start_byte);
+        }
+        cursor = after_delimiter(usfm, token.end_byte);
+
+        match token.marker.as_str() {
+            "c" => {
+                extractor.end_verse();
+                chapter = word_after(usfm, token).to_string();
+                cursor = after_delimiter(usfm, end_of_word(usfm, token));
+            }
+            "v" if !token.closing => {
+                extractor.end_verse();
+                extractor.current = Some(VerseText {
+                    chapter: chapter.clone(),
+                    verse: word_after(usfm, token).to_string(),
+                    text: String::new(),
+                    pieces: Vec::new(),
+                });
+                paused = false;
+                cursor = after_delimiter(usfm, end_of_word(usfm, token));
+            }
+            _ if token.closing => cursor = token.end_byte,
+            marker if skips_content(marker, catalog) => skipping = Some((token, 1)),
+            marker if catalog.is_milestone(marker) => {
+                cursor = after_milestone(usfm, token.end_byte)
+            }
+            marker if catalog.is_paragraph(marker) => {
+                paused = !catalog
+                    .get(marker)
+                    .is_some_and(|info| info.is_text_type("VerseText"));
+                extractor.paragraph_start = true;
+            }
+            _ => {}
+        }
+    }
+    if !paused && skipping.is_none() {
+        extractor.add_text(cursor.min(usfm.len()), usfm.len());
+    }
+    extractor.end_verse();
+    extractor.verses
+}
+
+// Skips the single space or line break that ends a marker
+pub(crate) fn after_delimiter(usfm: &str, byte: usize) -> usize {
+    match usfm[byte..].chars().next() {
+        Some('\r') if usfm[byte..].starts_with("\r\n") => byte + 2,
+        Some(c) if c.is_whitespace() => byte + c.len_utf8(),
+        _ => byte,
+    }
+}
+
+// End of the number after \c or \v
+pub(crate) fn end_of_word(usfm: &str, token: &MarkerToken) -> usize {
+    let word = word_after(usfm, token);
+    if word.is_empty() {
+        return token.end_byte;
+    }
+    let start = token.end_byte + usfm[token.end_byte..].find(word).unwrap_or_default();
+    start + word.len()
+}
+
+// Skips the attributes and `\*` that end a milestone on its line
+pub(crate) fn after_milestone(usfm: &str, byte: usize) -> usize {
+    let line_end = usfm[byte..].find('\n').map_or(usfm.len(), |end| byte + end);
+    match usfm[byte..line_end].find("\\*") {
+        Some(end) => byte + end + 2,
+        None => byte,
+    }
+}
@@ -1,3 +1,5 @@
+// Shared by several test crates, each of which uses only part of it
+#![allow(dead_code)]
 pub use regex::Regex;
 pub use rust_usfm::parser::{Filter, USFMParser};
 pub use std::fs;
@@ -10,6 +12,7 @@ pub use lazy_static::lazy_static;
 
 
 use roxmltree;
+use std::ops::Deref;
 use std::sync::Mutex;
 use strum::IntoEnumIterator;
 
@@ -286,3 +289,48 @@ pub const EXCLUDE_USX_FILES: &[&str] = &[
     // \v and other contents contained inside \lit. New docs doesnt have \lit
     "tests/usfmjsTests/usfm-body-testF/origin.xml",
 ];
+
+/// A fresh folder under the system temp directory, removed again when
+/// dropped so a failing test does not leave it behind.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        Self::with_files::<&str>(name, &[])
+    }
+
+    /// The process id keeps runs of the same test from sharing a folder.
+    pub fn with_files<C: AsRef<[u8]>>(name: &str, files: &[(&str, C)]) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "rust-usfm-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, content) in files {
+            fs::write(dir.join(file), content).unwrap();
+        }
+        TempDir(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,164 @@
+// test_batch.rs
+
+mod common;
+
+use common::TempDir;
+use rust_usfm::batch::{run_batch, BatchOptions, BatchReport, FileReport};
+use rust_usfm::parser::USFMParser;
+use rust_usfm::usj_generator::usj_from_tree;
+use std::fs;
+use std::path::PathBuf;
+
+// The sample files of the corpus folders
+fn corpus_files(folders: &[&str]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for folder in folders {
+        let pattern = format!("../tests/{}/*/origin.usfm", folder);
+        files.extend(glob::glob(&pattern).unwrap().map(Result::unwrap));
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let files = corpus_files(&["basic", "specExamples"]);
+        assert!(files.len() > 10);
+        let options = BatchOptions {
+            threads: Some(4),
+            ..BatchOptions::default()
+        };
+        let report = run_batch(&files, &options).unwrap();
+        let again = run_batch(&files, &options).unwrap();
+        assert_eq!(report, again);
+
+        let mut parser = USFMParser::new().unwrap();
+        for (file, path) in report.files.iter().zip(&files) {
+            assert_eq!(&file.path, path);
+            let Some(usj) = &file.usj else {
+                continue;
+            };
+            let usfm = fs::read_to_string(path).unwrap();
+            let tree = parser.parser.parse(&usfm, None).unwrap();
+            assert_eq!(usj, &usj_from_tree(&tree, &usfm).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let mut files = corpus_files(&["basic"]);
+        files.push(PathBuf::from("../tests/missing/origin.usfm"));
+        let options = BatchOptions {
+            threads: Some(2),
+            convert: false,
+            lint: true,
+            ..BatchOptions::default()
+        };
+        let report = run_batch(&files, &options).unwrap();
+        let summary = report.summary();
+        assert_eq!(summary.files, files.len());
+        assert_eq!(summary.converted, 0);
+        assert_eq!(summary.failed, 1);
+        assert!(summary.warnings > 0);
+        assert!(!report.is_success());
+        assert!(report
+            .files
+            .last()
+            .unwrap()
+            .error
+            .as_ref()
+            .unwrap()
+            .starts_with("Cannot read"));
+        assert!(report.to_string().ends_with(&format!(
+            "{} files, 0 converted, 1 failed, {} errors, {} warnings",
+            summary.files, summary.errors, summary.warnings
+        )));
+    }
+
+    #[test]
+    fn test_syntax_errors_still_convert() {
+        let dir = TempDir::with_files(
+            "batch-syntax",
+            &[("GEN.usfm", "\\id GEN\n\\c 1\n\\p\n\\v 1 In \\w the|\\w* beginning\n")],
+        );
+        let options = BatchOptions {
+            lint: true,
+            ..BatchOptions::default()
+        };
+        let report = run_batch(&[dir.join("GEN.usfm")], &options).unwrap();
+        let file = &report.files[0];
+        assert_eq!(file.error, None);
+        assert!(file.usj.as_ref().unwrap().contains("beginning"));
+        assert!(file
+            .diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.rule == "syntax-error"));
+    }
+
+    #[test]
+    fn test_nfc_output() {
+        let dir = TempDir::with_files(
+            "batch-nfc",
+            &[("GEN.usfm", "\\id GEN\n\\c 1\n\\p\n\\v 1 cafe\u{301}\n")],
+        );
+        let files = [dir.join("GEN.usfm")];
+        let options = BatchOptions {
+            nfc: true,
+            ..BatchOptions::default()
+        };
+        let report = run_batch(&files, &options).unwrap();
+        assert!(report.files[0].usj.as_ref().unwrap().contains("café"));
+
+        let out = TempDir::new("batch-nfc-out");
+        report.write_usfm(&out).unwrap();
+        assert_eq!(
+            fs::read_to_string(out.join("GEN.usfm")).unwrap(),
+            "\\id GEN\n\\c 1\n\\p\n\\v 1 café\n"
+        );
+        let plain = run_batch(&files, &BatchOptions::default()).unwrap();
+        assert!(plain.files[0].usj.as_ref().unwrap().contains("cafe\u{301}"));
+        assert_eq!(plain.files[0].usfm, None);
+    }
+
+    #[test]
+    fn test_write_usj_keeps_clashing_names_apart() {
+        let paths = [
+            "a/x/GEN.usfm",
+            "b/x/GEN.usfm",
+            "x/GEN.SFM",
+            "c/gen.usfm",
+            "y/EXO.usfm",
+        ];
+        let files = paths
+            .iter()
+            .map(|path| FileReport {
+                path: PathBuf::from(path),
+                usj: Some(path.to_string()),
+                usfm: None,
+                diagnostics: Vec::new(),
+                error: None,
+            })
+            .collect();
+        let out = TempDir::new("batch-clash-out");
+        BatchReport { files }.write_usj(&out).unwrap();
+
+        let mut written: Vec<_> = fs::read_dir(&*out)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        written.sort();
+        assert_eq!(written.len(), paths.len());
+        for (name, path) in [
+            ("x-GEN.json", "a/x/GEN.usfm"),
+            ("x-GEN-2.json", "b/x/GEN.usfm"),
+            ("x-GEN-3.json", "x/GEN.SFM"),
+            ("c-gen.json", "c/gen.usfm"),
+            ("EXO.json", "y/EXO.usfm"),
+        ] {
+            assert_eq!(fs::read_to_string(out.join(name)).unwrap(), path);
+        }
+    }
+}
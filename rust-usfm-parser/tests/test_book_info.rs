@@ -0,0 +1,63 @@
+// test_book_info.rs
+
+use rust_usfm::book_info::{book_info, BookInfo, Title};
+use rust_usfm::parser::USFMParser;
+use rust_usfm::stylesheet::MarkerCatalog;
+
+fn title(level: u8, text: &str) -> Title {
+    Title {
+        level,
+        text: text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_book_info() {
+        let usfm = "\\id MRK 41MRKGNT92.SFM, Good News\n\\usfm 3.0\n\\ide UTF-8\n\\h Mark\n\\toc1 The Gospel according to Mark\n\\toc2 Mark\n\\toc3 Mk\n\\toca1 Marcos\n\\mt2 The Gospel\n\\mt1 \\w Mark|lemma=\"Markos\"\\w*\\f + \\fr 1.0 \\ft note\\f*\n\\c 1\n\\mt Not a title\n\\p\n\\v 1 Text\n";
+        let info = book_info(usfm, &MarkerCatalog::usfm()).unwrap();
+        assert_eq!(
+            info,
+            BookInfo {
+                code: "MRK".to_string(),
+                description: Some("41MRKGNT92.SFM, Good News".to_string()),
+                usfm_version: Some("3.0".to_string()),
+                encoding: Some("UTF-8".to_string()),
+                header: Some("Mark".to_string()),
+                long_name: Some("The Gospel according to Mark".to_string()),
+                short_name: Some("Mark".to_string()),
+                abbreviation: Some("Mk".to_string()),
+                alternate_long_name: Some("Marcos".to_string()),
+                alternate_short_name: None,
+                alternate_abbreviation: None,
+                titles: vec![title(2, "The Gospel"), title(1, "Mark")],
+            }
+        );
+    }
+
+    #[test]
+    fn test_partial_headers() {
+        let catalog = MarkerCatalog::usfm();
+        assert_eq!(book_info("\\c 1\n\\p\n\\v 1 Text\n", &catalog), None);
+
+        let info = book_info("\\id PHM\n\\p\n\\v 1 Paul\n\\mt Late\n", &catalog).unwrap();
+        assert_eq!(info.code, "PHM");
+        assert_eq!(info.description, None);
+        assert!(info.titles.is_empty());
+
+        let mut parser = USFMParser::new().unwrap();
+        assert_eq!(parser.book_info(), None);
+        parser
+            .parse_usfm("\\id TIT Titus\n\\h Titus\n\\mt Titus\n\\c 1\n\\p\n\\v 1 Paul\n")
+            .unwrap();
+        let info = parser.book_info().unwrap();
+        assert_eq!(
+            (info.code.as_str(), info.header.as_deref()),
+            ("TIT", Some("Titus"))
+        );
+        assert_eq!(info.titles, vec![title(1, "Titus")]);
+    }
+}
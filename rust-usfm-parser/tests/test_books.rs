@@ -0,0 +1,105 @@
+// test_books.rs
+
+use rust_usfm::books::{
+    book, book_name, canonical_order, is_book_code, sort_by_canon, Testament, Versification, BOOKS,
+};
+use rust_usfm::characters::CharacterInventory;
+use rust_usfm::parser::USFMParser;
+use rust_usfm::stylesheet::MarkerCatalog;
+use rust_usfm::usj_generator::usj_generator;
+use rust_usfm::validator::Validator;
+use serde_json::Value;
+
+fn book_code_messages(usfm: &str) -> Vec<String> {
+    let mut validator = Validator::new().unwrap();
+    validator
+        .lint(usfm)
+        .unwrap()
+        .into_iter()
+        .filter(|diagnostic| diagnostic.rule == "unknown-book-code")
+        .map(|diagnostic| diagnostic.message)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_book_catalog() {
+        assert_eq!(BOOKS.len(), 116);
+        assert!(is_book_code("GEN") && is_book_code("XXA") && is_book_code("GLO"));
+        assert!(!is_book_code("gen") && !is_book_code("XYZ"));
+        assert_eq!(book_name("1CO"), Some("1 Corinthians"));
+
+        let genesis = book("GEN").unwrap();
+        assert_eq!(genesis.testament, Testament::Old);
+        assert_eq!(genesis.chapter_count(Versification::English), Some(50));
+        assert_eq!(book("TOB").unwrap().testament, Testament::Deuterocanon);
+        assert_eq!(book("FRT").unwrap().testament, Testament::Peripheral);
+        assert_eq!(
+            book("FRT").unwrap().chapter_count(Versification::Original),
+            None
+        );
+
+        let malachi = book("MAL").unwrap();
+        assert_eq!(malachi.chapter_count(Versification::Original), Some(3));
+        assert_eq!(malachi.chapter_count(Versification::English), Some(4));
+        assert_eq!(
+            book("PSA")
+                .unwrap()
+                .chapter_count(Versification::Septuagint),
+            Some(151)
+        );
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        assert!(canonical_order("FRT") < canonical_order("GEN"));
+        assert!(canonical_order("MAL") < canonical_order("MAT"));
+        assert!(canonical_order("REV") < canonical_order("TOB"));
+        assert!(canonical_order("LAO") < canonical_order("GLO"));
+        assert_eq!(canonical_order("XYZ"), None);
+
+        let mut files = vec!["GLO.usfm", "XYZ.usfm", "REV.usfm", "GEN.usfm", "FRT.usfm"];
+        sort_by_canon(&mut files, |file| &file[..3]);
+        assert_eq!(
+            files,
+            ["FRT.usfm", "GEN.usfm", "REV.usfm", "GLO.usfm", "XYZ.usfm"]
+        );
+
+        let catalog = MarkerCatalog::usfm();
+        let mut inventory = CharacterInventory::new();
+        inventory.add_book(
+            "\\id REV\n\\c 1\n\\p\n\\v 1 a\n\\v 2 a\n\\v 3 a\n",
+            &catalog,
+        );
+        inventory.add_book("\\id GEN\n\\c 2\n\\p\n\\v 4 a\n", &catalog);
+        assert_eq!(
+            inventory.get('a').unwrap().references,
+            ["GEN 2:4", "REV 1:1", "REV 1:2"]
+        );
+    }
+
+    #[test]
+    fn test_book_code_lint() {
+        assert!(book_code_messages("\\id GEN\n\\c 1\n\\p\n\\v 1 a\n").is_empty());
+        assert_eq!(
+            book_code_messages("\\id php\n\\c 1\n\\p\n\\v 1 a\n"),
+            vec!["Book code \"php\" should be \"PHP\""]
+        );
+        assert_eq!(
+            book_code_messages("\\id XYZ Notes\n\\c 1\n\\p\n\\v 1 a\n"),
+            vec!["\\id has unknown book code \"XYZ\""]
+        );
+    }
+
+    #[test]
+    fn test_usj_book_code() {
+        let usfm = "\\id php Letter\n\\c 1\n\\p\n\\v 1 Paul\n";
+        USFMParser::new().unwrap().parse_usfm(usfm).unwrap();
+        let usj: Value = serde_json::from_str(&usj_generator(usfm).unwrap()).unwrap();
+        assert_eq!(usj["content"][0]["code"], "PHP");
+        assert_eq!(usj["content"][1]["sid"], "PHP 1");
+    }
+}
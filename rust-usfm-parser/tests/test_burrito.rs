@@ -0,0 +1,139 @@
+// test_burrito.rs
+
+mod common;
+
+use common::TempDir;
+use jsonschema::{Draft, JSONSchema};
+use rust_usfm::burrito::{
+    book_scope, burrito_metadata, export_burrito, Burrito, BurritoOptions, ID_AUTHORITY,
+    USFM_MIME_TYPE,
+};
+use rust_usfm::project::Project;
+use serde_json::Value;
+use std::path::PathBuf;
+
+const JUDE: &str = "\\id JUD\n\\toc1 The Letter of Jude\n\\toc2 Jude\n\\toc3 Jud\n\\c 1\n\\p\n\\v 1 Jude\n\\v 2-3 Mercy\n";
+const MARK: &str =
+    "\\id MRK\n\\c 1\n\\p\n\\v 1 A\n\\v 2 B\n\\c 2\n\\p\n\\v 1 C\n\\c 3\n\\p\n\\v 5-7 D\n\\v 9 E\n";
+
+fn options() -> BurritoOptions {
+    BurritoOptions {
+        name: "Example Translation".to_string(),
+        abbreviation: Some("EXT".to_string()),
+        language_tag: "en".to_string(),
+        language_name: "English".to_string(),
+        copyright: None,
+        date_created: Some("2024-05-01T09:30:00Z".to_string()),
+        id: None,
+    }
+}
+
+// Errors of the metadata against the Scripture Burrito 1.0 source metadata schema
+fn schema_errors(metadata: &Value) -> Vec<String> {
+    let schema: Value = serde_json::from_str(include_str!(
+        "../../schemas/burrito_source_metadata.schema.json"
+    ))
+    .unwrap();
+    let schema = JSONSchema::options()
+        .with_draft(Draft::Draft7)
+        .compile(&schema)
+        .unwrap();
+    let errors = match schema.validate(metadata) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|error| format!("{}: {}", error.instance_path, error))
+            .collect(),
+    };
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_book_scope() {
+        assert!(book_scope("JUD", JUDE).is_empty());
+        assert_eq!(book_scope("MRK", MARK), ["1-2", "3:5-7", "3:9"]);
+        assert!(book_scope("GLO", "\\id GLO\n\\p Words\n").is_empty());
+        assert_eq!(
+            book_scope("OBA", "\\id OBA\n\\c 1\n\\p\n\\v 2 Text\n"),
+            ["1:2"]
+        );
+    }
+
+    #[test]
+    fn test_export_and_read() {
+        let project = Project::from_sources(vec![
+            (PathBuf::from("jude.usfm"), JUDE.to_string()),
+            (PathBuf::from("mark.usfm"), MARK.to_string()),
+        ]);
+        let dir = TempDir::new("burrito-export");
+        let metadata = export_burrito(&project, &dir, &options()).unwrap();
+
+        assert_eq!(metadata["meta"]["dateCreated"], "2024-05-01T09:30:00Z");
+        assert_eq!(
+            metadata["type"]["flavorType"]["currentScope"]["MRK"][0],
+            "1-2"
+        );
+        let jude = &metadata["ingredients"]["ingredients/JUD.usfm"];
+        assert_eq!(jude["size"], JUDE.len());
+        assert_eq!(jude["checksum"]["md5"].as_str().unwrap().len(), 32);
+        assert_eq!(jude["scope"]["JUD"], serde_json::json!([]));
+        assert_eq!(metadata["localizedNames"]["book-jud"]["abbr"]["en"], "Jud");
+        assert_eq!(schema_errors(&metadata), Vec::<String>::new());
+        assert!(metadata["identification"]["primary"][ID_AUTHORITY].is_object());
+
+        let burrito = Burrito::load(&dir).unwrap();
+        assert_eq!(burrito.name(), Some("Example Translation"));
+        assert_eq!(burrito.ingredients.len(), 2);
+        assert_eq!(
+            burrito.ingredients["ingredients/MRK.usfm"].mime_type,
+            USFM_MIME_TYPE
+        );
+        let read = burrito.project().unwrap();
+        assert_eq!(read.books.len(), 2);
+        assert_eq!(read.book("JUD").unwrap().usfm, JUDE);
+    }
+
+    #[test]
+    fn test_metadata_defaults_are_valid() {
+        let usfm = "\\id RUT\n\\toc2 Ruth\n\\c 1\n\\p\n\\v 1 Nao\u{301}mi\n";
+        let project = Project::from_sources(vec![(PathBuf::from("ruth.usfm"), usfm.to_string())]);
+        let options = BurritoOptions {
+            abbreviation: None,
+            date_created: None,
+            ..options()
+        };
+        let metadata = burrito_metadata(&project, &options);
+        assert_eq!(schema_errors(&metadata), Vec::<String>::new());
+        assert_eq!(metadata["localizedNames"]["book-rut"]["long"]["en"], "Ruth");
+        let ruth = &metadata["ingredients"]["ingredients/RUT.usfm"];
+        assert_eq!(ruth["size"], usfm.len() - 1);
+    }
+
+    #[test]
+    fn test_locate_ingredients() {
+        let metadata = r#"{
+            "format": "scripture burrito",
+            "meta": { "defaultLocale": "fr" },
+            "identification": { "name": { "en": "Bible", "fr": "Bible en français" } },
+            "ingredients": {
+                "release/GEN.usx": { "mimeType": "text/x-usx", "size": 10 },
+                "release/EXO.SFM": { "mimeType": "text/plain" },
+                "release/LICENSE.md": { "mimeType": "text/markdown" }
+            }
+        }"#;
+        let burrito = Burrito::from_metadata(PathBuf::from("b"), metadata).unwrap();
+        assert_eq!(burrito.name(), Some("Bible en français"));
+        assert_eq!(
+            burrito.usx_ingredients(),
+            [PathBuf::from("b/release/GEN.usx")]
+        );
+        assert_eq!(
+            burrito.usfm_ingredients(),
+            [PathBuf::from("b/release/EXO.SFM")]
+        );
+        assert!(Burrito::from_metadata(PathBuf::new(), "{\"format\": \"other\"}").is_err());
+    }
+}
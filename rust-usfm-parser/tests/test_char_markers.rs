@@ -0,0 +1,105 @@
+// test_char_markers.rs
+
+use rust_usfm::char_markers::{analyze_char_markers, CharMarkerAnalysis, PairingIssueKind};
+use rust_usfm::stylesheet::USFM_CATALOG;
+use rust_usfm::validator::Validator;
+
+fn analyze(usfm: &str) -> CharMarkerAnalysis {
+    analyze_char_markers(usfm, &USFM_CATALOG)
+}
+
+fn kinds(analysis: &CharMarkerAnalysis) -> Vec<PairingIssueKind> {
+    analysis.issues.iter().map(|issue| issue.kind).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairs_nested_markers() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 \\wj Say \\+nd Lord\\+nd* now\\wj* \\f + \\fr 1.1: \\ft a \\+nd b\\+nd*\\f*\n";
+        let analysis = analyze(usfm);
+        assert!(analysis.issues.is_empty());
+        let pairs: Vec<(&str, bool)> = analysis
+            .pairs
+            .iter()
+            .map(|pair| (pair.open.marker.as_str(), pair.close.nested))
+            .collect();
+        assert_eq!(pairs, vec![("nd", true), ("wj", false), ("nd", true), ("f", false)]);
+    }
+
+    #[test]
+    fn test_linked_reference_in_text_is_paired() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 See \\xt Gen 2.4\\xt* and go on\n";
+        let analysis = analyze(usfm);
+        assert!(analysis.issues.is_empty());
+        assert_eq!(analysis.pairs[0].open.marker, "xt");
+    }
+
+    #[test]
+    fn test_unclosed_suggests_end_of_paragraph() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 The \\nd Lord is here.\n\\p\n\\v 2 Text\n";
+        let analysis = analyze(usfm);
+        assert_eq!(kinds(&analysis), vec![PairingIssueKind::Unclosed]);
+        let issue = &analysis.issues[0];
+        assert_eq!(
+            issue.message,
+            "\\nd is not closed; add \\nd* at the end of the paragraph"
+        );
+        let at = issue.suggested_closer.unwrap();
+        assert_eq!(&usfm[..at], "\\id GEN\n\\c 1\n\\p\n\\v 1 The \\nd Lord is here.");
+    }
+
+    #[test]
+    fn test_closer_after_verse_boundary() {
+        let usfm = "\\id MAT\n\\c 5\n\\p\n\\v 3 \\wj Blessed are the poor,\n\\v 4 blessed are those who mourn.\\wj*\n";
+        let analysis = analyze(usfm);
+        assert_eq!(kinds(&analysis), vec![PairingIssueKind::SpansVerse]);
+        assert_eq!(
+            analysis.issues[0].message,
+            "\\wj runs on into the next verse; add \\wj* at the end of verse 3"
+        );
+        let at = analysis.issues[0].suggested_closer.unwrap();
+        assert!(usfm[..at].ends_with("the poor,"));
+    }
+
+    #[test]
+    fn test_stray_and_crossed_markers() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 \\add a \\+nd b\\add* c\\+nd* d\\bd*\n";
+        let analysis = analyze(usfm);
+        assert_eq!(
+            kinds(&analysis),
+            vec![PairingIssueKind::Crossed, PairingIssueKind::StrayClosing]
+        );
+        assert_eq!(
+            analysis.issues[0].message,
+            "\\add* closes \\add while \\+nd opened inside it is still open; add \\+nd* before it"
+        );
+        assert_eq!(analysis.issues[1].message, "\\bd* has no matching \\bd");
+        assert_eq!(analysis.pairs.len(), 2);
+    }
+
+    #[test]
+    fn test_markers_unclosed_inside_note() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 Text\\f + \\ft a \\+bd bold\\f* more\n";
+        let analysis = analyze(usfm);
+        assert_eq!(kinds(&analysis), vec![PairingIssueKind::Unclosed]);
+        assert_eq!(
+            analysis.issues[0].message,
+            "\\+bd is not closed; add \\+bd* before \\f*"
+        );
+    }
+
+    #[test]
+    fn test_validator_reports_unclosed_markers() {
+        let mut validator = Validator::new().unwrap();
+        let diagnostics =
+            validator.check_char_markers("\\id GEN\n\\c 1\n\\p\n\\v 1 The \\nd Lord\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].row, diagnostics[0].column), (3, 9));
+        assert!(validator
+            .is_valid_usfm("\\id GEN\n\\c 1\n\\p\n\\v 1 The \\nd Lord\n")
+            .is_err());
+    }
+}
@@ -0,0 +1,144 @@
+// test_characters.rs
+
+use rust_usfm::characters::{
+    check_characters, normalize_usfm, to_nfc, CharacterInventory, CharacterSet,
+};
+use rust_usfm::lint::LintConfig;
+use rust_usfm::parser::USFMParser;
+use rust_usfm::stylesheet::MarkerCatalog;
+use rust_usfm::usj_generator::{write_usj_with, UsjOptions};
+use serde_json::Value;
+use std::borrow::Cow;
+
+fn messages(usfm: &str, allowed: Option<&CharacterSet>) -> Vec<(&'static str, String)> {
+    check_characters(usfm, &MarkerCatalog::usfm(), allowed)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.message))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inventory() {
+        let catalog = MarkerCatalog::usfm();
+        let mut inventory = CharacterInventory::new();
+        inventory.add_book(
+            "\\id GEN\n\\c 1\n\\p\n\\v 1 Abba\\f + \\ft ab\\f* \\w a|lemma=\"x\"\\w*\n\\v 2 a\n",
+            &catalog,
+        );
+        inventory.add_book("\\id EXO\n\\c 3\n\\p\n\\v 4 é\n", &catalog);
+
+        let listed: Vec<_> = inventory
+            .entries()
+            .map(|entry| (entry.code_point(), entry.count))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("U+0041".to_string(), 1),
+                ("U+0061".to_string(), 4),
+                ("U+0062".to_string(), 3),
+                ("U+00E9".to_string(), 1),
+            ]
+        );
+        let a = inventory.get('a').unwrap();
+        assert_eq!(a.name, "LATIN SMALL LETTER A");
+        assert_eq!(a.references, vec!["GEN 1:1", "GEN 1:2"]);
+        assert_eq!(inventory.get('é').unwrap().references, vec!["EXO 3:4"]);
+
+        let allowed = CharacterSet::parse("a-zA-Z");
+        let outside: Vec<_> = inventory
+            .outside(&allowed)
+            .map(|entry| entry.character)
+            .collect();
+        assert_eq!(outside, vec!['é']);
+    }
+
+    #[test]
+    fn test_character_set() {
+        let set = CharacterSet::parse("a-cX.-");
+        assert!(set.contains('b') && set.contains('X') && set.contains('-'));
+        assert!(set.contains(' ') && set.contains('\u{a0}'));
+        assert!(!set.contains('d') && !set.contains(','));
+
+        let config = LintConfig::from_toml("allowed_characters = \"a-z\"\n").unwrap();
+        assert_eq!(config.allowed_characters, Some(CharacterSet::parse("a-z")));
+        assert_eq!(LintConfig::default().allowed_characters, None);
+    }
+
+    #[test]
+    fn test_invisible_and_disallowed() {
+        let usfm = "\u{feff}\\id GEN\n\\c 1\n\\p\n\\v 1 go\u{200b}od क्\u{200d}ष @ x@\n";
+        assert_eq!(
+            messages(usfm, None),
+            vec![(
+                "invisible-character",
+                "Invisible character U+200B ZERO WIDTH SPACE in GEN 1:1".to_string()
+            )]
+        );
+        let allowed = CharacterSet::parse("a-z\u{915}-\u{94d}\u{200d}");
+        let diagnostics = check_characters(usfm, &MarkerCatalog::usfm(), Some(&allowed));
+        let disallowed = diagnostics.last().unwrap();
+        assert_eq!(
+            disallowed.message,
+            "Character U+0040 COMMERCIAL AT is not in the allowed set (2 occurrences)"
+        );
+        assert_eq!(&usfm[disallowed.start_byte..disallowed.end_byte], "@");
+        assert_eq!((disallowed.row, disallowed.column), (3, 26));
+    }
+
+    #[test]
+    fn test_normalization_checks() {
+        let decomposed = "e\u{301}";
+        assert!(messages("\\id GEN\n\\c 1\n\\p\n\\v 1 café\n", None).is_empty());
+        assert_eq!(
+            messages(
+                &format!("\\id GEN\n\\c 1\n\\p\n\\v 1 café\n\\v 2 caf{}!\n", decomposed),
+                None
+            ),
+            vec![
+                (
+                    "non-nfc-text",
+                    format!("\"caf{}!\" in GEN 1:2 is not in NFC", decomposed)
+                ),
+                (
+                    "mixed-normalization",
+                    format!(
+                        "Text mixes composed (\"café\" in GEN 1:1) and decomposed (\"caf{}!\" in GEN 1:2) characters",
+                        decomposed
+                    )
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_to_nfc() {
+        assert!(matches!(to_nfc("café"), Cow::Borrowed("café")));
+        assert_eq!(to_nfc("cafe\u{301}"), "café");
+
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 \\w cafe\u{301}|lemma=\"e\u{301}\"\\w*\n";
+        let tree = USFMParser::new().unwrap().parse_usfm(usfm).unwrap();
+        let options = UsjOptions {
+            nfc: true,
+            ..UsjOptions::default()
+        };
+        let mut written = Vec::new();
+        write_usj_with(&tree, usfm, &mut written, options).unwrap();
+        let usj: Value = serde_json::from_slice(&written).unwrap();
+        let word = &usj["content"][2]["content"][1];
+        assert_eq!(word["lemma"], "é");
+        assert_eq!(word["content"][0], "café");
+
+        assert_eq!(
+            normalize_usfm(usfm),
+            "\\id GEN\n\\c 1\n\\p\n\\v 1 \\w café|lemma=\"é\"\\w*\n"
+        );
+        // A combining mark straight after a marker does not join its name
+        assert_eq!(normalize_usfm("\\qa\u{301}"), "\\qa\u{301}");
+        assert!(matches!(normalize_usfm("\\p café"), Cow::Borrowed(_)));
+    }
+}
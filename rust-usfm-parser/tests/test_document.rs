@@ -0,0 +1,115 @@
+// test_document.rs
+
+use rust_usfm::document::Document;
+use rust_usfm::parser::USFMParser;
+use rust_usfm::usj_generator::usj_from_tree;
+use rust_usfm::validator::Validator;
+use serde_json::Value;
+
+const USFM: &str = "\\id MRK Mark\n\\h Mark\n\\toc1 Mark\n\\toc2 Mark\n\\mt Mark\n\
+\\c 1\n\\p\n\\v 1 The beginning of the gospel.\n\\v 2 As it is written.\n\
+\\c 2\n\\p\n\\v 1 He entered Capernaum.\n\\v 2 Many were gathered.\n\
+\\c 3\n\\p\n\\v 1 He entered the synagogue.\n";
+
+// A document parsed afresh, to compare an edited one with
+fn fresh(usfm: &str) -> (Value, Vec<String>) {
+    let document = Document::new(usfm).unwrap();
+    let usj = serde_json::from_str(&document.usj()).unwrap();
+    let diagnostics = document
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| format!("{} {}", diagnostic, diagnostic.start_byte))
+        .collect();
+    (usj, diagnostics)
+}
+
+fn assert_matches_fresh(document: &Document) {
+    let (usj, diagnostics) = fresh(document.usfm());
+    assert_eq!(serde_json::from_str::<Value>(&document.usj()).unwrap(), usj);
+    let edited: Vec<_> = document
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| format!("{} {}", diagnostic, diagnostic.start_byte))
+        .collect();
+    assert_eq!(edited, diagnostics);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segments() {
+        let document = Document::new(USFM).unwrap();
+        let chapters: Vec<_> = document
+            .segments()
+            .iter()
+            .map(|segment| segment.chapter.as_deref())
+            .collect();
+        assert_eq!(chapters, [None, Some("1"), Some("2"), Some("3")]);
+        assert_eq!(
+            document.segments()[1].range.start,
+            USFM.find("\\c 1").unwrap()
+        );
+
+        let tree = USFMParser::new().unwrap().parse_usfm(USFM).unwrap();
+        let usj: Value = serde_json::from_str(&usj_from_tree(&tree, USFM).unwrap()).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&document.usj()).unwrap(), usj);
+        assert!(document.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_edit_redoes_one_chapter() {
+        let mut document = Document::new(USFM).unwrap();
+        let start = USFM.find("Capernaum").unwrap();
+        let changes = document
+            .replace(start..start + "Capernaum".len(), "Capernaum Capernaum")
+            .unwrap();
+        assert_eq!(changes.segments, [2]);
+        assert!(changes.ranges.iter().any(|range| range.start_byte == start));
+        assert_eq!(document.diagnostics()[0].rule, "repeated-word");
+        assert_matches_fresh(&document);
+
+        // Findings of untouched chapters move with the text before them
+        let gospel = USFM.find("gospel").unwrap();
+        let changes = document.replace(gospel..gospel, "good\n").unwrap();
+        assert_eq!(changes.segments, [1]);
+        assert_matches_fresh(&document);
+    }
+
+    #[test]
+    fn test_quotation_across_chapters() {
+        let usfm =
+            "\\id MRK\n\\c 1\n\\p\n\\v 1 He said, “Come\n\\c 2\n\\p\n\\v 1 and see.” (Then\n";
+        let document = Document::new(usfm).unwrap();
+        let linted = Validator::new().unwrap().lint(usfm).unwrap();
+        assert_eq!(document.diagnostics(), linted);
+        let unmatched: Vec<_> = linted
+            .iter()
+            .filter(|diagnostic| diagnostic.rule == "unmatched-punctuation")
+            .map(|diagnostic| diagnostic.start_byte)
+            .collect();
+        assert_eq!(unmatched, [usfm.find('(').unwrap()]);
+    }
+
+    #[test]
+    fn test_structural_edits() {
+        let mut document = Document::new(USFM).unwrap();
+
+        // A new chapter splits one segment in two
+        let start = USFM.find("\\v 2 Many").unwrap();
+        document.replace(start..start, "\\c 4\n\\p\n").unwrap();
+        assert_eq!(document.segments().len(), 5);
+        assert!(document
+            .diagnostics()
+            .iter()
+            .any(|diagnostic| diagnostic.rule == "chapter-sequence"));
+        assert_matches_fresh(&document);
+
+        // The book code is in every chapter's sids
+        let changes = document.replace(4..7, "LUK").unwrap();
+        assert_eq!(changes.segments, [0, 1, 2, 3, 4]);
+        assert!(document.usj().contains(r#""sid":"LUK 3:1""#));
+        assert_matches_fresh(&document);
+    }
+}
@@ -0,0 +1,164 @@
+// test_lint.rs
+
+use rust_usfm::diagnostics::Severity;
+use rust_usfm::lint::{find_rule, LintConfig, RuleLevel, RULES};
+use rust_usfm::validator::Validator;
+use std::fs;
+
+const HEADERS: &str = "\\id GEN\n\\h Genesis\n\\toc1 The Book of Genesis\n\\toc2 Genesis\n\\mt1 Genesis\n";
+
+fn rules(validator: &mut Validator, usfm: &str) -> Vec<(&'static str, Severity)> {
+    validator
+        .lint(usfm)
+        .unwrap()
+        .iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.severity))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_ids_are_unique() {
+        for (index, rule) in RULES.iter().enumerate() {
+            assert!(RULES[..index].iter().all(|other| other.id != rule.id));
+            assert_eq!(find_rule(rule.id), Some(rule));
+        }
+    }
+
+    #[test]
+    fn test_config_from_toml() {
+        let config = LintConfig::from_toml(
+            "ignore_markers = [\"\\\\s5\"]\n[rules]\nmissing-header-marker = \"error\"\nverse-sequence = \"off\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.ignore_markers, vec!["s5"]);
+        assert_eq!(
+            config.rules.get("missing-header-marker"),
+            Some(&RuleLevel::Severity(Severity::Error))
+        );
+        assert_eq!(config.severity_of("verse-sequence"), None);
+        assert_eq!(config.severity_of("chapter-sequence"), Some(Severity::Warning));
+
+        let unknown = LintConfig::from_toml("[rules]\nno-such-rule = \"off\"\n");
+        assert_eq!(unknown, Err("Unknown lint rule 'no-such-rule'".to_string()));
+        assert!(LintConfig::from_toml("[rules]\nsyntax-error = \"fatal\"\n").is_err());
+    }
+
+    #[test]
+    fn test_missing_toc_as_error() -> Result<(), Box<dyn std::error::Error>> {
+        let usfm = "\\id GEN\n\\h Genesis\n\\toc2 Genesis\n\\mt1 Genesis\n\\c 1\n\\p\n\\v 1 Text\n";
+        let mut validator = Validator::new()?;
+        assert_eq!(rules(&mut validator, usfm), vec![("missing-header-marker", Severity::Warning)]);
+        assert_eq!(validator.is_valid_usfm(usfm), Ok(true));
+
+        let path = std::env::temp_dir().join("rust-usfm-test-lint.toml");
+        fs::write(&path, "[rules]\nmissing-header-marker = \"error\"\n")?;
+        validator.load_lint_config(&path)?;
+        fs::remove_file(&path)?;
+        assert_eq!(rules(&mut validator, usfm), vec![("missing-header-marker", Severity::Error)]);
+        let errors = validator.is_valid_usfm(usfm).unwrap_err();
+        assert!(errors.contains("Missing \\toc1 (long book name) [missing-header-marker]"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignored_markers() {
+        // translationStudio marks chunks with an empty \s5 inside the paragraph
+        let usfm = format!("{}\\c 1\n\\p\n\\v 1 Text\n\\s5\n\\v 2 More text\n", HEADERS);
+        let mut validator = Validator::new().unwrap();
+        assert!(rules(&mut validator, &usfm).contains(&("marker-placement", Severity::Error)));
+
+        validator.lint_config = LintConfig::from_toml("ignore_markers = [\"s5\"]").unwrap();
+        assert_eq!(validator.lint(&usfm).unwrap(), vec![]);
+        assert_eq!(validator.is_valid_usfm(&usfm), Ok(true));
+    }
+
+    #[test]
+    fn test_turning_rules_off() {
+        let usfm = format!("{}\\c 1\n\\p\n\\v 1 The \\nd Lord\n\\v 3 Text\n\\c 3\n", HEADERS);
+        let mut validator = Validator::new().unwrap();
+        assert_eq!(
+            rules(&mut validator, &usfm),
+            vec![
+                ("unclosed-marker", Severity::Error),
+                ("syntax-error", Severity::Error),
+                ("verse-sequence", Severity::Warning),
+                ("chapter-sequence", Severity::Warning),
+            ]
+        );
+        validator.lint_config = LintConfig::from_toml(
+            "[rules]\nunclosed-marker = \"info\"\nsyntax-error = \"off\"\nverse-sequence = \"off\"\nchapter-sequence = \"off\"\n",
+        )
+        .unwrap();
+        assert_eq!(rules(&mut validator, &usfm), vec![("unclosed-marker", Severity::Info)]);
+        assert_eq!(validator.is_valid_usfm(&usfm), Ok(true));
+    }
+
+    #[test]
+    fn test_rules_off_by_default() {
+        let usfm = format!("{}\\c 1\n\\p\n\\v 1 Wait , then go.\n", HEADERS);
+        let mut validator = Validator::new().unwrap();
+        assert_eq!(rules(&mut validator, &usfm), vec![]);
+        assert!(!find_rule("punctuation-spacing").unwrap().enabled);
+        assert_eq!(LintConfig::default().severity_of("mixed-script"), None);
+
+        validator.lint_config =
+            LintConfig::from_toml("[rules]\npunctuation-spacing = \"on\"\n").unwrap();
+        assert_eq!(
+            rules(&mut validator, &usfm),
+            vec![("punctuation-spacing", Severity::Warning)]
+        );
+    }
+
+    #[test]
+    fn test_sequence_messages() {
+        let usfm = format!(
+            "{}\\c 1\n\\p\n\\v 1 a\n\\v 2a b\n\\v 2b c\n\\v 3-4 d\n\\v 4 e\n\\v 8 f\n\\v 6 g\n\\v 9b-7 h\n",
+            HEADERS
+        );
+        let mut validator = Validator::new().unwrap();
+        let messages: Vec<String> = validator
+            .lint(&usfm)
+            .unwrap()
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Verse 4 is repeated",
+                "Verse 8 follows verse 4; verses 5-7 are missing",
+                "Verse 6 is out of order; it follows verse 8",
+                "\"9b-7\" ends before it starts",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attribute_rules() {
+        let usfm = format!(
+            "{}\\c 1\n\\p\n\\v 1 \\w grace|lemma=\"charis\" x-note=\"a\"\\w* \\w law|law\\w* \\em x|weight=\"bold\"\\em* \\rb 日本\\rb* \\qt-s |sid=\"q\" colour=\"red\"\\*y\\qt-e |eid=\"q\"\\*\n",
+            HEADERS
+        );
+        let mut validator = Validator::new().unwrap();
+        // The grammar rejects these attributes as well
+        validator.lint_config = LintConfig::from_toml("[rules]\nsyntax-error = \"off\"").unwrap();
+        let messages: Vec<String> = validator
+            .lint(&usfm)
+            .unwrap()
+            .into_iter()
+            .map(|diagnostic| format!("{}: {}", diagnostic.rule, diagnostic.message))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "unknown-attribute: \\em does not define the attribute weight",
+                "missing-attribute: \\rb is missing the required attribute gloss",
+                "unknown-attribute: \\qt-s does not define the attribute colour",
+            ]
+        );
+    }
+}
@@ -0,0 +1,55 @@
+// test_logging.rs
+
+use rust_usfm::parser::USFMParser;
+use rust_usfm::usj_generator::usj_from_tree;
+use std::io;
+use std::sync::{Arc, Mutex};
+use tracing::Level;
+
+// A log destination tests can read back
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Buffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// What converting `usfm` logs at `level`
+fn conversion_log(usfm: &str, level: Level) -> String {
+    let buffer = Buffer::default();
+    let writer = buffer.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    let tree = USFMParser::new().unwrap().parse_usfm(usfm).unwrap();
+    tracing::subscriber::with_default(subscriber, || usj_from_tree(&tree, usfm).unwrap());
+    let log = buffer.0.lock().unwrap().clone();
+    String::from_utf8(log).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USFM: &str = "\\id GEN\n\\c 1\n\\p\n\\v 1 In the beginning\n\\c 2\n\\p\n\\v 1 Thus\n";
+
+    #[test]
+    fn test_trace_dumps_nodes_in_spans() {
+        let log = conversion_log(USFM, Level::TRACE);
+        assert!(log.contains(r#"book{code="GEN"}:chapter{number="2"}"#));
+        assert!(log.contains(r#"node kind="verseText""#));
+    }
+
+    #[test]
+    fn test_quiet_by_default() {
+        assert_eq!(conversion_log(USFM, Level::INFO), "");
+    }
+}
@@ -0,0 +1,98 @@
+// test_milestones.rs
+
+use rust_usfm::diagnostics::Severity;
+use rust_usfm::milestones::{pair_milestones, MilestoneAnalysis, MilestoneIssueKind};
+use rust_usfm::stylesheet::USFM_CATALOG;
+use rust_usfm::validator::Validator;
+use std::fs;
+
+fn analyze(usfm: &str) -> MilestoneAnalysis {
+    pair_milestones(usfm, &USFM_CATALOG)
+}
+
+fn kinds(analysis: &MilestoneAnalysis) -> Vec<MilestoneIssueKind> {
+    analysis.issues.iter().map(|issue| issue.kind).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairs_quotation_milestones() -> Result<(), Box<dyn std::error::Error>> {
+        let usfm = fs::read_to_string("../tests/usfmjsTests/qt/origin.usfm")?;
+        let analysis = analyze(&usfm);
+        assert!(analysis.issues.is_empty());
+        let speakers: Vec<&str> = analysis
+            .pairs
+            .iter()
+            .map(|pair| pair.start.attribute("who").unwrap())
+            .collect();
+        assert_eq!(speakers, vec!["Pilate", "Jesus", "Pilate"]);
+        assert!(analysis.pairs[0].end.end_byte > analysis.pairs[0].start.end_byte);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pairs_by_sid_and_eid() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 \\qt-s |sid=\"a\" who=\"A\"\\*x \\qt-s |sid=\"b\" who=\"B\"\\*y\\qt-e |eid=\"b\"\\*z\\qt-e |eid=\"a\"\\*\n";
+        let analysis = analyze(usfm);
+        assert!(analysis.issues.is_empty());
+        let pair = analysis.pair_by_sid("a").unwrap();
+        assert_eq!(pair.end.attribute("eid"), Some("a"));
+        assert_eq!(pair.start.attribute("who"), Some("A"));
+    }
+
+    #[test]
+    fn test_default_attribute() {
+        let analysis = analyze("\\id GEN\n\\c 1\n\\p\n\\v 1 \\qt-s |Speaker\\*text\\qt-e\\*\n");
+        assert_eq!(analysis.pairs[0].start.attribute("who"), Some("Speaker"));
+    }
+
+    #[test]
+    fn test_orphans_and_mismatched_ids() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 \\qt-s |sid=\"qt1\"\\*a\n\\v 2 b\\qt-e |eid=\"qt2\"\\* \\k-e\\* \\zaln-s |x-lemma=\"x\"\\*\\w c\\w*\n";
+        let analysis = analyze(usfm);
+        assert_eq!(
+            kinds(&analysis),
+            vec![
+                MilestoneIssueKind::IdMismatch,
+                MilestoneIssueKind::OrphanEnd,
+                MilestoneIssueKind::OrphanStart,
+            ]
+        );
+        assert_eq!(
+            analysis.issues[0].message,
+            "\\qt-e (eid=\"qt2\") does not match \\qt-s (sid=\"qt1\"); expected eid=\"qt1\""
+        );
+        assert_eq!(analysis.issues[1].message, "\\k-e has no matching \\k-s");
+        assert_eq!(analysis.issues[2].message, "\\zaln-s is never ended by \\zaln-e");
+    }
+
+    #[test]
+    fn test_improper_nesting() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 \\qt1-s |sid=\"a\"\\*x \\qt2-s |sid=\"b\"\\*y\\qt1-e |eid=\"a\"\\*z\\qt2-e |eid=\"b\"\\*\n";
+        let analysis = analyze(usfm);
+        assert_eq!(kinds(&analysis), vec![MilestoneIssueKind::ImproperNesting]);
+        assert_eq!(analysis.pairs.len(), 2);
+    }
+
+    #[test]
+    fn test_nested_alignment_milestones() -> Result<(), Box<dyn std::error::Error>> {
+        let usfm = fs::read_to_string("../tests/usfmjsTests/heb1-1_multi_alignment/origin.usfm")?;
+        let analysis = analyze(&usfm);
+        assert!(analysis.issues.is_empty());
+        assert!(analysis.pairs.iter().all(|pair| pair.start.stem() == "zaln"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validator_warns_without_failing() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 \\qt-s |Speaker\\*text\n";
+        let mut validator = Validator::new().unwrap();
+        let diagnostics = validator.check_milestones(usfm);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(validator.is_valid_usfm(usfm), Ok(true));
+    }
+}
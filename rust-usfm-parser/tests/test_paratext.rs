@@ -0,0 +1,143 @@
+// test_paratext.rs
+
+mod common;
+
+use common::TempDir;
+use rust_usfm::books::Versification;
+use rust_usfm::lint::LintConfig;
+use rust_usfm::paratext::{paratext_book_number, ParatextProject, ParatextSettings};
+
+const SETTINGS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<ScriptureText>
+  <Name>XYZ</Name>
+  <FullName>Example Translation</FullName>
+  <Language>Example</Language>
+  <LanguageIsoCode>xyz:::</LanguageIsoCode>
+  <Encoding>1252</Encoding>
+  <Versification>4</Versification>
+  <FileNamePrePart></FileNamePrePart>
+  <FileNameBookNameForm>41MAT</FileNameBookNameForm>
+  <FileNamePostPart>XYZ.SFM</FileNamePostPart>
+</ScriptureText>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings() {
+        let settings = ParatextSettings::parse(SETTINGS).unwrap();
+        assert_eq!(settings.name, "XYZ");
+        assert_eq!(settings.full_name.as_deref(), Some("Example Translation"));
+        assert_eq!(settings.language.as_deref(), Some("Example"));
+        assert_eq!(settings.language_code.as_deref(), Some("xyz"));
+        assert_eq!(settings.encoding, 1252);
+        assert_eq!(settings.versification, Versification::English);
+        assert_eq!(settings.file_name("MAT").unwrap(), "41MATXYZ.SFM");
+        assert_eq!(settings.file_name("GEN").unwrap(), "01GENXYZ.SFM");
+
+        let form = ParatextSettings {
+            file_prefix: "pre-".to_string(),
+            book_name_form: "MAT".to_string(),
+            file_suffix: ".usfm".to_string(),
+            ..ParatextSettings::default()
+        };
+        assert_eq!(form.file_name("REV").unwrap(), "pre-REV.usfm");
+        assert!(ParatextSettings::parse(
+            "<ScriptureText><Versification>9</Versification></ScriptureText>"
+        )
+        .is_err());
+        assert_eq!(settings.decode(b"caf\xe9").unwrap(), "café");
+        assert!(ParatextSettings::default().decode(b"caf\xe9").is_err());
+    }
+
+    #[test]
+    fn test_book_numbers() {
+        let numbers: Vec<_> = [
+            "GEN", "MAL", "MAT", "REV", "TOB", "XXA", "XXG", "FRT", "GLO", "TDX", "LAO",
+        ]
+        .iter()
+        .map(|code| paratext_book_number(code).unwrap())
+        .collect();
+        assert_eq!(
+            numbers,
+            ["01", "39", "41", "67", "68", "94", "100", "A0", "A9", "B0", "C3"]
+        );
+        assert_eq!(paratext_book_number("XYZ"), None);
+        assert_eq!(paratext_book_number(""), None);
+    }
+
+    #[test]
+    fn test_load_project() {
+        let dir = TempDir::with_files(
+            "paratext-load",
+            &[
+                ("Settings.xml", SETTINGS.as_bytes()),
+                (
+                    "custom.sty",
+                    b"\\Marker zx\n\\Endmarker zx*\n\\StyleType Character\n\n\\Marker zlabel\n\\StyleType Paragraph\n",
+                ),
+                (
+                    "41MATXYZ.SFM",
+                    b"\\id MAT\n\\h Matthew\n\\zlabel Draft\n\\c 1\n\\p\n\\v 1 Caf\xe9 \\zx note\\zx*\n",
+                ),
+                ("29jolxyz.sfm", b"\\id JOL\n\\c 4\n\\p\n\\v 1 Text\n"),
+                ("MRK.SFM", b"\\id MRK\n\\c 1\n\\p\n\\v 1 Text\n"),
+            ],
+        );
+        let paratext = ParatextProject::load(&dir).unwrap();
+
+        let codes: Vec<_> = paratext
+            .project
+            .books
+            .iter()
+            .map(|book| book.code.as_str())
+            .collect();
+        assert_eq!(codes, ["JOL", "MAT"]);
+        assert!(paratext.project.book("MAT").unwrap().usfm.contains("Café"));
+        assert!(paratext.catalog.is_character("zx"));
+        // Read as a paragraph of its own, not as more of the `\h` text
+        let mat = paratext.project.book("MAT").unwrap();
+        assert_eq!(mat.info.header.as_deref(), Some("Matthew"));
+
+        let converted = paratext.to_usj().unwrap();
+        let usj = converted[1].1.as_ref().unwrap();
+        let usj: serde_json::Value = serde_json::from_str(usj).unwrap();
+        assert_eq!(usj["content"][2]["marker"], "zlabel");
+        assert_eq!(
+            usj["content"][4]["content"][2],
+            serde_json::json!({"type": "char", "marker": "zx", "content": ["note"]})
+        );
+
+        let linted = paratext.lint().unwrap();
+        let rules = |index: usize| -> Vec<&str> {
+            linted[index]
+                .1
+                .iter()
+                .map(|diagnostic| diagnostic.rule)
+                .collect()
+        };
+        assert!(rules(0).contains(&"chapter-out-of-range"));
+        assert!(!rules(1).contains(&"unknown-marker"));
+        assert_eq!(
+            linted[0]
+                .1
+                .iter()
+                .find(|d| d.rule == "chapter-out-of-range")
+                .unwrap()
+                .message,
+            "Chapter 4 is beyond the 3 chapters of JOL in the english versification"
+        );
+
+        assert!(ParatextProject::load(dir.join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_versification_config() {
+        let config = LintConfig::from_toml("versification = \"russian-orthodox\"\n").unwrap();
+        assert_eq!(config.versification, Some(Versification::RussianOrthodox));
+        assert!(LintConfig::from_toml("versification = \"klingon\"\n").is_err());
+        assert_eq!(LintConfig::default().versification, None);
+    }
+}
@@ -0,0 +1,117 @@
+// test_placement.rs
+
+use rust_usfm::placement::check_placement;
+use rust_usfm::stylesheet::{MarkerCatalog, USFM_CATALOG, USFM_STY};
+use std::fs;
+
+fn messages(usfm: &str) -> Vec<String> {
+    check_placement(usfm, &USFM_CATALOG)
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intro_markers_only_before_first_chapter() {
+        let usfm = "\\id GEN\n\\is Intro\n\\ip Text\n\\c 1\n\\is Late heading\n\\p\n\\v 1 In the beginning\n\\ie\n";
+        assert_eq!(
+            messages(usfm),
+            vec![
+                "\\is must occur in the book introduction, before the first \\c",
+                "\\ie must occur in the book introduction, before the first \\c",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_section_introduction_after_heading() {
+        let usfm = "\\id MRK\n\\c 1\n\\ms Ministry in Galilee\n\\ip Section introduction\n\\p\n\\v 1 Text\n";
+        assert!(messages(usfm).is_empty());
+
+        let usfm = "\\id MRK\n\\c 1\n\\p\n\\v 1 Text\n\\ip Not a section introduction\n";
+        assert_eq!(messages(usfm).len(), 1);
+    }
+
+    #[test]
+    fn test_note_content_only_inside_notes() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 Text\\f + \\fr 1.1: \\ft note\\f* \\fq outside\n";
+        assert_eq!(
+            messages(usfm),
+            vec!["\\fq must occur inside a note (\\f, \\fe, \\ef)"]
+        );
+    }
+
+    #[test]
+    fn test_table_cells_only_in_rows() {
+        let usfm = "\\id GEN\n\\c 1\n\\tr \\th1 A \\th2 B\n\\tr \\tc1 a \\tcr2 b\n\\p \\tc1 c\n";
+        assert_eq!(
+            messages(usfm),
+            vec!["\\tc1 must occur in a table row (\\tr)"]
+        );
+    }
+
+    #[test]
+    fn test_chapter_label_before_first_verse() {
+        let usfm = "\\id GEN\n\\cl Chapter\n\\c 1\n\\cl One\n\\p\n\\v 1 Text\n\\c 2\n\\p\n\\v 1 Text\n\\cl Two\n";
+        assert_eq!(
+            messages(usfm),
+            vec!["\\cl must directly follow \\c 2, before the chapter's first verse"]
+        );
+    }
+
+    #[test]
+    fn test_titles_placement() {
+        let usfm = "\\id GEN\n\\mt1 Genesis\n\\mte1 Too early\n\\c 1\n\\imt1 Too late\n\\p\n\\v 1 Text\n\\mte1 The End\n";
+        assert_eq!(
+            messages(usfm),
+            vec![
+                "\\mte1 must occur after the first \\c",
+                "\\imt1 must occur in the book introduction, before the first \\c",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verse_outside_paragraph() {
+        let usfm = "\\id GEN\n\\c 1\n\\s5\n\\v 1 Text\n";
+        let found = messages(usfm);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].starts_with("\\v is not allowed under \\s5; expected under \\li"));
+    }
+
+    #[test]
+    fn test_gn_headers_sample_is_well_placed() -> Result<(), Box<dyn std::error::Error>> {
+        let usfm = fs::read_to_string("../tests/usfmjsTests/gn_headers/origin.usfm")?;
+        assert_eq!(messages(&usfm), Vec::<String>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_overrides_apply_on_top_of_usfm_sty() {
+        let usfm = "\\id GEN\n\\c 1\n\\lit Glory:\n\\v 1 \\nd Lord\\nd*\n";
+        assert_eq!(messages(usfm), Vec::<String>::new());
+
+        let standard = MarkerCatalog::from_stylesheet(USFM_STY).unwrap();
+        assert_eq!(check_placement(usfm, &standard).len(), 2);
+    }
+
+    #[test]
+    fn test_custom_stylesheet_rules_apply() {
+        let mut catalog = MarkerCatalog::usfm();
+        catalog
+            .extend_from_stylesheet("\\Marker nd\n\\OccursUnder q1 q2\n")
+            .unwrap();
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 The \\nd Lord\\nd*\n";
+        let diagnostics = check_placement(usfm, &catalog);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "\\nd is not allowed under \\p; expected under \\q"
+        );
+        assert_eq!((diagnostics[0].row, diagnostics[0].column), (3, 9));
+    }
+}
@@ -0,0 +1,92 @@
+// test_project.rs
+
+mod common;
+
+use common::TempDir;
+use rust_usfm::project::{Project, Statistics};
+use rust_usfm::validator::Validator;
+use serde_json::Value;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_dir() {
+        let dir = TempDir::with_files(
+            "project-load",
+            &[
+                ("a.usfm", "\\id REV\n\\c 1\n\\p\n\\v 1 The revelation\n"),
+                (
+                    "b.SFM",
+                    "\\id gen Genesis\n\\c 1\n\\p\n\\v 1 In the beginning\n",
+                ),
+                ("c.usfm", "\\id REV copy\n\\c 1\n\\p\n\\v 1 Copy\n"),
+                ("d.usfm", "\\c 1\n\\p\n\\v 1 No id\n"),
+                ("notes.txt", "\\id MAT\n"),
+            ],
+        );
+        let project = Project::load_dir(&dir).unwrap();
+        let codes: Vec<_> = project
+            .books
+            .iter()
+            .map(|book| book.code.as_str())
+            .collect();
+        assert_eq!(codes, ["GEN", "REV"]);
+        assert_eq!(project.book("REV").unwrap().path, dir.join("a.usfm"));
+        assert_eq!(
+            project.book("GEN").unwrap().info.description.as_deref(),
+            Some("Genesis")
+        );
+        assert_eq!(project.duplicates.len(), 1);
+        assert_eq!(project.duplicates[0].code, "REV");
+        assert_eq!(
+            project.duplicates[0].paths,
+            [dir.join("a.usfm"), dir.join("c.usfm")]
+        );
+        assert_eq!(project.unidentified, [dir.join("d.usfm")]);
+
+        let pattern = format!("{}/*.usfm", dir.display());
+        let globbed = Project::load_glob(&pattern).unwrap();
+        assert_eq!(globbed.books.len(), 1);
+        assert!(Project::load_dir(dir.join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_project_outputs() {
+        let dir = TempDir::with_files(
+            "project-outputs",
+            &[
+                ("41MAT.usfm", "\\id MAT\n\\c 1\n\\p\n\\v 1 The book of the genealogy\n\\v 2 Abraham\n\\c 2\n\\p\n\\v 1 Now\n"),
+                ("42MRK.usfm", "\\id MRK\n\\c 1\n\\p\n\\v 1 The beginning the the gospel\n"),
+            ],
+        );
+        let project = Project::load_dir(&dir).unwrap();
+
+        assert_eq!(
+            project.statistics(),
+            Statistics {
+                books: 2,
+                chapters: 3,
+                verses: 4,
+                words: 12
+            }
+        );
+        let per_book = project.book_statistics();
+        assert_eq!(
+            (per_book[1].0.code.as_str(), per_book[1].1.words),
+            ("MRK", 5)
+        );
+
+        let converted = project.to_usj().unwrap();
+        let usj: Value = serde_json::from_str(converted[1].1.as_ref().unwrap()).unwrap();
+        assert_eq!(usj["content"][0]["code"], "MRK");
+
+        let mut validator = Validator::new().unwrap();
+        let linted = project.lint(&mut validator).unwrap();
+        assert!(linted[1]
+            .1
+            .iter()
+            .any(|diagnostic| diagnostic.rule == "repeated-word"));
+    }
+}
@@ -0,0 +1,196 @@
+// test_reference.rs
+
+use rust_usfm::parser::USFMParser;
+use rust_usfm::reference::{
+    book_code, format_references, parse_references, parse_references_in, Location, ReferenceRange,
+    ReferenceStyle, VerseNumber,
+};
+use rust_usfm::usj_generator::usj_generator;
+use rust_usfm::validator::Validator;
+use serde_json::Value;
+
+fn verse(book: &str, chapter: u32, verse: u32, segment: Option<&str>) -> Location {
+    Location {
+        book: book.to_string(),
+        chapter,
+        verse: Some(verse),
+        segment: segment.map(str::to_string),
+    }
+}
+
+fn range(start: Location, end: Location) -> ReferenceRange {
+    ReferenceRange { start, end }
+}
+
+// Number and sid of every verse, in document order
+fn verse_sids(node: &Value, verses: &mut Vec<(String, String)>) {
+    if node["type"] == "verse" {
+        verses.push((
+            node["number"].as_str().unwrap_or_default().to_string(),
+            node["sid"].as_str().unwrap_or_default().to_string(),
+        ));
+    }
+    for child in node["content"].as_array().into_iter().flatten() {
+        verse_sids(child, verses);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_book_names() {
+        assert_eq!(book_code("GEN"), Some("GEN"));
+        assert_eq!(book_code("Mat"), Some("MAT"));
+        assert_eq!(book_code("Matt."), Some("MAT"));
+        assert_eq!(book_code("1 Cor"), Some("1CO"));
+        assert_eq!(book_code("1Co"), Some("1CO"));
+        assert_eq!(book_code("Song of Songs"), Some("SNG"));
+        assert_eq!(book_code("Phil"), Some("PHP"));
+        assert_eq!(book_code("Jud"), Some("JUD"));
+        assert_eq!(book_code("Judg"), Some("JDG"));
+        assert_eq!(book_code("Xyz"), None);
+        assert_eq!(book_code("J"), None);
+    }
+
+    #[test]
+    fn test_parse_references() {
+        assert_eq!(
+            parse_references("GEN 1:1-3; 2:4").unwrap(),
+            vec![
+                range(verse("GEN", 1, 1, None), verse("GEN", 1, 3, None)),
+                range(verse("GEN", 2, 4, None), verse("GEN", 2, 4, None)),
+            ]
+        );
+        assert_eq!(
+            parse_references("Mat 5.3").unwrap(),
+            vec![range(verse("MAT", 5, 3, None), verse("MAT", 5, 3, None))]
+        );
+        assert_eq!(
+            parse_references("1Co 13:4a-7").unwrap(),
+            vec![range(
+                verse("1CO", 13, 4, Some("a")),
+                verse("1CO", 13, 7, None)
+            )]
+        );
+        let chapters = parse_references("Psalm 23-25").unwrap();
+        assert_eq!(
+            (chapters[0].start.chapter, chapters[0].end.chapter),
+            (23, 25)
+        );
+        assert_eq!(chapters[0].start.verse, None);
+
+        let jude = parse_references("Jude 3, 5; Rom 1:32-2:1").unwrap();
+        assert_eq!(jude[0].start, verse("JUD", 1, 3, None));
+        assert_eq!(jude[1].start, verse("JUD", 1, 5, None));
+        assert_eq!(jude[2].end, verse("ROM", 2, 1, None));
+
+        assert_eq!(
+            parse_references_in("1:4", Some("MRK")).unwrap()[0].start,
+            verse("MRK", 1, 4, None)
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_references("1:4"),
+            Err("\"1:4\" does not name a book".to_string())
+        );
+        assert_eq!(
+            parse_references("Hezekiah 1:1"),
+            Err("Unknown book \"Hezekiah\"".to_string())
+        );
+        assert_eq!(
+            parse_references("GEN 1:5-2"),
+            Err("\"GEN 1:5-2\" ends before it starts".to_string())
+        );
+        assert!(parse_references("GEN 0:1").is_err());
+        assert!(parse_references("GEN a").is_err());
+        assert!(parse_references("").is_err());
+    }
+
+    #[test]
+    fn test_format_references() {
+        let ranges = parse_references("Gen 1:1-3,5; 2:4; Exod 3; 1 Cor 13:4a-14:1").unwrap();
+        assert_eq!(
+            format_references(&ranges, &ReferenceStyle::default()),
+            "GEN 1:1-3, 5; 2:4; EXO 3; 1CO 13:4a-14:1"
+        );
+        assert_eq!(
+            format_references(&ranges, &ReferenceStyle::english()),
+            "Genesis 1:1\u{2013}3, 5; 2:4; Exodus 3; 1 Corinthians 13:4a\u{2013}14:1"
+        );
+        let dotted = ReferenceStyle {
+            chapter_verse: ".".to_string(),
+            ..ReferenceStyle::default()
+        };
+        assert_eq!(format_references(&ranges[..1], &dotted), "GEN 1.1-3");
+        assert_eq!(ranges[4].to_string(), "1CO 13:4a-14:1");
+        assert_eq!(ranges[1].start.to_string(), "GEN 1:5");
+    }
+
+    #[test]
+    fn test_reference_targets_lint() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 See \\ref Mark 1:4|MRK 1:4\\ref* and \\ref here|Mrk 99\\ref*\\x - \\xo 1:1 \\xt 1|GEN 2:1\\xt* \\xt Ruth|link-href=\"#article-Ruth\"\\xt* \\xt x|href=\"GEN x\"\\xt*\\x*\n";
+        let mut validator = Validator::new().unwrap();
+        let messages: Vec<_> = validator
+            .lint(usfm)
+            .unwrap()
+            .into_iter()
+            .filter(|diagnostic| diagnostic.rule == "invalid-reference")
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec!["\\xt href \"GEN x\" is not a valid reference: \"GEN x\" is not a reference"]
+        );
+    }
+
+    #[test]
+    fn test_verse_numbers() {
+        let span = VerseNumber::parse("2b-6a").unwrap();
+        assert_eq!((span.start, span.end), (2, 6));
+        assert_eq!(
+            (span.start_segment.as_deref(), span.end_segment.as_deref()),
+            (Some("b"), Some("a"))
+        );
+        assert!(span.is_span() && span.covers(2) && span.covers(6) && !span.covers(7));
+        assert_eq!(span.verses().collect::<Vec<_>>(), vec![2, 3, 4, 5, 6]);
+        assert_eq!(span.range("GEN", 1).to_string(), "GEN 1:2b-6a");
+
+        let part: VerseNumber = "4a".parse().unwrap();
+        assert_eq!(part.verses(), 4..=4);
+        assert!(!part.is_span());
+        assert_eq!(part.to_string(), "4a");
+        assert_eq!(VerseNumber::parse("03-04").unwrap().to_string(), "3-4");
+        assert_eq!(VerseNumber::parse("4a-4b").unwrap().to_string(), "4a-4b");
+        assert_eq!(
+            VerseNumber::parse("2-"),
+            Err("\"2-\" is not a verse number".to_string())
+        );
+        assert_eq!(
+            VerseNumber::parse("5-3"),
+            Err("\"5-3\" ends before it starts".to_string())
+        );
+    }
+
+    #[test]
+    fn test_verse_sids() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1-3 One\n\\v 4a Two\n\\v 05 Three\n";
+        USFMParser::new().unwrap().parse_usfm(usfm).unwrap();
+        let usj: Value = serde_json::from_str(&usj_generator(usfm).unwrap()).unwrap();
+        let mut verses = Vec::new();
+        verse_sids(&usj, &mut verses);
+        let verses: Vec<_> = verses
+            .iter()
+            .take(3)
+            .map(|(number, sid)| (number.as_str(), sid.as_str()))
+            .collect();
+        assert_eq!(
+            verses,
+            [("1-3", "GEN 1:1-3"), ("4a", "GEN 1:4a"), ("05", "GEN 1:5")]
+        );
+    }
+}
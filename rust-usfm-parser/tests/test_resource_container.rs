@@ -0,0 +1,150 @@
+// test_resource_container.rs
+
+mod common;
+
+use common::TempDir;
+use rust_usfm::project::Project;
+use rust_usfm::resource_container::{
+    export_resource_container, DublinCore, Manifest, RcLanguage, ResourceContainer,
+};
+use std::path::PathBuf;
+
+const MANIFEST: &str = r#"
+dublin_core:
+  conformsto: 'rc0.2'
+  contributor:
+    - 'Door43 World Missions Community'
+  creator: 'Door43 World Missions Community'
+  description: 'An open-licensed update of the ASV'
+  format: 'text/usfm'
+  identifier: 'ult'
+  issued: '2019-05-01'
+  language:
+    identifier: 'en'
+    title: 'English'
+    direction: 'ltr'
+  modified: '2019-05-01'
+  publisher: 'unfoldingWord'
+  relation:
+    - 'en/tw'
+  rights: 'CC BY-SA 4.0'
+  source:
+    -
+      identifier: 'asv'
+      language: 'en'
+      version: '1901'
+  subject: 'Aligned Bible'
+  title: 'unfoldingWord Literal Text'
+  type: 'bundle'
+  version: '12'
+
+checking:
+  checking_entity:
+    - 'unfoldingWord'
+  checking_level: '3'
+
+projects:
+  -
+    title: 'Titus'
+    versification: 'ufw'
+    identifier: 'tit'
+    sort: 57
+    path: './57-TIT.usfm'
+    categories: [ 'bible-nt' ]
+  -
+    title: 'Ruth'
+    versification: 'ufw'
+    identifier: 'rut'
+    sort: 8
+    path: './08-RUT.usfm'
+    categories: [ 'bible-ot' ]
+  -
+    title: 'Translation Notes'
+    identifier: 'notes'
+    sort: 100
+    path: './notes.tsv'
+    categories: []
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_manifest() {
+        let manifest = Manifest::from_yaml(MANIFEST).unwrap();
+        assert_eq!(manifest.dublin_core.identifier, "ult");
+        assert_eq!(manifest.dublin_core.kind, "bundle");
+        assert_eq!(manifest.dublin_core.language.title, "English");
+        assert_eq!(manifest.dublin_core.source[0].version, "1901");
+        assert_eq!(manifest.checking.unwrap().checking_level, "3");
+        assert_eq!(manifest.projects.len(), 3);
+        assert!(manifest.projects[0].is_usfm() && !manifest.projects[2].is_usfm());
+        assert!(Manifest::from_yaml("projects: 3").is_err());
+    }
+
+    #[test]
+    fn test_load_container() {
+        let dir = TempDir::with_files(
+            "rc-load",
+            &[
+                ("manifest.yaml", MANIFEST),
+                ("57-TIT.usfm", "\\id TIT\n\\c 1\n\\p\n\\v 1 Paul\n"),
+                ("08-RUT.usfm", "\\id RUT\n\\c 1\n\\p\n\\v 1 In the days\n"),
+            ],
+        );
+        let container = ResourceContainer::load(&dir).unwrap();
+        assert_eq!(
+            container.usfm_files(),
+            [dir.join("08-RUT.usfm"), dir.join("57-TIT.usfm")]
+        );
+        let project = container.project().unwrap();
+        let codes: Vec<_> = project
+            .books
+            .iter()
+            .map(|book| book.code.as_str())
+            .collect();
+        assert_eq!(codes, ["RUT", "TIT"]);
+    }
+
+    #[test]
+    fn test_export_container() {
+        let project = Project::from_sources(vec![
+            (
+                PathBuf::from("mat.usfm"),
+                "\\id MAT\n\\toc2 Matthew\n\\c 1\n\\p\n\\v 1 A\n".to_string(),
+            ),
+            (
+                PathBuf::from("gen.usfm"),
+                "\\id GEN\n\\c 1\n\\p\n\\v 1 B\n".to_string(),
+            ),
+        ]);
+        let dublin_core = DublinCore {
+            identifier: "xyz".to_string(),
+            title: "Example Bible".to_string(),
+            language: RcLanguage {
+                identifier: "en".to_string(),
+                title: "English".to_string(),
+                ..RcLanguage::default()
+            },
+            ..DublinCore::default()
+        };
+        let dir = TempDir::new("rc-export");
+        let manifest = export_resource_container(&project, &dir, dublin_core).unwrap();
+        assert_eq!(manifest.projects[0].path, "./01-GEN.usfm");
+        assert_eq!(manifest.projects[0].title, "Genesis");
+        assert_eq!(manifest.projects[1].identifier, "mat");
+        assert_eq!(manifest.projects[1].title, "Matthew");
+        assert_eq!(manifest.projects[1].categories, ["bible-nt"]);
+
+        let container = ResourceContainer::load(&dir).unwrap();
+        let read = container.project().unwrap();
+        assert_eq!(container.manifest, manifest);
+        assert_eq!(container.manifest.dublin_core.conformsto, "rc0.2");
+        assert_eq!(read.books.len(), 2);
+        assert_eq!(
+            read.book("MAT").unwrap().usfm,
+            project.book("MAT").unwrap().usfm
+        );
+    }
+}
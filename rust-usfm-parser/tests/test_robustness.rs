@@ -0,0 +1,121 @@
+// test_robustness.rs
+
+use once_cell::sync::Lazy;
+use proptest::prelude::*;
+use rust_usfm::document::Document;
+use rust_usfm::usj_generator::usj_from_tree;
+use rust_usfm::validator::Validator;
+use std::fs;
+
+// Pieces of USFM that most often leave a tree half-formed
+const FRAGMENTS: [&str; 16] = [
+    "\\",
+    "\\c ",
+    "\\v ",
+    "\\p\n",
+    "\\f + ",
+    "\\f*",
+    "\\w ",
+    "\\w*",
+    "|",
+    "=\"",
+    "\"",
+    "\\+nd ",
+    "\\qt-s |",
+    "\\*",
+    "\n",
+    "é\u{301}",
+];
+
+static CORPUS: Lazy<Vec<String>> = Lazy::new(|| {
+    let mut files: Vec<_> = glob::glob("../tests/*/*/origin.usfm")
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    files.sort();
+    files
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .collect()
+});
+
+// Edits as (position, length removed, fragment inserted), positions as a
+// fraction of the text so they apply to any sample
+fn edits() -> impl Strategy<Value = Vec<(f64, usize, usize)>> {
+    prop::collection::vec((0.0..1.0, 0usize..20, 0..FRAGMENTS.len()), 1..8)
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn mutate(usfm: &str, edits: &[(f64, usize, usize)]) -> String {
+    let mut usfm = usfm.to_string();
+    for &(position, length, fragment) in edits {
+        let start = floor_char_boundary(&usfm, (usfm.len() as f64 * position) as usize);
+        let end = floor_char_boundary(&usfm, start + length);
+        usfm.replace_range(start..end, FRAGMENTS[fragment]);
+    }
+    usfm
+}
+
+// Everything a server does with a document it was sent. It stops at USJ:
+// the crate checks USX against usx.rng but has no writer to convert to it.
+fn process(validator: &mut Validator, usfm: &str) -> Result<(), String> {
+    let tree = validator
+        .parser
+        .parser
+        .parse(usfm, None)
+        .ok_or("Failed to parse")?;
+    let usj = usj_from_tree(&tree, usfm).map_err(|e| e.to_string())?;
+    serde_json::from_str::<serde_json::Value>(&usj).map_err(|e| e.to_string())?;
+    validator.validate_usj_semantics(&usj)?;
+    validator.lint(usfm)?;
+    let _ = validator.parser.parse_usfm(usfm);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn test_mutated_corpus_converts(sample in 0..CORPUS.len(), edits in edits()) {
+            let usfm = mutate(&CORPUS[sample], &edits);
+            let mut validator = Validator::new().unwrap();
+            prop_assert!(process(&mut validator, &usfm).is_ok());
+        }
+
+        #[test]
+        fn test_random_text_converts(usfm in "(\\\\[a-z]{1,3}[0-9*]? ?|[a-z |=\"*+\\n])*") {
+            let mut validator = Validator::new().unwrap();
+            prop_assert!(process(&mut validator, &usfm).is_ok());
+        }
+
+        #[test]
+        fn test_document_edits(sample in 0..CORPUS.len(), edits in edits()) {
+            let usfm = &CORPUS[sample];
+            let mut document = Document::new(usfm.as_str()).unwrap();
+            for &(position, length, fragment) in &edits {
+                let text = document.usfm();
+                let start = floor_char_boundary(text, (text.len() as f64 * position) as usize);
+                let end = floor_char_boundary(text, start + length);
+                document.replace(start..end, FRAGMENTS[fragment]).unwrap();
+            }
+            // Error recovery may build a different tree than a fresh parse
+            // would, so the chapters are compared with the whole tree converted
+            let usj = usj_from_tree(document.tree(), document.usfm()).unwrap();
+            prop_assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&document.usj()).unwrap(),
+                serde_json::from_str::<serde_json::Value>(&usj).unwrap()
+            );
+        }
+    }
+}
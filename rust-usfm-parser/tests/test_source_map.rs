@@ -0,0 +1,84 @@
+// test_source_map.rs
+
+use rust_usfm::parser::USFMParser;
+use rust_usfm::source_map::{usj_at, SourceMap};
+use rust_usfm::usj_generator::{write_usj_with, UsjFormat, UsjOptions};
+use serde_json::Value;
+use std::fs;
+
+const USFM: &str = "\\id PHP Philippians\n\\c 1\n\\p\n\\v 1 Paul \\w grace|lemma=\"grace\"\\w* here\\f + \\ft note\\f*\n\\v 2 Grace to you\n";
+
+fn convert(usfm: &str, options: UsjOptions) -> (Value, SourceMap) {
+    let tree = USFMParser::new().unwrap().parse_usfm(usfm).unwrap();
+    let mut usj = Vec::new();
+    let source_map = write_usj_with(&tree, usfm, &mut usj, options).unwrap();
+    (serde_json::from_slice(&usj).unwrap(), source_map)
+}
+
+fn with_source_map() -> UsjOptions<'static> {
+    UsjOptions {
+        source_map: true,
+        ..UsjOptions::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_match_source() {
+        let usfm = fs::read_to_string("../tests/basic/attributes/origin.usfm").unwrap();
+        let (usj, source_map) = convert(&usfm, with_source_map());
+        assert!(!source_map.is_empty());
+        for (path, span) in source_map.iter() {
+            let source = &usfm[span.start_byte..span.end_byte];
+            match usj_at(&usj, path).unwrap() {
+                Value::String(text) => assert_eq!(text, &source.replace('\n', " ")),
+                element => {
+                    let marker = element["marker"].as_str().unwrap();
+                    assert!(source.starts_with(&format!("\\{}", marker)), "{}", source);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_both_ways() {
+        let (usj, source_map) = convert(USFM, with_source_map());
+        let path = source_map.path_at(USFM.find("grace|").unwrap()).unwrap();
+        assert_eq!(usj_at(&usj, path).unwrap(), "grace");
+        let char_path = &path[..path.len() - 1];
+        assert_eq!(usj_at(&usj, char_path).unwrap()["lemma"], "grace");
+        let span = source_map.span(char_path).unwrap();
+        assert_eq!(span.start_byte, USFM.find("\\w grace").unwrap());
+        assert_eq!((span.start_row, span.start_column), (3, 10));
+
+        let note = source_map.path_at(USFM.find("note").unwrap()).unwrap();
+        assert_eq!(
+            usj_at(&usj, &note[..note.len() - 2]).unwrap()["type"],
+            "note"
+        );
+        assert_eq!(source_map.path_at(USFM.len() + 1), None);
+    }
+
+    #[test]
+    fn test_inline_spans() {
+        let options = UsjOptions {
+            format: UsjFormat::Compact,
+            spans: true,
+            source_map: true,
+            ..UsjOptions::default()
+        };
+        let (usj, source_map) = convert(USFM, options);
+        let verse = &usj["content"][2]["content"][0];
+        assert_eq!(verse["marker"], "v");
+        let span = source_map.span(&[2, 0]).unwrap();
+        assert_eq!(verse["span"]["start"], span.start_byte);
+        assert_eq!(verse["span"]["end"], span.end_byte);
+        assert_eq!(verse["span"]["row"], 3);
+
+        let (plain, _) = convert(USFM, UsjOptions::default());
+        assert!(plain["content"][2]["content"][0].get("span").is_none());
+    }
+}
@@ -0,0 +1,136 @@
+// test_stylesheet.rs
+
+use rust_usfm::parser::Filter;
+use rust_usfm::stylesheet::{MarkerCatalog, StyleType, USFM_CATALOG};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_stylesheet_classifies_markers() {
+        assert_eq!(USFM_CATALOG.style_type("p"), Some(StyleType::Paragraph));
+        assert_eq!(USFM_CATALOG.style_type("\\nd"), Some(StyleType::Character));
+        assert_eq!(USFM_CATALOG.style_type("+wj*"), Some(StyleType::Character));
+        assert_eq!(USFM_CATALOG.style_type("f"), Some(StyleType::Note));
+        assert_eq!(USFM_CATALOG.style_type("qt2-s"), Some(StyleType::Milestone));
+        assert!(USFM_CATALOG.is_note_content("ft"));
+        assert!(!USFM_CATALOG.is_note_content("nd"));
+        assert!(!USFM_CATALOG.contains("xyz"));
+    }
+
+    #[test]
+    fn test_numbered_markers_fall_back_to_base() {
+        assert_eq!(USFM_CATALOG.get("q5").unwrap().marker, "q");
+        assert_eq!(USFM_CATALOG.get("toc").unwrap().marker, "toc1");
+        assert_eq!(USFM_CATALOG.get("s1").unwrap().base, "s");
+        assert_eq!(USFM_CATALOG.opener_of("esbe").unwrap().marker, "esb");
+    }
+
+    #[test]
+    fn test_default_attributes() {
+        assert_eq!(USFM_CATALOG.default_attribute("w"), Some("lemma"));
+        assert_eq!(USFM_CATALOG.default_attribute("rb"), Some("gloss"));
+        assert_eq!(USFM_CATALOG.default_attribute("xt"), Some("href"));
+        assert_eq!(USFM_CATALOG.default_attribute("fig"), Some("alt"));
+        assert_eq!(USFM_CATALOG.default_attribute("qt-s"), Some("who"));
+        assert_eq!(USFM_CATALOG.default_attribute("nd"), None);
+    }
+
+    #[test]
+    fn test_custom_stylesheet_overrides_and_extends() {
+        let custom = r#"
+# custom.sty
+\Marker nd
+\OccursUnder p q1
+
+\marker xyz
+\Endmarker xyz*
+\StyleType Character
+\Attributes ?level
+"#;
+        let mut catalog = MarkerCatalog::usfm();
+        catalog.extend_from_stylesheet(custom).unwrap();
+
+        let nd = catalog.get("nd").unwrap();
+        assert_eq!(nd.occurs_under, vec!["p", "q1"]);
+        assert_eq!(nd.style_type, Some(StyleType::Character));
+        assert!(catalog.is_character("xyz"));
+        assert_eq!(catalog.default_attribute("xyz"), Some("level"));
+        assert!(!USFM_CATALOG.contains("xyz"));
+    }
+
+    #[test]
+    fn test_invalid_stylesheet_is_rejected() {
+        assert!(MarkerCatalog::from_stylesheet("\\StyleType Character").is_err());
+        assert!(MarkerCatalog::from_stylesheet("\\Marker nd\n\\StyleType Bold").is_err());
+    }
+
+    #[test]
+    fn test_filters_come_from_catalog() {
+        let characters = Filter::Characters.value();
+        assert!(characters.contains(&"nd"));
+        assert!(!characters.contains(&"ft"));
+        assert!(Filter::Notes.value().contains(&"ft"));
+        assert!(Filter::Paragraphs.value().contains(&"tc"));
+        assert!(Filter::Titles.value().contains(&"mt"));
+        assert!(Filter::BookHeaders.value().contains(&"toc"));
+        assert!(Filter::Comments.value().contains(&"rem"));
+        assert!(!Filter::BookHeaders.value().contains(&"id"));
+    }
+
+    #[test]
+    fn test_filter_lists() {
+        let expected: [(Filter, &[&str]); 6] = [
+            (
+                Filter::BookHeaders,
+                &[
+                    "h", "ib", "ide", "ie", "iex", "ili", "im", "imi", "imq", "imt", "imte", "io",
+                    "iot", "ip", "ipi", "ipq", "ipr", "iq", "is", "toc", "toca", "usfm",
+                ],
+            ),
+            (
+                Filter::Titles,
+                &["cd", "cl", "d", "mr", "ms", "mt", "mte", "r", "s", "sd", "sp", "sr"],
+            ),
+            (Filter::Comments, &["lit", "rem", "restore", "sts"]),
+            (
+                Filter::Paragraphs,
+                &[
+                    "b", "cls", "lf", "lh", "li", "lim", "m", "mi", "nb", "p", "pb", "pc", "ph",
+                    "pi", "pm", "pmc", "pmo", "pmr", "po", "pr", "q", "qa", "qc", "qd", "qm", "qr",
+                    "tc", "tcc", "tcr", "th", "thc", "thr", "tr",
+                ],
+            ),
+            (
+                Filter::Characters,
+                &[
+                    "add", "addpn", "bd", "bdit", "bk", "dc", "em", "fm", "ior", "iqt", "it", "jmp",
+                    "k", "lik", "litl", "liv", "nd", "ndx", "no", "ord", "pn", "png", "pro", "qac",
+                    "qs", "qt", "rb", "rq", "sc", "sig", "sls", "sup", "tl", "w", "wa", "wg", "wh",
+                    "wj",
+                ],
+            ),
+            (
+                Filter::Notes,
+                &[
+                    "ef", "ex", "f", "fdc", "fe", "fk", "fl", "fp", "fq", "fqa", "fr", "ft", "fv",
+                    "fw", "x", "xdc", "xk", "xnt", "xo", "xop", "xot", "xq", "xt", "xta",
+                ],
+            ),
+        ];
+        for (filter, markers) in expected {
+            assert_eq!(filter.value(), markers, "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn test_filters_read_custom_catalog() {
+        let mut catalog = MarkerCatalog::usfm();
+        catalog
+            .extend_from_stylesheet("\\Marker zgloss\n\\Endmarker zgloss*\n\\StyleType Character\n")
+            .unwrap();
+        assert!(Filter::Characters.markers_in(&catalog).contains(&"zgloss"));
+        assert!(!Filter::Characters.value().contains(&"zgloss"));
+    }
+}
@@ -0,0 +1,158 @@
+// test_text_checks.rs
+
+use rust_usfm::lint::LintConfig;
+use rust_usfm::stylesheet::MarkerCatalog;
+use rust_usfm::text_checks::check_text;
+use rust_usfm::validator::Validator;
+use rust_usfm::verse_text::extract_verse_text;
+
+const QUOTES: [(char, char); 2] = [('\u{201c}', '\u{201d}'), ('\u{2018}', '\u{2019}')];
+
+fn messages(usfm: &str) -> Vec<(&'static str, String)> {
+    check_text(usfm, &MarkerCatalog::usfm(), &QUOTES)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.message))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_verse_text() {
+        let usfm = "\\id GEN\n\\c 1\n\\s The beginning\n\\p\n\\v 1 In the \\nd Lord\\nd*\\f + \\ft note\\f* we \\w trust|lemma=\"x\"\\w*.\n\\q1\n\\v 2-3 Poetry\n";
+        let verses = extract_verse_text(usfm, &MarkerCatalog::usfm());
+        let texts: Vec<_> = verses
+            .iter()
+            .map(|verse| (verse.reference(), verse.text.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("1:1".to_string(), "In the Lord we trust.\n"),
+                ("1:2-3".to_string(), "Poetry\n")
+            ]
+        );
+        let offset = verses[0].text.find("trust").unwrap();
+        assert_eq!(verses[0].source_byte(offset), usfm.find("trust").unwrap());
+    }
+
+    #[test]
+    fn test_repeated_word() {
+        let usfm =
+            "\\c 1\n\\p\n\\v 1 Let every soul be subject to \\add to\\add* the authorities.\n";
+        let diagnostics = check_text(usfm, &MarkerCatalog::usfm(), &QUOTES);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Repeated word \"to\" in 1:1");
+        assert_eq!(
+            &usfm[diagnostics[0].start_byte..diagnostics[0].end_byte],
+            "to"
+        );
+        assert_eq!((diagnostics[0].row, diagnostics[0].column), (2, 39));
+
+        assert_eq!(
+            messages("\\c 1\n\\p\n\\v 1 Had had, 7 7 and To to.\n").len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_punctuation_spacing() {
+        assert_eq!(
+            messages("\\c 1\n\\p\n\\v 1 He said , go away.Then he left;quickly . . . gone.\n"),
+            vec![
+                (
+                    "punctuation-spacing",
+                    "Space before \",\" in 1:1".to_string()
+                ),
+                (
+                    "punctuation-spacing",
+                    "Missing space after \".\" in 1:1".to_string()
+                ),
+                (
+                    "punctuation-spacing",
+                    "Missing space after \";\" in 1:1".to_string()
+                ),
+            ]
+        );
+        assert!(messages("\\c 1\n\\p\n\\v 1 See 3:16, e.g. this (a).\n").is_empty());
+    }
+
+    #[test]
+    fn test_unmatched_punctuation() {
+        assert_eq!(
+            messages(
+                "\\c 1\n\\p\n\\v 1 He said, “Don’t go (yet.\n\\v 2 It’s the disciples’ house.”)\n"
+            ),
+            vec![
+                (
+                    "unmatched-punctuation",
+                    "Opening ( is not closed".to_string()
+                ),
+                (
+                    "unmatched-punctuation",
+                    "Closing ) has no opening (".to_string()
+                ),
+            ]
+        );
+        // A quotation reopened in each of its paragraphs is one quotation
+        let usfm =
+            "\\c 1\n\\p\n\\v 1 He said, “First.\n\\p\n\\v 2 “Second.”\n\\v 3 End’ of it” here.\n";
+        assert_eq!(
+            messages(usfm),
+            vec![(
+                "unmatched-punctuation",
+                "Closing ” has no opening “".to_string()
+            )]
+        );
+        let diagnostics = check_text(usfm, &MarkerCatalog::usfm(), &[('«', '»')]);
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_mixed_script_and_whitespace() {
+        assert_eq!(
+            messages(
+                "\\c 1\n\\p\n\\v 1  Тhe word \\f + \\ft note\\f* is 漢字かな.\n\\v 2 End.  \n"
+            ),
+            vec![
+                (
+                    "verse-whitespace",
+                    "Verse 1:1 starts with whitespace".to_string()
+                ),
+                (
+                    "mixed-script",
+                    "Word \"Тhe\" mixes Cyrillic and Latin letters".to_string()
+                ),
+                (
+                    "verse-whitespace",
+                    "Verse 1:2 ends with extra whitespace".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quotes_config() {
+        let config = LintConfig::from_toml("quotes = [\"«»\", \"\\\"\\\"\"]\n").unwrap();
+        assert_eq!(config.quotes, vec![('«', '»'), ('"', '"')]);
+        assert_eq!(LintConfig::default().quotes, QUOTES.to_vec());
+        assert!(LintConfig::from_toml("quotes = [\"«\"]\n").is_err());
+
+        let path = std::env::temp_dir().join("rust-usfm-test-quotes.toml");
+        std::fs::write(&path, "quotes = [\"«»\"]\n").unwrap();
+        let mut validator = Validator::new().unwrap();
+        validator.load_lint_config(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 He said “go” and «stay.\n";
+        let unmatched: Vec<_> = validator
+            .lint(usfm)
+            .unwrap()
+            .into_iter()
+            .filter(|diagnostic| diagnostic.rule == "unmatched-punctuation")
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        assert_eq!(unmatched, vec!["Opening « is not closed"]);
+    }
+}
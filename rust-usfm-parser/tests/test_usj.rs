@@ -29,7 +29,6 @@ fn get_types(element: &Value) -> Vec<String> {
             // Check for marker
             match obj.get("marker") {
                 Some(Value::String(marker)) => {
-                    println!("Found marker in obj: {}", marker); // Added debug print
                     types.push(marker.to_string());
                 }
                 _ => (),
@@ -42,7 +41,6 @@ fn get_types(element: &Value) -> Vec<String> {
                     Some(Value::String(_)) => types.push("va".to_string()),
                     _ => (),
                 }
-                print!("'{:?}'", types)  //Added for debug
             }
 
             // Check for pubnumber
@@ -52,7 +50,6 @@ fn get_types(element: &Value) -> Vec<String> {
                     Some(Value::String(_)) => types.push("vp".to_string()),
                     _ => (),
                 }
-                print!("'{:?}'", types)     //Added for debug
             }
 
             // Check for category
@@ -198,7 +195,6 @@ mod tests {
 
             // Get all markers from input file
             let all_markers_in_input = find_all_markers(&file_path, false, true)?;
-            println!("{:?}", all_markers_in_input);     //Added for debug
 
             // Generate USJ and get all types
             let usj_string = usj_generator(&usfm_content)?;
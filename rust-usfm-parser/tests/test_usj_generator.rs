@@ -0,0 +1,117 @@
+// test_usj_generator.rs
+
+use rust_usfm::parser::USFMParser;
+use rust_usfm::stylesheet::MarkerCatalog;
+use rust_usfm::usj_generator::{usj_from_tree, write_usj, write_usj_with, UsjFormat, UsjOptions};
+use serde_json::Value;
+use std::fs;
+
+fn usj(usfm: &str) -> Value {
+    let tree = USFMParser::new().unwrap().parse_usfm(usfm).unwrap();
+    serde_json::from_str(&usj_from_tree(&tree, usfm).unwrap()).unwrap()
+}
+
+// A custom.sty of the kind a Paratext project ships
+const CUSTOM_STY: &str = "\\Marker zgloss\n\\Endmarker zgloss*\n\\StyleType Character\n\n\\Marker zhead\n\\StyleType Paragraph\n\n\\Marker w\n\\Attributes ?strong ?lemma\n";
+
+fn usj_with_catalog(usfm: &str, catalog: &MarkerCatalog) -> Value {
+    let tree = USFMParser::new().unwrap().parse_usfm(usfm).unwrap();
+    let options = UsjOptions {
+        catalog,
+        ..UsjOptions::default()
+    };
+    let mut usj = Vec::new();
+    write_usj_with(&tree, usfm, &mut usj, options).unwrap();
+    serde_json::from_slice(&usj).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_node_converted_once() {
+        let usj = usj("\\id GEN\n\\c 1\n\\p\n\\v 1 In the beginning\n\\v 2 The earth\n");
+        let content = usj["content"].as_array().unwrap();
+        assert_eq!(content.len(), 3);
+        assert_eq!(content[1]["sid"], "GEN 1");
+        assert_eq!(
+            content[2]["content"],
+            serde_json::json!([
+                {"type": "verse", "marker": "v", "number": "1", "sid": "GEN 1:1"},
+                "In the beginning ",
+                {"type": "verse", "marker": "v", "number": "2", "sid": "GEN 1:2"},
+                "The earth ",
+            ])
+        );
+    }
+
+    #[test]
+    fn test_chars_and_notes() {
+        let usj = usj(
+            "\\id PHP\n\\c 1\n\\p\n\\v 1 Paul \\w grace|lemma=\"grace\"\\w* here\\f + \\fr 1.1 \\ft note\\f*\n",
+        );
+        let para = &usj["content"][2]["content"];
+        assert_eq!(para[2]["marker"], "w");
+        assert_eq!(para[2]["lemma"], "grace");
+        assert_eq!(para[2]["content"], serde_json::json!(["grace"]));
+        assert_eq!(para[4]["type"], "note");
+        assert_eq!(para[4]["caller"], "+");
+        assert_eq!(para[4]["content"][1]["marker"], "ft");
+    }
+
+    #[test]
+    fn test_pretty_output_matches_fixtures() {
+        let mut parser = USFMParser::new().unwrap();
+        for sample in ["basic/attributes", "basic/footnote", "advanced/milestones"] {
+            let usfm = fs::read_to_string(format!("../tests/{}/origin.usfm", sample)).unwrap();
+            let expected = fs::read_to_string(format!("../tests/{}/origin.json", sample)).unwrap();
+            let tree = parser.parse_usfm(&usfm).unwrap();
+            assert_eq!(usj_from_tree(&tree, &usfm).unwrap().trim(), expected.trim());
+        }
+    }
+
+    #[test]
+    fn test_compact_output() {
+        let usfm = "\\id GEN\n\\c 1\n\\p\n\\v 1 \\w \"Light\"|lemma=\"light\"\\w*\n";
+        let tree = USFMParser::new().unwrap().parse_usfm(usfm).unwrap();
+        let mut usj = Vec::new();
+        write_usj(&tree, usfm, &mut usj, UsjFormat::Compact).unwrap();
+        assert_eq!(
+            String::from_utf8(usj).unwrap(),
+            concat!(
+                r#"{"type":"USJ","version":"3.1","content":["#,
+                r#"{"type":"book","marker":"id","code":"GEN","content":[]},"#,
+                r#"{"type":"chapter","marker":"c","number":"1","sid":"GEN 1"},"#,
+                r#"{"type":"para","marker":"p","content":["#,
+                r#"{"type":"verse","marker":"v","number":"1","sid":"GEN 1:1"},"#,
+                r#"{"type":"char","marker":"w","lemma":"light","content":["\"Light\""]}]}]}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_custom_stylesheet_markers() {
+        let usfm = "\\id GEN\n\\c 1\n\\zhead A heading\n\\p\n\\v 1 In \\zgloss the beginning\\zgloss* was \\w word|H7225\\w*\n";
+        let mut catalog = MarkerCatalog::usfm();
+        catalog.extend_from_stylesheet(CUSTOM_STY).unwrap();
+        let custom = usj_with_catalog(usfm, &catalog);
+        let content = &custom["content"];
+        assert_eq!(
+            content[2],
+            serde_json::json!({"type": "para", "marker": "zhead", "content": ["A heading "]})
+        );
+        let para = &content[3]["content"];
+        assert_eq!(
+            para[2],
+            serde_json::json!({"type": "char", "marker": "zgloss", "content": ["the beginning"]})
+        );
+        assert_eq!(para[4]["strong"], "H7225");
+
+        // The standard catalog knows neither marker, nor the new default attribute
+        let plain = usj(usfm);
+        assert_eq!(plain["content"][2]["type"], "ms");
+        assert_eq!(plain["content"][3]["content"][2]["type"], "ms");
+        assert_eq!(plain["content"][3]["content"][4]["lemma"], "H7225");
+    }
+}
@@ -0,0 +1,157 @@
+// test_usj_schema.rs
+
+use rust_usfm::diagnostics::UsjError;
+use rust_usfm::parser::USFMParser;
+use rust_usfm::usj_generator::usj_from_tree;
+use rust_usfm::validator::Validator;
+use serde_json::{json, Value};
+use std::fs;
+
+fn minimal() -> Value {
+    json!({
+        "type": "USJ",
+        "version": "3.1",
+        "content": [
+            {"type": "book", "marker": "id", "code": "GEN", "content": []},
+            {"type": "chapter", "marker": "c", "number": "1", "sid": "GEN 1"},
+            {
+                "type": "para",
+                "marker": "p",
+                "content": [
+                    {"type": "verse", "marker": "v", "number": "1", "sid": "GEN 1:1"},
+                    "In the beginning ",
+                    {"type": "char", "marker": "nd", "content": ["God"]},
+                    {"type": "note", "marker": "f", "caller": "+", "content": [
+                        {"type": "char", "marker": "ft", "content": ["A note"]}
+                    ]}
+                ]
+            }
+        ]
+    })
+}
+
+fn errors(usj: &Value) -> Vec<UsjError> {
+    Validator::new()
+        .unwrap()
+        .is_valid_usj(&usj.to_string())
+        .unwrap()
+}
+
+fn paths(usj: &Value) -> Vec<String> {
+    errors(usj).into_iter().map(|error| error.path).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_usj() {
+        assert_eq!(errors(&minimal()), vec![]);
+
+        let usj = fs::read_to_string("../tests/basic/character/origin.json").unwrap();
+        assert!(Validator::new()
+            .unwrap()
+            .is_valid_usj(&usj)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_required_fields_per_type() {
+        let mut usj = minimal();
+        usj["content"][2]["content"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("number");
+        assert_eq!(
+            errors(&usj),
+            vec![UsjError {
+                path: "/content/2/content/0".to_string(),
+                message: "\"number\" is a required property".to_string(),
+            }]
+        );
+
+        let mut usj = minimal();
+        usj["content"][1].as_object_mut().unwrap().remove("sid");
+        usj["content"][2]["content"][3]
+            .as_object_mut()
+            .unwrap()
+            .remove("caller");
+        assert_eq!(paths(&usj), vec!["/content/1", "/content/2/content/3"]);
+    }
+
+    #[test]
+    fn test_markers_are_enumerated() {
+        let mut usj = minimal();
+        usj["content"][2]["marker"] = json!("s5");
+        usj["content"][2]["content"][2]["marker"] = json!("p");
+        let found = errors(&usj);
+        assert_eq!(
+            found
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "At /content/2/content/2/marker: \"p\" is not one of the allowed values",
+                "At /content/2/marker: \"s5\" is not one of the allowed values",
+            ]
+        );
+
+        // Projects may define their own z markers
+        let mut usj = minimal();
+        usj["content"][2]["marker"] = json!("zpara");
+        usj["content"][2]["content"][2]["marker"] = json!("zwords");
+        assert_eq!(errors(&usj), vec![]);
+    }
+
+    #[test]
+    fn test_book_without_id() {
+        // Without a book code the sids start with a space, as in the test suite
+        let usfm = "\\id \n\\c 1\n\\p\n\\v 1 In the beginning\n";
+        let tree = USFMParser::new().unwrap().parser.parse(usfm, None).unwrap();
+        let usj: Value = serde_json::from_str(&usj_from_tree(&tree, usfm).unwrap()).unwrap();
+        assert_eq!(usj["content"][1]["sid"], " 1");
+        assert_eq!(usj["content"][2]["content"][0]["sid"], " 1:1");
+        assert_eq!(paths(&usj), vec!["/content/0/code"]);
+
+        let usj = fs::read_to_string("../tests/mandatory/id/origin.json").unwrap();
+        assert_eq!(errors(&serde_json::from_str(&usj).unwrap()), vec![]);
+
+        let mut usj = minimal();
+        usj["content"][1]["sid"] = json!("");
+        assert_eq!(paths(&usj), vec!["/content/1/sid"]);
+    }
+
+    #[test]
+    fn test_unfolding_word_chunks_are_rejected() {
+        // \s5 marks a chunk break in unfoldingWord texts. It is not a USFM 3.1
+        // marker, so those test-suite files are invalid USJ on purpose.
+        let usj = fs::read_to_string("../tests/usfmjsTests/1ch_verse_span/origin.json").unwrap();
+        let found = errors(&serde_json::from_str(&usj).unwrap());
+        assert!(!found.is_empty());
+        assert!(found
+            .iter()
+            .all(|error| error.message == "\"s5\" is not one of the allowed values"));
+    }
+
+    #[test]
+    fn test_values_and_types() {
+        let mut usj = minimal();
+        usj["version"] = json!("2");
+        usj["content"][0]["code"] = json!("Gen");
+        usj["content"][1]["type"] = json!("chap");
+        assert_eq!(
+            paths(&usj),
+            vec!["/content/0/code", "/content/1/type", "/version"]
+        );
+
+        assert_eq!(paths(&json!({"type": "USJ", "version": "3.1"})), vec!["/"]);
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let mut validator = Validator::new().unwrap();
+        assert!(validator.is_valid_usj("{\"type\": ").is_err());
+    }
+}
@@ -0,0 +1,109 @@
+// test_usj_semantics.rs
+
+use rust_usfm::validator::Validator;
+use serde_json::{json, Value};
+
+fn document(content: Value) -> Value {
+    json!({"type": "USJ", "version": "3.1", "content": content})
+}
+
+fn book_and_chapter() -> Vec<Value> {
+    vec![
+        json!({"type": "book", "marker": "id", "code": "GEN", "content": []}),
+        json!({"type": "chapter", "marker": "c", "number": "1", "sid": "GEN 1"}),
+    ]
+}
+
+fn problems(usj: &Value) -> Vec<String> {
+    Validator::new()
+        .unwrap()
+        .validate_usj_semantics(&usj.to_string())
+        .unwrap()
+        .iter()
+        .map(|error| error.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consistent_usj() {
+        let mut content = book_and_chapter();
+        content.push(json!({"type": "para", "marker": "p", "content": [
+            {"type": "verse", "marker": "v", "number": "1", "sid": "GEN 1:1"},
+            "In the beginning",
+            {"type": "note", "marker": "f", "caller": "+", "content": [
+                {"type": "char", "marker": "ft", "content": ["A note"]}
+            ]},
+            {"type": "verse", "marker": "v", "number": "2-3", "sid": "GEN 1:2-3"},
+            {"type": "ms", "marker": "qt-s", "sid": "qt1"}
+        ]}));
+        assert_eq!(problems(&document(json!(content))), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_verse_outside_chapter() {
+        let usj = document(json!([
+            {"type": "book", "marker": "id", "code": "GEN", "content": []},
+            {"type": "para", "marker": "p", "content": [
+                {"type": "verse", "marker": "v", "number": "1", "sid": "GEN 1:1"}
+            ]}
+        ]));
+        assert_eq!(
+            problems(&usj),
+            vec!["At /content/1/content/0: Verse 1 is not inside a chapter"]
+        );
+    }
+
+    #[test]
+    fn test_sids_follow_book_and_chapter() {
+        let mut content = book_and_chapter();
+        content[1]["sid"] = json!("EXO 1");
+        content.push(json!({"type": "para", "marker": "p", "content": [
+            {"type": "verse", "marker": "v", "number": "1", "sid": "GEN 2:1"},
+            {"type": "verse", "marker": "v", "number": "2", "sid": "GEN 001:002"}
+        ]}));
+        assert_eq!(
+            problems(&document(json!(content))),
+            vec![
+                "At /content/1/sid: sid \"EXO 1\" does not match the book code GEN",
+                "At /content/2/content/0/sid: sid \"GEN 2:1\" should be \"GEN 1:1\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_marker_kinds() {
+        let mut content = book_and_chapter();
+        content.push(json!({"type": "para", "marker": "nd", "content": [
+            {"type": "char", "marker": "q1", "content": ["text"]},
+            {"type": "ms", "marker": "wj", "sid": "x"}
+        ]}));
+        assert_eq!(
+            problems(&document(json!(content))),
+            vec![
+                "At /content/2/marker: \\nd is a character marker and cannot be used as para",
+                "At /content/2/content/0/marker: \\q1 is a paragraph marker and cannot be used as char",
+                "At /content/2/content/1/marker: \\wj is a character marker and cannot be used as ms",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_note_without_caller() {
+        let mut content = book_and_chapter();
+        content.push(json!({"type": "para", "marker": "p", "content": [
+            {"type": "note", "marker": "f", "content": []},
+            {"type": "note", "marker": "x", "caller": " ", "content": []}
+        ]}));
+        assert_eq!(
+            problems(&document(json!(content))),
+            vec![
+                "At /content/2/content/0: Note \\f has no caller",
+                "At /content/2/content/1: Note \\x has no caller",
+            ]
+        );
+    }
+}
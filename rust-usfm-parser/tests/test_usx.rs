@@ -0,0 +1,149 @@
+// test_usx.rs
+
+use rust_usfm::relaxng::Schema;
+use rust_usfm::validator::Validator;
+use std::fs;
+
+const MINIMAL: &str = r#"<usx version="3.0">
+  <book code="GEN" style="id" />
+  <chapter number="1" style="c" sid="GEN 1" />
+  <para style="p">
+    <verse number="1" style="v" sid="GEN 1:1" />In the beginning<verse eid="GEN 1:1" /></para>
+  <chapter eid="GEN 1" />
+</usx>"#;
+
+fn errors(usx: &str) -> Vec<(u32, u32, String)> {
+    Validator::new()
+        .unwrap()
+        .validate_usx(usx)
+        .unwrap()
+        .into_iter()
+        .map(|error| (error.line, error.column, error.message))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_usx() {
+        let mut validator = Validator::new().unwrap();
+        assert!(validator.is_valid_usx(MINIMAL).unwrap());
+        assert_eq!(validator.get_message(), "");
+
+        let usx = fs::read_to_string("../tests/basic/character/origin.xml").unwrap();
+        assert!(validator.is_valid_usx(&usx).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_style_value() {
+        let usx = MINIMAL.replace("<para style=\"p\">", "<para style=\"s5\">");
+        assert_eq!(
+            errors(&usx),
+            vec![(
+                4,
+                9,
+                "Invalid value \"s5\" for attribute style of <para>".to_string()
+            )]
+        );
+
+        let mut validator = Validator::new().unwrap();
+        assert!(!validator.is_valid_usx(&usx).unwrap());
+        assert_eq!(
+            validator.get_message(),
+            "At line 4, column 9: Invalid value \"s5\" for attribute style of <para>"
+        );
+    }
+
+    #[test]
+    fn test_unknown_attribute_and_missing_attribute() {
+        let usx = MINIMAL
+            .replace(
+                "<para style=\"p\">",
+                "<para style=\"p\" status=\"unknown\">",
+            )
+            .replace(
+                "<book code=\"GEN\" style=\"id\" />",
+                "<book style=\"id\" />",
+            );
+        assert_eq!(
+            errors(&usx),
+            vec![
+                (
+                    2,
+                    3,
+                    "Element <book> is missing required attribute code".to_string()
+                ),
+                (
+                    4,
+                    19,
+                    "Attribute status is not allowed on <para>".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_element_not_allowed() {
+        let usx = MINIMAL.replace(
+            "In the beginning",
+            "In the <book code=\"GEN\" style=\"id\" />beginning",
+        );
+        let found = errors(&usx);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].0, found[0].1), (5, 56));
+        assert!(found[0]
+            .2
+            .starts_with("Element <book> is not allowed here; expected <char>"));
+
+        let found = errors("<usx version=\"3.0\"><para style=\"p\">text</para></usx>");
+        assert_eq!(
+            found,
+            vec![(
+                1,
+                20,
+                "Element <para> is not allowed here; expected <book>".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_malformed_xml() {
+        let mut validator = Validator::new().unwrap();
+        assert!(validator.is_valid_usx("<usx><para></usx>").is_err());
+    }
+
+    #[test]
+    fn test_schema_subset() {
+        let schema = Schema::parse(
+            r#"<grammar xmlns="http://relaxng.org/ns/structure/1.0"
+                datatypeLibrary="http://www.w3.org/2001/XMLSchema-datatypes">
+              <start><element name="list"><oneOrMore><ref name="item"/></oneOrMore></element></start>
+              <define name="item">
+                <element name="item">
+                  <optional><attribute name="n"><data type="integer"/></attribute></optional>
+                  <choice><value>a</value><value>b</value></choice>
+                </element>
+              </define>
+            </grammar>"#,
+        )
+        .unwrap();
+        assert!(schema
+            .validate("<list><item n=\"1\">a</item><item>b</item></list>")
+            .unwrap()
+            .is_empty());
+        let found = schema
+            .validate("<list><item n=\"x\">c</item></list>")
+            .unwrap();
+        let messages: Vec<_> = found.iter().map(|error| error.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Invalid value \"x\" for attribute n of <item>",
+                "Text \"c\" is not allowed in <item>"
+            ]
+        );
+        assert!(!schema.validate("<list/>").unwrap().is_empty());
+    }
+}